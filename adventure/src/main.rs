@@ -1,35 +1,25 @@
-use assets_manager::{asset::Png, AssetCache};
+use assets_manager::AssetCache;
+use engine::controls::{Controls, ControlsMenu};
+use engine::geom::Vec2;
+use engine::hud::{Align, Hud};
+use engine::input::InputState;
+use engine::registry::LevelRegistry;
+use engine::replay::{Playback, Recording};
+use engine::sheets::Sheets;
+use engine::split::{Split, SplitScreen, View};
+use engine::{Args, DT};
 use frenderer::{
-    input::{Input, Key},
-    sprites::{Camera2D, SheetRegion, Transform},
-    wgpu, Immediate,
+    sprites::{SheetRegion, Transform},
+    Immediate,
 };
-use image::error::ParameterErrorKind;
-use rand::Rng;
-mod geom;
-mod grid;
-use geom::*;
+use std::path::PathBuf;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum EntityType {
-    Player1,
-    Player2,
-    Enemy,
-    P_Projectile,
-    E_Projectile,
-    // which level, grid x in dest level, grid y in dest level
-    #[allow(dead_code)]
-    Door(String, u16, u16),
-}
-
-#[derive(Clone, Copy, Debug)]
-struct TileData {
-    solid: bool,
-    sheet_region: SheetRegion,
-}
+mod world;
+use world::*;
 
 const PLAYER: SheetRegion = SheetRegion::rect(315, 100, 16, 16);
 
+#[allow(dead_code)]
 const PLAYER2: SheetRegion = SheetRegion::rect(315, 100, 16, 16);
 
 const ENEMY: SheetRegion = SheetRegion::rect(533 + 16, 39, 16, 16);
@@ -40,66 +30,22 @@ const E_PROJECTILE: SheetRegion = SheetRegion::rect(525, 43, 7, 7);
 
 const HEART: SheetRegion = SheetRegion::rect(525, 35, 8, 8);
 
-#[derive(Clone, Debug)]
-struct Entity {
-    alive: bool,
-    pos: Vec2,
-    dir: f32,
-    etype: EntityType,
-}
+const DOOR: SheetRegion = SheetRegion::rect(611, 39, 16, 16);
 
-// struct Projectile {
-//     alive: bool,
-//     pos: Vec2,
-//     dir: f32,
-//     shooter: ProjectileType,
-// }
+// a wall tile, painted black for the line between the halves of a split screen, or
+// whatever color the HUD's bars need
+const SOLID: SheetRegion = SheetRegion::rect(419, 61, 16, 16);
+const DIVIDER: SheetRegion = SOLID.with_colormod([0, 0, 0, 255]).with_depth(2);
 
-// enum
+// the world's sprites go behind the divider and the HUD (depths 0 and 1)
+const DEPTH: u16 = 3;
 
-fn dir_to_vec2(dir: f32) -> Vec2 {
-    Vec2 {
-        x: f32::cos(dir),
-        y: f32::sin(dir),
-    }
-}
-fn vec2_to_dir(vec2: Vec2) -> f32 {
-    vec2.y.atan2(vec2.x)
-}
+// the reload bars under the hearts
+const RELOAD: [u8; 4] = [240, 200, 60, 255];
 
 impl Entity {
-    pub fn rect(&self) -> Rect {
-        Rect {
-            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
-            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
-            w: TILE_SZ as u16 - 4,
-            h: TILE_SZ as u16 - 4,
-        }
-    }
-    pub fn circle(&self) -> Circle {
-        Circle {
-            x: self.pos.x,
-            y: self.pos.y,
-            r: TILE_SZ as f32 / 2.0,
-        }
-    }
-    pub fn shape_rect(&self) -> Shape {
-        Shape::Rect(Rect {
-            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
-            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
-            w: TILE_SZ as u16 - 4,
-            h: TILE_SZ as u16 - 4,
-        })
-    }
-    pub fn shape_circle(&self) -> Shape {
-        Shape::Circle(Circle {
-            x: self.pos.x,
-            y: self.pos.y,
-            r: TILE_SZ as f32 / 2.0,
-        })
-    }
     pub fn transform(&self) -> Transform {
-        if self.etype == EntityType::P_Projectile || self.etype == EntityType::E_Projectile {
+        if self.etype == EntityType::PlayerProjectile || self.etype == EntityType::EnemyProjectile {
             Transform {
                 x: self.pos.x,
                 y: self.pos.y,
@@ -107,6 +53,14 @@ impl Entity {
                 h: 4,
                 rot: self.dir,
             }
+        } else if self.etype == EntityType::Burst {
+            Transform {
+                x: self.pos.x,
+                y: self.pos.y,
+                w: 8,
+                h: 8,
+                rot: 0.0,
+            }
         } else {
             Transform {
                 x: self.pos.x,
@@ -122,641 +76,295 @@ impl Entity {
             EntityType::Player1 => PLAYER,
             EntityType::Player2 => PLAYER,
             EntityType::Enemy => ENEMY,
-            EntityType::P_Projectile => P_PROJECTILE,
-            EntityType::E_Projectile => E_PROJECTILE,
-            _ => panic!("can't draw doors"),
+            EntityType::PlayerProjectile => P_PROJECTILE,
+            EntityType::EnemyProjectile => E_PROJECTILE,
+            EntityType::Burst => P_PROJECTILE,
+            EntityType::Door(..) => DOOR,
         }
-        .with_depth(1)
+        .with_depth(DEPTH)
     }
 }
-mod level;
-use level::Level;
+
 struct Game {
     assets: AssetCache,
-    current_level: usize,
-    levels: Vec<Level>,
-    players: Vec<Entity>,
-    enemies: Vec<Entity>,
-    bounce: Vec<usize>,
-    p_projectiles: Vec<Entity>,
-    e_projectiles: Vec<Entity>,
-    e_attack_timer: f32,
-    p1_attack_timer: f32,
-    p2_attack_timer: f32,
-    p_health: Vec<u8>,
-    e_health: Vec<u8>,
+    // the tile PNG and the HUD font's glyphs, and the sprite groups drawing them
+    sheets: Sheets,
+    // pans and zooms to keep the players on screen, and with --split gives them half
+    // each when they're far apart
+    screen: SplitScreen,
+    // hearts, reload bars and banners, in sprite group 2
+    hud: Hud,
+    world: World,
+    // with --replay, where input comes from instead of the keyboard
+    playback: Option<Playback>,
+    // with --record, where to save and what's been recorded so far
+    recording: Option<(PathBuf, Recording)>,
+    // F1/F2 rebind player 1/2's keys, F9 swaps them
+    menu: ControlsMenu,
 }
 
-// Feel free to change this if you use a different tilesheet
-const TILE_SZ: usize = 16;
 const W: usize = 320;
 const H: usize = 240;
 
-// pixels per second
-const PLAYER_SPEED: f32 = 64.0;
-const ROTATE_SPEED: f32 = 0.1;
-const ENEMY_SPEED: f32 = 32.0;
-const KNOCKBACK_SPEED: f32 = 128.0;
-
-const ATTACK_MAX_TIME: f32 = 0.3;
-const ATTACK_COOLDOWN_TIME: f32 = 0.1;
-const ENEMY_ATTACK_COOLDOWN_TIME: f32 = 10.0;
-const KNOCKBACK_TIME: f32 = 0.25;
-
-const DT: f32 = 1.0 / 60.0;
-
-//necessary structs and functions for collision detection
-struct Contact {
-    a_i: usize,
-    a_r: Rect,
-    b_i: usize,
-    b_r: Rect,
-    displacement: Vec2,
-}
-
-fn gather_contacts(objs_a: &Vec<Rect>, objs_b: &Vec<Rect>) -> Vec<Contact> {
-    let mut contacts: Vec<Contact> = Vec::new();
-
-    for (a_idx, a_rect) in objs_a.iter().enumerate() {
-        for (b_idx, b_rect) in objs_b.iter().enumerate() {
-            if let Some(overlap) = a_rect.overlap(*b_rect) {
-                contacts.push(Contact {
-                    a_i: a_idx,
-                    a_r: *a_rect,
-                    b_i: b_idx,
-                    b_r: *b_rect,
-                    displacement: overlap,
-                })
-            }
-        }
-    }
-    contacts
-}
-
-fn gather_level_contacts(objs: &Vec<Rect>, level: &Level) -> Vec<Contact> {
-    let mut contacts: Vec<Contact> = Vec::new();
-
-    for (a_idx, a_rect) in objs.iter().enumerate() {
-        for (b_idx, (b_rect, tile_data)) in level.tiles_within(*a_rect).enumerate() {
-            if tile_data.solid {
-                if let Some(overlap) = a_rect.overlap(b_rect) {
-                    contacts.push(Contact {
-                        a_i: a_idx,
-                        a_r: *a_rect,
-                        b_i: b_idx,
-                        b_r: b_rect,
-                        displacement: overlap,
-                    });
-                }
-            }
-        }
-    }
-    contacts
-}
-
-impl Game {
-    fn player_level_collision_response(&mut self, contacts: &mut Vec<Contact>) {
-        for contact in contacts.iter_mut() {
-            if contact.displacement.x < contact.displacement.y {
-                contact.displacement.y = 0.0;
-            } else {
-                contact.displacement.x = 0.0;
-            }
-
-            let b_pos = contact.b_r.rect_to_pos();
-
-            if let Some(entity) = self.players.get_mut(contact.a_i) {
-                if entity.pos.x < b_pos.x {
-                    contact.displacement.x *= -1.0;
-                }
-                if entity.pos.y < b_pos.y {
-                    contact.displacement.y *= -1.0;
-                }
-
-                entity.pos += contact.displacement;
-            }
-        }
-    }
-    fn enemy_level_collision_response(&mut self, contacts: &mut Vec<Contact>) {
-        for contact in contacts.iter_mut() {
-            if contact.displacement.x < contact.displacement.y {
-                contact.displacement.y = 0.0;
-            } else {
-                contact.displacement.x = 0.0;
-            }
-
-            let b_pos = contact.b_r.rect_to_pos();
-
-            if let Some(entity) = self.enemies.get_mut(contact.a_i) {
-                if entity.pos.x < b_pos.x {
-                    contact.displacement.x *= -1.0;
-                }
-                if entity.pos.y < b_pos.y {
-                    contact.displacement.y *= -1.0;
-                }
-
-                entity.pos += contact.displacement;
-            }
-        }
-    }
-
-    //todo! Separate projectiles from entities
-    fn p_projectile_level_response(&mut self, contacts: &mut Vec<Contact>) {
-        for contact in contacts.iter_mut() {
-            if contact.displacement.x < contact.displacement.y {
-                contact.displacement.y = 0.0;
-            } else {
-                contact.displacement.x = 0.0;
-            }
-
-            let b_pos: Vec2 = contact.b_r.rect_to_pos();
-
-            if let Some(projectile) = self.p_projectiles.get_mut(contact.a_i) {
-                let mut t_vec2 = dir_to_vec2(projectile.dir);
-
-                if projectile.pos.x < b_pos.x {
-                    contact.displacement.x *= -1.0;
-                }
-                if projectile.pos.y < b_pos.y {
-                    contact.displacement.y *= -1.0;
-                }
-
-                //now bounce
-
-                if contact.displacement.x != 0.0 {
-                    t_vec2.x *= -1.0;
-                } else if contact.displacement.y != 0.0 {
-                    t_vec2.y *= -1.0;
-                }
-
-                projectile.pos += contact.displacement;
-                projectile.dir = vec2_to_dir(t_vec2);
-            }
-        }
-    }
-
-    fn e_projectile_level_response(&mut self, contacts: &mut Vec<Contact>) {
-        for contact in contacts.iter_mut() {
-            if contact.displacement.x < contact.displacement.y {
-                contact.displacement.y = 0.0;
-            } else {
-                contact.displacement.x = 0.0;
-            }
-
-            let b_pos: Vec2 = contact.b_r.rect_to_pos();
-
-            if let Some(projectile) = self.e_projectiles.get_mut(contact.a_i) {
-                let mut t_vec2 = dir_to_vec2(projectile.dir);
-
-                if projectile.pos.x < b_pos.x {
-                    contact.displacement.x *= -1.0;
-                }
-                if projectile.pos.y < b_pos.y {
-                    contact.displacement.y *= -1.0;
-                }
-
-                //now bounce
-
-                if contact.displacement.x != 0.0 {
-                    t_vec2.x *= -1.0;
-                } else if contact.displacement.y != 0.0 {
-                    t_vec2.y *= -1.0;
-                }
-
-                projectile.pos += contact.displacement;
-                projectile.dir = vec2_to_dir(t_vec2);
-            }
-        }
-    }
-
-    fn damage_player(&mut self, entity_contacts: &mut Vec<Contact>) {
-        for contact in entity_contacts.iter_mut() {
-            if self.p_health[contact.b_i] > 0 {
-                self.p_health[contact.b_i] -= 1;
-            }
-
-            self.e_projectiles[contact.a_i].alive = false;
-        }
-    }
-    fn damage_enemy(&mut self, entity_contacts: &mut Vec<Contact>) {
-        for contact in entity_contacts.iter_mut() {
-            if self.e_health[contact.b_i] > 0 {
-                self.e_health[contact.b_i] -= 1;
-            }
-            self.p_projectiles[contact.a_i].alive = false;
-        }
-    }
-}
+const FIRST_LEVEL: &str = "level1";
 
 fn main() {
     #[cfg(not(target_arch = "wasm32"))]
-    let source = assets_manager::source::FileSystem::new("engine/content")
-        .expect("Couldn't load resources");
+    let source =
+        assets_manager::source::FileSystem::new("engine/content").expect("Couldn't load resources");
     #[cfg(target_arch = "wasm32")]
     let source = assets_manager::source::Embedded::from(assets_manager::source::embed!("content"));
     let cache = assets_manager::AssetCache::with_source(source);
+    let controls = Controls::load(&cache, "controls").unwrap_or_else(|err| {
+        eprintln!("Using the default controls: {err}");
+        Controls::default()
+    });
+
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    let mut playback = args.replay.as_ref().map(|path| {
+        Recording::load(path, &controls.keys())
+            .unwrap_or_else(|err| panic!("Couldn't load replay {}: {err}", path.display()))
+            .playback()
+    });
+    let first = match &playback {
+        Some(playback) => playback.level(),
+        None => args.level.as_deref().unwrap_or(FIRST_LEVEL),
+    };
+    let levels = LevelRegistry::load(&cache, first).unwrap_or_else(|err| panic!("{err}"));
+    let seed = match &playback {
+        Some(playback) => playback.seed(),
+        None => args.seed_or(levels[0].seed()),
+    };
+    // pass this back with --seed to replay the same enemy moves
+    println!("seed {seed}");
+    let recording = args.record.map(|path| {
+        (
+            path,
+            Recording::new(levels[0].name(), seed, &controls.keys()),
+        )
+    });
+    let mut world = World::new(levels, seed);
+    world.controls = controls;
+
+    if args.headless {
+        let idle = InputState::default();
+        let frames = args
+            .frames
+            .or(playback.as_ref().map(Playback::remaining))
+            .unwrap_or(0);
+        for _ in 0..frames {
+            let input = match &mut playback {
+                Some(playback) => playback.next_input().unwrap_or(&idle),
+                None => &idle,
+            };
+            world.step(input, DT);
+        }
+        print!("{world}");
+        return;
+    }
 
-    let drv = frenderer::Driver::new(
-        winit::window::WindowBuilder::new()
-            .with_title("test")
-            .with_inner_size(winit::dpi::LogicalSize::new(1024.0, 768.0)),
-        Some((W as u32 * 4, H as u32 * 4)),
-    );
-
-    let mut input = Input::default();
-
-    let mut now = frenderer::clock::Instant::now();
-    let mut acc = 0.0;
-    drv.run_event_loop::<(), _>(
-        move |window, frend| {
-            let mut frend = Immediate::new(frend);
-            let game = Game::new(&mut frend, cache);
-            (window, game, frend)
-        },
-        move |event, target, (window, ref mut game, ref mut frend)| {
-            use winit::event::{Event, WindowEvent};
-            match event {
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => {
-                    target.exit();
-                }
-                Event::WindowEvent {
-                    event: WindowEvent::Resized(size),
-                    ..
-                } => {
-                    if !frend.gpu().is_web() {
-                        frend.resize_surface(size.width, size.height);
-                    }
-                    window.request_redraw();
-                }
-                Event::WindowEvent {
-                    event: WindowEvent::RedrawRequested,
-                    ..
-                } => {
-                    let elapsed = now.elapsed().as_secs_f32();
-                    // You can add the time snapping/death spiral prevention stuff here if you want.
-                    // I'm not using it here to keep the starter code small.
-                    acc += elapsed;
-                    now = std::time::Instant::now();
-                    // While we have time to spend
-                    while acc >= DT {
-                        // simulate a frame
-                        acc -= DT;
-                        game.simulate(&input, DT);
-                        input.next_frame();
-                    }
-                    game.render(frend);
-                    frend.render();
-                    window.request_redraw();
-                }
-                event => {
-                    input.process_input_event(&event);
-                }
-            }
-        },
-    )
-    .expect("event loop error");
+    engine::run("test", (W as u32 * 4, H as u32 * 4), move |frend| {
+        Game::new(frend, cache, world, playback, recording, args.split)
+    });
 }
 
 impl Game {
-    fn new(renderer: &mut Immediate, cache: AssetCache) -> Self {
-        let tile_handle = cache
-            .load::<Png>("texture")
-            .expect("Couldn't load tilesheet img");
-        let tile_img = tile_handle.read().0.to_rgba8();
-        let tile_tex = renderer.create_array_texture(
-            &[&tile_img],
-            wgpu::TextureFormat::Rgba8UnormSrgb,
-            tile_img.dimensions(),
-            Some("tiles-sprites"),
-        );
-        let levels = vec![Level::from_str(
-            &cache
-                .load::<String>("level1")
-                .expect("Couldn't access level1.txt")
-                .read(),
-        )];
-        let current_level = 0;
-        let camera = Camera2D {
-            screen_pos: [0.0, 0.0],
-            screen_size: [W as f32, H as f32],
-        };
-        let sprite_estimate =
-            levels[current_level].sprite_count() + levels[current_level].starts().len();
-        renderer.sprite_group_add(
-            &tile_tex,
-            vec![Transform::ZERO; sprite_estimate],
-            vec![SheetRegion::ZERO; sprite_estimate],
-            camera,
-        );
-        let player_start = *levels[current_level]
-            .starts()
-            .iter()
-            .find(|(t, _)| *t == EntityType::Player1)
-            .map(|(_, ploc)| ploc)
-            .expect("Start level doesn't put the player anywhere");
-
-        let player2_start = *levels[current_level]
-            .starts()
-            .iter()
-            .find(|(t, _)| *t == EntityType::Player2)
-            .map(|(_, ploc)| ploc)
-            .expect("Start level doesn't put the player anywhere");
-
+    fn new(
+        renderer: &mut Immediate,
+        cache: AssetCache,
+        world: World,
+        playback: Option<Playback>,
+        recording: Option<(PathBuf, Recording)>,
+        split: Option<Split>,
+    ) -> Self {
+        let (sheets, hud) = Sheets::new(&cache, "texture", "font", (W as f32, H as f32), SOLID);
+        let sprite_estimate = world.level().sprite_count() + world.level().starts().len();
         let mut game = Game {
+            sheets,
             assets: cache,
-            current_level,
-            p1_attack_timer: 0.0,
-            p2_attack_timer: 0.0,
-            e_attack_timer: 0.0,
-            bounce: Vec::new(),
-            levels,
-            p_health: vec![1, 1],
-            players: vec![
-                Entity {
-                    alive: true,
-                    etype: EntityType::Player1,
-                    pos: player_start,
-                    dir: 0.0,
-                },
-                Entity {
-                    alive: true,
-                    etype: EntityType::Player2,
-                    pos: player2_start,
-                    dir: 0.0,
-                },
-            ],
-            e_health: Vec::new(),
-            enemies: Vec::new(),
-            p_projectiles: Vec::new(),
-            e_projectiles: Vec::new(),
+            screen: SplitScreen::new(W as f32, H as f32, split),
+            hud,
+            world,
+            playback,
+            recording,
+            menu: ControlsMenu::new(),
         };
-        game.enter_level(player_start, player2_start);
+        let lens = [sprite_estimate, sprite_estimate, 64];
+        game.sheets
+            .add_sprite_groups(renderer, &game.assets, &game.screen, &game.hud, lens)
+            .expect("Couldn't load the tile and glyph sheets");
+        game.follow_players(0.0);
         game
     }
-    fn level(&self) -> &Level {
-        &self.levels[self.current_level]
-    }
-    fn enter_level(&mut self, player_pos: Vec2, player2_pos: Vec2) {
-        self.players.truncate(2);
-        self.players[0].pos = player_pos;
-        self.players[1].pos = player2_pos;
-        for (etype, pos) in self.levels[self.current_level].starts().iter() {
-            match etype {
-                EntityType::Player1 => {}
-                EntityType::Player2 => {}
-                EntityType::Door(_rm, _x, _y) => todo!("doors not supported"),
-                EntityType::Enemy => {
-                    // enemies have health of 3
-                    self.e_health.push(3);
-
-                    self.enemies.push(Entity {
-                        alive: true,
-                        pos: *pos,
-                        dir: 270.0,
-                        etype: etype.clone(),
-                    })
-                }
-                EntityType::P_Projectile => {}
-                EntityType::E_Projectile => {}
-            }
-        }
+    // Keeps the cameras on whoever's still in the game
+    fn follow_players(&mut self, dt: f32) {
+        let players = [0, 1].map(|p_i| {
+            (self.world.health(self.world.players[p_i]) > 0).then_some(self.world.player(p_i).pos)
+        });
+        self.screen.follow(self.world.level(), players, dt);
     }
-    fn render(&mut self, frend: &mut Immediate) {
-        self.level().render_immediate(frend);
-
-        for (p_i, player) in self.players.iter().enumerate() {
-            if self.p_health[p_i] > 0 {
-                frend.draw_sprite(0, self.players[p_i].transform(), PLAYER);
-            }
+    // Each player's hearts and reload bar in their top corner, how many enemies are
+    // left, and a banner when the game's decided
+    fn draw_hud(&mut self, frend: &mut Immediate) {
+        self.hud.clear();
+        let size = self.hud.size();
+        let top = size.y - 4.0;
+        for (p_i, (x, align)) in [(4.0, Align::Left), (size.x - 4.0, Align::Right)]
+            .into_iter()
+            .enumerate()
+        {
+            let anchor = Vec2 { x, y: top };
+            let id = self.world.players[p_i];
+            let max = self
+                .world
+                .components
+                .health
+                .get(id)
+                .map_or(0, |health| health.max);
+            self.hud
+                .hearts(anchor, align, self.world.health(id), max, HEART);
+            let bar = Vec2 { x, y: top - 11.0 };
+            let bar_size = Vec2 { x: 24.0, y: 2.0 };
+            self.hud
+                .bar(bar, align, bar_size, self.world.reload(p_i), RELOAD);
         }
-
-        for (e_i, enemy) in self.enemies.iter().enumerate() {
-            if self.e_health[e_i] > 0 {
-                frend.draw_sprite(0, self.enemies[e_i].transform(), enemy.uv());
-            }
+        let enemies = format!("ENEMIES {}", self.world.enemies().count());
+        let middle = Vec2 {
+            x: size.x / 2.0,
+            y: top,
+        };
+        self.hud.text(middle, Align::Center, &enemies, 1);
+        let banner = self
+            .world
+            .players
+            .iter()
+            .all(|id| self.world.health(*id) == 0)
+            .then_some("GAME OVER");
+        if let Some(banner) = banner {
+            self.hud.banner(banner);
         }
-
-        for (p_i, projectile) in self.p_projectiles.iter().enumerate() {
-            if projectile.alive {
-                frend.draw_sprite(0, projectile.transform(), projectile.uv());
+        self.hud.draw(frend, 2);
+    }
+    // Everything in the world `view` can see, into sprite group `group`
+    fn draw_world(&self, frend: &mut Immediate, group: usize, view: &View, alpha: f32) {
+        view.draw_level(frend, group, self.world.level());
+        // things are drawn `alpha` of the way through their last step
+        let world = &self.world;
+        let interpolated = |id| world.components.interpolated(&world.entities, id, alpha);
+
+        for (etype, pos) in self.world.level().starts() {
+            if let EntityType::Door(..) = etype {
+                view.draw_sprite(
+                    frend,
+                    group,
+                    Transform {
+                        x: pos.x,
+                        y: pos.y,
+                        w: TILE_SZ as u16,
+                        h: TILE_SZ as u16,
+                        rot: 0.0,
+                    },
+                    DOOR.with_depth(DEPTH),
+                );
             }
         }
 
-        for (p_i, projectile) in self.e_projectiles.iter().enumerate() {
-            if projectile.alive {
-                frend.draw_sprite(0, projectile.transform(), projectile.uv());
+        for id in self.world.players {
+            if self.world.health(id) > 0 {
+                let player = interpolated(id);
+                view.draw_sprite(frend, group, player.transform(), PLAYER.with_depth(DEPTH));
             }
         }
 
-        // do we need this? what is this for?
-
-        // let (w, h) = match self.entities[0].dir {
-        //     90.0 | 270.0 => (16, 8),
-        //     _ => (8, 16),
-        // };
-
-        // let delta = dir_to_vec2(self.entities[0].dir) * 7.0;
-        // let delta2 = dir_to_vec2(self.entities[1].dir) * 7.0;
-
-        // let pos = self.entities[0].pos + delta;
-        // let pos2 = self.entities[1].pos + delta;
-    }
-    fn simulate(&mut self, input: &Input, dt: f32) {
-        if self.p1_attack_timer > 0.0 {
-            self.p1_attack_timer -= dt;
-        }
-        if self.p2_attack_timer > 0.0 {
-            self.p2_attack_timer -= dt;
-        }
-        if self.e_attack_timer > 0.0 {
-            self.e_attack_timer -= dt;
+        for (id, _) in self.world.enemies() {
+            let enemy = interpolated(id);
+            view.draw_sprite(frend, group, enemy.transform(), enemy.uv());
         }
 
-        let mut d_angle: f32 = 0.0;
-        let mut d_angle2: f32 = 0.0;
-
-        if input.is_key_down(Key::ArrowLeft) {
-            d_angle += ROTATE_SPEED;
-        } else if input.is_key_down(Key::ArrowRight) {
-            d_angle -= ROTATE_SPEED;
+        for (id, _) in self.world.projectiles() {
+            let projectile = interpolated(id);
+            view.draw_sprite(frend, group, projectile.transform(), projectile.uv());
         }
 
-        if input.is_key_down(Key::KeyA) {
-            d_angle2 += ROTATE_SPEED;
-        } else if input.is_key_down(Key::KeyD) {
-            d_angle2 -= ROTATE_SPEED;
+        let bursts = self
+            .world
+            .entities
+            .values()
+            .filter(|entity| entity.etype == EntityType::Burst);
+        for burst in bursts {
+            view.draw_sprite(frend, group, burst.transform(), burst.uv());
         }
 
-        self.players[0].dir += d_angle;
-        self.players[1].dir += d_angle2;
-
-        if self.p1_attack_timer <= 0.0 && input.is_key_pressed(Key::Space) && self.p_health[0] > 0 {
-            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
-            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
-            self.p_projectiles.push(Entity {
-                alive: true,
-
-                // how to put the bullet at the top of the tank so it doesnt kill itself
-                pos: self.players[0].pos + dir_to_vec2(self.players[0].dir) * 15.0,
-                dir: self.players[0].dir,
-                etype: EntityType::P_Projectile,
-            });
-
-            self.bounce.push(3);
-
-            self.p1_attack_timer = ATTACK_MAX_TIME;
+        if let Some(divider) = view.divider {
+            frend.draw_sprite(group, divider, DIVIDER);
         }
+    }
+}
 
-        if self.p2_attack_timer <= 0.0 && input.is_key_pressed(Key::KeyQ) && self.p_health[1] > 0 {
-            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
-            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
-            self.p_projectiles.push(Entity {
-                alive: true,
-                pos: self.players[1].pos + dir_to_vec2(self.players[1].dir) * 15.0,
-                dir: self.players[1].dir,
-                etype: EntityType::P_Projectile,
-            });
-
-            self.bounce.push(3);
-
-            self.p2_attack_timer = ATTACK_MAX_TIME;
+impl engine::App for Game {
+    fn hot_reload(&mut self, frend: &mut Immediate) {
+        let levels = &mut self.world.levels;
+        self.sheets
+            .hot_reload(frend, &self.assets, levels, &self.screen, &self.hud);
+    }
+    fn render(&mut self, frend: &mut Immediate, alpha: f32) {
+        // while the game's paused (or a replay's over) nothing moves, so don't keep
+        // drawing things partway between their last two steps
+        let stopped = self.menu.is_open()
+            || self
+                .playback
+                .as_ref()
+                .is_some_and(|playback| playback.remaining() == 0);
+        let alpha = if stopped { 1.0 } else { alpha };
+        // one sprite group per view: group 1 sits empty unless the screen's split
+        let views = self.screen.views(alpha);
+        for group in 0..2 {
+            if let Some(view) = views.get(group) {
+                frend.sprite_group_set_camera(group, view.camera);
+                self.draw_world(frend, group, view, alpha);
+            }
         }
-
-        if self.e_attack_timer <= 0.0 {
-            for (e_i, enemy) in self.enemies.iter().enumerate() {
-                if self.e_health[e_i] > 0 {
-                    self.e_projectiles.push(Entity {
-                        alive: true,
-                        pos: self.enemies[e_i].pos + dir_to_vec2(self.enemies[e_i].dir) * 15.0,
-                        dir: self.enemies[e_i].dir,
-                        etype: EntityType::E_Projectile,
-                    });
-    
-                    self.bounce.push(3);
-                }
+        self.draw_hud(frend);
+    }
+    fn simulate(&mut self, input: &InputState, dt: f32) {
+        // replays only know the keys they started with, so no rebinding during them
+        if self.playback.is_none() && self.recording.is_none() {
+            let paused = self.menu.update(input, &mut self.world.controls);
+            for message in self.menu.take_messages() {
+                println!("{message}");
+            }
+            if paused {
+                return;
             }
-
-            self.e_attack_timer = ENEMY_ATTACK_COOLDOWN_TIME;
         }
-
-        let mut dest = self.players[0].pos;
-        let mut dest2 = self.players[1].pos;
-
-        if input.is_key_down(Key::ArrowUp) {
-            dest += dir_to_vec2(self.players[0].dir);
-        } else if input.is_key_down(Key::ArrowDown) {
-            dest += dir_to_vec2(self.players[0].dir) * -1.0;
+        let input = match &mut self.playback {
+            Some(playback) => match playback.next_input() {
+                Some(input) => input,
+                // the replay is over, so hold still on its last frame
+                None => return,
+            },
+            None => input,
+        };
+        if let Some((_, recording)) = &mut self.recording {
+            recording.record(input);
         }
-
-        if input.is_key_down(Key::KeyW) {
-            dest2 += dir_to_vec2(self.players[1].dir);
-        } else if input.is_key_down(Key::KeyS) {
-            dest2 += dir_to_vec2(self.players[1].dir) * -1.0;
+        let health = self.world.players.map(|id| self.world.health(id));
+        self.world.step(input, dt);
+        // getting shot shakes the screen
+        if (0..2).any(|p_i| self.world.health(self.world.players[p_i]) < health[p_i]) {
+            self.screen.shake(3.0, 0.4);
         }
-
-        self.players[0].pos = dest;
-        self.players[1].pos = dest2;
-
-        let mut rng = rand::thread_rng();
-        for enemy in self.enemies.iter_mut() {
-            if rng.gen_bool(0.05) {
-                enemy.dir = match rng.gen_range(0..4) {
-                    0 => 180.0,
-                    1 => 0.0,
-                    2 => 270.0,
-                    3 => 90.0,
-                    _ => panic!(),
-                };
+        self.follow_players(dt);
+    }
+    fn exit(&mut self) {
+        if let Some((path, recording)) = &self.recording {
+            match recording.save(path) {
+                Ok(()) => println!("Saved {} ticks to {}", recording.len(), path.display()),
+                Err(err) => eprintln!("Couldn't save replay to {}: {err}", path.display()),
             }
-            enemy.pos += dir_to_vec2(enemy.dir) * ENEMY_SPEED * DT;
         }
-
-        for projectile in self.p_projectiles.iter_mut() {
-            projectile.pos += dir_to_vec2(projectile.dir);
-        }
-
-        for projectile in self.e_projectiles.iter_mut() {
-            projectile.pos += dir_to_vec2(projectile.dir);
-        }
-
-        // Collision Detection & Response:
-
-        // Create all rectangles first:
-
-        let player_rects: Vec<Rect> = self.players.iter().map(|entity| entity.rect()).collect();
-
-        let enemy_rects: Vec<Rect> = self.enemies.iter().map(|entity| entity.rect()).collect();
-
-        let player_projectile_rect: Vec<Rect> = self
-            .p_projectiles
-            .iter()
-            .map(|projectile| projectile.rect())
-            .collect();
-
-        let enemy_projectile_rect: Vec<Rect> = self
-            .e_projectiles
-            .iter()
-            .map(|projectile| projectile.rect())
-            .collect();
-
-        // Contacts
-
-        let mut player_level_contacts: Vec<Contact> =
-            gather_level_contacts(&player_rects, self.level());
-
-        let mut enemy_level_contacts: Vec<Contact> =
-            gather_level_contacts(&enemy_rects, self.level());
-
-        player_level_contacts.sort_by(|a, b| {
-            b.displacement
-                .mag_sq()
-                .partial_cmp(&a.displacement.mag_sq())
-                .unwrap()
-        });
-
-        enemy_level_contacts.sort_by(|a, b| {
-            b.displacement
-                .mag_sq()
-                .partial_cmp(&a.displacement.mag_sq())
-                .unwrap()
-        });
-
-        let mut p_projectile_level_contacts: Vec<Contact> =
-            gather_level_contacts(&player_projectile_rect, self.level());
-
-        let mut e_projectile_level_contacts: Vec<Contact> =
-            gather_level_contacts(&enemy_projectile_rect, self.level());
-
-        p_projectile_level_contacts.sort_by(|a, b| {
-            b.displacement
-                .mag_sq()
-                .partial_cmp(&a.displacement.mag_sq())
-                .unwrap()
-        });
-
-        e_projectile_level_contacts.sort_by(|a, b| {
-            b.displacement
-                .mag_sq()
-                .partial_cmp(&a.displacement.mag_sq())
-                .unwrap()
-        });
-
-        let mut e_projectile_player_contacts: Vec<Contact> =
-            gather_contacts(&enemy_projectile_rect, &player_rects);
-
-        let mut p_projectile_enemy_contacts: Vec<Contact> =
-            gather_contacts(&player_projectile_rect, &enemy_rects);
-
-        self.player_level_collision_response(&mut player_level_contacts);
-        self.enemy_level_collision_response(&mut enemy_level_contacts);
-
-        self.p_projectile_level_response(&mut p_projectile_level_contacts);
-        self.e_projectile_level_response(&mut e_projectile_level_contacts);
-
-        self.damage_player(&mut e_projectile_player_contacts);
-        self.damage_enemy(&mut p_projectile_enemy_contacts);
     }
 }
@@ -0,0 +1,804 @@
+use engine::arena::{Arena, EntityId};
+use engine::collision::sweep_bounce;
+use engine::controls::{Action, Controls};
+use engine::ecs::{
+    self, join, Actor, Bounces, CollisionLayer, Components, Controller, Despawn, DespawnCause,
+    Health, Lifetime, Motion, Velocity,
+};
+use engine::geom::*;
+use engine::input::InputState;
+use engine::layers::*;
+use engine::level::StartKind;
+use engine::registry::LevelRegistry;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityType {
+    Player1,
+    Player2,
+    Enemy,
+    PlayerProjectile,
+    EnemyProjectile,
+    // the flash a projectile leaves when it runs out
+    Burst,
+    // which level, grid x in dest level, grid y in dest level
+    Door(String, u16, u16),
+}
+
+impl StartKind for EntityType {
+    fn from_start(kind: &str, args: &[&str]) -> Option<Self> {
+        match (kind, args) {
+            // levels with one start (like the mazes) just say "player"
+            ("player" | "player1", []) => Some(EntityType::Player1),
+            ("player2", []) => Some(EntityType::Player2),
+            ("enemy", []) => Some(EntityType::Enemy),
+            ("door", [to_room, to_x, to_y]) => Some(EntityType::Door(
+                to_room.to_string(),
+                to_x.parse().ok()?,
+                to_y.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+    fn door(&self) -> Option<(&str, u16, u16)> {
+        match self {
+            EntityType::Door(to_room, to_x, to_y) => Some((to_room, *to_x, *to_y)),
+            _ => None,
+        }
+    }
+}
+
+pub type Level = engine::level::Level<EntityType>;
+
+// what a level looks like when the players come back through its door
+pub struct LevelState {
+    pub enemies: Vec<(Entity, Health)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub alive: bool,
+    pub pos: Vec2,
+    pub dir: f32,
+    pub etype: EntityType,
+}
+
+impl Actor for Entity {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+    fn dir(&self) -> f32 {
+        self.dir
+    }
+    fn place(&mut self, pos: Vec2, dir: f32) {
+        self.pos = pos;
+        self.dir = dir;
+    }
+    fn alive(&self) -> bool {
+        self.alive
+    }
+    fn kill(&mut self) {
+        self.alive = false;
+    }
+}
+
+impl Entity {
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        }
+    }
+    #[allow(dead_code)]
+    pub fn circle(&self) -> Circle {
+        Circle {
+            x: self.pos.x,
+            y: self.pos.y,
+            r: TILE_SZ as f32 / 2.0,
+        }
+    }
+    #[allow(dead_code)]
+    pub fn shape_rect(&self) -> Shape {
+        Shape::Rect(Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        })
+    }
+    #[allow(dead_code)]
+    pub fn shape_circle(&self) -> Shape {
+        Shape::Circle(Circle {
+            x: self.pos.x,
+            y: self.pos.y,
+            r: TILE_SZ as f32 / 2.0,
+        })
+    }
+}
+
+// Everything the game simulates; main.rs draws it and feeds it input
+pub struct World {
+    pub current_level: usize,
+    pub levels: LevelRegistry<EntityType, LevelState>,
+    // the players, enemies and everyone's projectiles
+    pub entities: Arena<Entity>,
+    pub components: Components,
+    pub players: [EntityId; 2],
+    // whether each player was already touching a door last frame
+    pub on_door: [bool; 2],
+    pub e_attack_timer: f32,
+    pub p1_attack_timer: f32,
+    pub p2_attack_timer: f32,
+    // projectiles that ran out of bounces or time during the last step
+    pub despawned: Vec<Despawn>,
+    // how many steps have run
+    pub frame: usize,
+    // every random choice goes through this, so a seed replays the same game
+    pub rng: StdRng,
+    // which keys each player's actions are on
+    pub controls: Controls,
+}
+
+// Feel free to change this if you use a different tilesheet
+pub const TILE_SZ: usize = 16;
+
+// pixels per second
+const PLAYER_SPEED: f32 = 64.0;
+const ENEMY_SPEED: f32 = 32.0;
+const PROJECTILE_SPEED: f32 = 60.0;
+const KNOCKBACK_SPEED: f32 = 128.0;
+// radians per second
+const ROTATE_SPEED: f32 = 6.0;
+// how often an enemy picks a new direction, on average, per second
+const ENEMY_TURN_RATE: f64 = 3.0;
+
+// tanks get up to speed and stop again in an eighth of a second, enemies in a quarter
+const PLAYER_MOTION: Motion = Motion {
+    max_speed: PLAYER_SPEED,
+    accel: PLAYER_SPEED * 8.0,
+    friction: PLAYER_SPEED * 8.0,
+};
+const ENEMY_MOTION: Motion = Motion {
+    max_speed: ENEMY_SPEED,
+    accel: ENEMY_SPEED * 4.0,
+    friction: ENEMY_SPEED * 4.0,
+};
+
+const ATTACK_MAX_TIME: f32 = 0.3;
+#[allow(dead_code)]
+const ATTACK_COOLDOWN_TIME: f32 = 0.1;
+const ENEMY_ATTACK_COOLDOWN_TIME: f32 = 10.0;
+const KNOCKBACK_TIME: f32 = 0.25;
+
+// Collision layers; each kind of collider only collides with what's in its mask
+const PLAYER: u32 = 1 << 0;
+const ENEMY: u32 = 1 << 1;
+const P_PROJECTILE: u32 = 1 << 2;
+const E_PROJECTILE: u32 = 1 << 3;
+
+// walls a projectile can bounce off before the next one stops it, and how many
+// seconds it lasts if it never gets there
+const PROJECTILE_BOUNCES: u8 = 3;
+const PROJECTILE_LIFETIME: f32 = 5.0;
+const BURST_LIFETIME: f32 = 0.25;
+
+impl Body for Entity {
+    fn shape(&self) -> Shape {
+        self.shape_rect()
+    }
+    fn pos_mut(&mut self) -> &mut Vec2 {
+        &mut self.pos
+    }
+    fn dir_mut(&mut self) -> &mut f32 {
+        &mut self.dir
+    }
+}
+
+impl Bodies<EntityId> for World {
+    type Body = Entity;
+    fn body_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(id)
+    }
+}
+
+// Adds an `etype` with the components that kind of thing has
+fn spawn(
+    entities: &mut Arena<Entity>,
+    components: &mut Components,
+    etype: EntityType,
+    pos: Vec2,
+    dir: f32,
+) -> EntityId {
+    let projectile = (None, Some(PROJECTILE_BOUNCES), Some(PROJECTILE_LIFETIME));
+    let ((health, bounces, lifetime), collider) = match etype {
+        EntityType::Player1 | EntityType::Player2 => {
+            ((Some(1), None, None), Some((PLAYER, E_PROJECTILE)))
+        }
+        EntityType::Enemy => ((Some(3), None, None), Some((ENEMY, P_PROJECTILE))),
+        EntityType::PlayerProjectile => (projectile, Some((P_PROJECTILE, ENEMY))),
+        EntityType::EnemyProjectile => (projectile, Some((E_PROJECTILE, PLAYER))),
+        EntityType::Burst => ((None, None, Some(BURST_LIFETIME)), None),
+        EntityType::Door(..) => panic!("doors are part of the level, not entities"),
+    };
+    // projectiles keep a steady speed, so only tanks get driven
+    let motion = match etype {
+        EntityType::Player1 | EntityType::Player2 => Some(PLAYER_MOTION),
+        EntityType::Enemy => Some(ENEMY_MOTION),
+        _ => None,
+    };
+    let player = match etype {
+        EntityType::Player1 => Some(0),
+        EntityType::Player2 => Some(1),
+        _ => None,
+    };
+    let id = entities.insert(Entity {
+        alive: true,
+        pos,
+        dir,
+        etype,
+    });
+    if let Some(max) = health {
+        components.health.insert(id, Health::new(max));
+    }
+    if let Some(bounces) = bounces {
+        components.bounces.insert(id, Bounces(bounces));
+    }
+    if let Some(lifetime) = lifetime {
+        components.lifetime.insert(id, Lifetime(lifetime));
+    }
+    if let Some(player) = player {
+        components.controller.insert(id, Controller::new(player));
+    }
+    if let Some(motion) = motion {
+        components.velocity.insert(id, Velocity(Vec2::ZERO));
+        components.motion.insert(id, motion);
+    }
+    if let Some((layer, hits)) = collider {
+        components.collision.insert(
+            id,
+            CollisionLayer {
+                layer,
+                mask: TILES | hits,
+            },
+        );
+    }
+    id
+}
+
+// a projectile (a) hit the level: it bounces, unless that was its last bounce
+fn ricochet(world: &mut World, hit: &Hit<EntityId>) {
+    let Some(projectile) = world.entities.get(hit.a).filter(|p| p.alive) else {
+        return;
+    };
+    // as in `bounce`, only a wall it's heading into turns it (and costs a bounce)
+    let into = dir_to_vec2(projectile.dir).dot(hit.normal) < 0.0;
+    if into && !world.components.spend_bounce(hit.a) {
+        world.despawn(hit.a, DespawnCause::OutOfBounces);
+        return;
+    }
+    bounce(world, hit);
+}
+
+// a projectile (a) hit someone on the other side (b), who gets knocked back the way
+// it was going
+fn damage(world: &mut World, hit: &Hit<EntityId>) {
+    // it's only good for one hit
+    let Some(projectile) = world.entities.get_mut(hit.a).filter(|p| p.alive) else {
+        return;
+    };
+    let Some(b) = hit.b else {
+        return;
+    };
+    let Some(health) = world.components.health.get_mut(b) else {
+        return;
+    };
+    health.hurt(1);
+    projectile.alive = false;
+    let push = dir_to_vec2(projectile.dir) * KNOCKBACK_SPEED;
+    world.components.knock_back(b, push, KNOCKBACK_TIME);
+}
+
+impl World {
+    // This tick's colliders and what happens when they meet.  Dead projectiles and
+    // the dead don't get hit by anything any more, but everyone stays out of walls.
+    fn collisions(&self) -> Collisions<World, EntityId> {
+        let mut collisions = Collisions::new();
+        collisions
+            .on(PLAYER, TILES, push_out)
+            .on(ENEMY, TILES, push_out)
+            .on(P_PROJECTILE, TILES, ricochet)
+            .on(E_PROJECTILE, TILES, ricochet)
+            .on(E_PROJECTILE, PLAYER, damage)
+            .on(P_PROJECTILE, ENEMY, damage);
+        for (id, entity, collision) in join(&self.entities, &self.components.collision) {
+            let dead = self.components.health.get(id).is_some_and(Health::is_dead);
+            let mask = if entity.alive && !dead {
+                collision.mask
+            } else {
+                collision.mask & TILES
+            };
+            collisions.add(id, entity.shape_rect(), collision.layer, mask);
+        }
+        collisions
+    }
+}
+
+impl World {
+    // Starts in level 0 of `levels`, with randomness drawn from `seed`
+    pub fn new(levels: LevelRegistry<EntityType, LevelState>, seed: u64) -> Self {
+        let current_level = 0;
+        let player_start = *levels[current_level]
+            .starts()
+            .iter()
+            .find(|(t, _)| *t == EntityType::Player1)
+            .map(|(_, ploc)| ploc)
+            .expect("Start level doesn't put the player anywhere");
+
+        // single-player levels only have one start, so both players share it
+        let player2_start = levels[current_level]
+            .starts()
+            .iter()
+            .find(|(t, _)| *t == EntityType::Player2)
+            .map(|(_, ploc)| *ploc)
+            .unwrap_or(player_start);
+
+        let mut entities = Arena::new();
+        let mut components = Components::default();
+        let players = [
+            spawn(
+                &mut entities,
+                &mut components,
+                EntityType::Player1,
+                player_start,
+                0.0,
+            ),
+            spawn(
+                &mut entities,
+                &mut components,
+                EntityType::Player2,
+                player2_start,
+                0.0,
+            ),
+        ];
+        let mut world = World {
+            current_level,
+            p1_attack_timer: 0.0,
+            p2_attack_timer: 0.0,
+            e_attack_timer: 0.0,
+            despawned: Vec::new(),
+            levels,
+            on_door: [false; 2],
+            entities,
+            components,
+            players,
+            frame: 0,
+            rng: StdRng::seed_from_u64(seed),
+            controls: Controls::default(),
+        };
+        world.enter_level(player_start, player2_start);
+        world
+    }
+    pub fn level(&self) -> &Level {
+        &self.levels[self.current_level]
+    }
+    // player 0 or 1
+    pub fn player(&self, p_i: usize) -> &Entity {
+        &self.entities[self.players[p_i]]
+    }
+    pub fn player_mut(&mut self, p_i: usize) -> &mut Entity {
+        &mut self.entities[self.players[p_i]]
+    }
+    // what's left of someone's health (projectiles don't have any)
+    pub fn health(&self, id: EntityId) -> u8 {
+        self.components.health.get(id).map_or(0, |health| health.hp)
+    }
+    pub fn enemies(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy)
+    }
+    pub fn projectiles(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities.iter().filter(|(_, entity)| {
+            matches!(
+                entity.etype,
+                EntityType::PlayerProjectile | EntityType::EnemyProjectile
+            )
+        })
+    }
+    pub fn spawn(&mut self, etype: EntityType, pos: Vec2, dir: f32) -> EntityId {
+        spawn(&mut self.entities, &mut self.components, etype, pos, dir)
+    }
+    // Takes out a projectile that ran out (at the end of the step), leaving a burst
+    // behind, and reports it in `despawned`
+    fn despawn(&mut self, id: EntityId, cause: DespawnCause) {
+        let Some(projectile) = self.entities.get_mut(id) else {
+            return;
+        };
+        if ecs::despawn(projectile, id, cause, &mut self.despawned) {
+            let pos = projectile.pos;
+            self.spawn(EntityType::Burst, pos, 0.0);
+        }
+    }
+    fn enter_level(&mut self, player_pos: Vec2, player2_pos: Vec2) {
+        self.components.keep_controlled(&mut self.entities);
+        self.player_mut(0).pos = player_pos;
+        self.player_mut(1).pos = player2_pos;
+        // levels we've been to before pick up where we left them
+        if let Some(state) = self.levels.take_saved(self.current_level) {
+            for (enemy, health) in state.enemies {
+                let id = self.spawn(enemy.etype, enemy.pos, enemy.dir);
+                self.components.health.insert(id, health);
+            }
+        } else {
+            let enemies: Vec<Vec2> = self.levels[self.current_level]
+                .starts()
+                .iter()
+                .filter(|(etype, _)| *etype == EntityType::Enemy)
+                .map(|(_, pos)| *pos)
+                .collect();
+            for pos in enemies {
+                self.spawn(EntityType::Enemy, pos, 3.0 * FRAC_PI_2);
+            }
+        }
+        let rects = [self.player(0).rect(), self.player(1).rect()];
+        self.levels
+            .arrive(self.current_level, &rects, &mut self.on_door);
+    }
+    fn go_through_door(&mut self, to: usize, pos: Vec2) {
+        let enemies: Vec<EntityId> = self.enemies().map(|(id, _)| id).collect();
+        let state = LevelState {
+            enemies: enemies
+                .into_iter()
+                .map(|id| {
+                    let health = self.components.health.remove(id);
+                    (self.entities.remove(id).unwrap(), health.unwrap())
+                })
+                .collect(),
+        };
+        self.levels.save(self.current_level, state);
+        self.current_level = to;
+        self.enter_level(pos, pos);
+    }
+
+    // How ready player `p_i`'s next shot is, from 0 right after firing to 1
+    pub fn reload(&self, p_i: usize) -> f32 {
+        let timer = [self.p1_attack_timer, self.p2_attack_timer][p_i];
+        1.0 - (timer / ATTACK_MAX_TIME).clamp(0.0, 1.0)
+    }
+
+    pub fn step(&mut self, input: &InputState, dt: f32) {
+        self.frame += 1;
+        self.despawned.clear();
+        self.components.remember_positions(&self.entities);
+        if self.p1_attack_timer > 0.0 {
+            self.p1_attack_timer -= dt;
+        }
+        if self.p2_attack_timer > 0.0 {
+            self.p2_attack_timer -= dt;
+        }
+        if self.e_attack_timer > 0.0 {
+            self.e_attack_timer -= dt;
+        }
+
+        // a stick turns (and below, drives) as far as it's pushed; keys go all the way
+        for p_i in 0..2 {
+            let turn = self.controls.turn(input, p_i);
+            self.player_mut(p_i).dir += turn * ROTATE_SPEED * dt;
+        }
+
+        if self.p1_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 0, Action::Fire)
+            && self.health(self.players[0]) > 0
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            // how to put the bullet at the top of the tank so it doesnt kill itself
+            let player = self.player(0);
+            let (pos, dir) = (player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+            self.spawn(EntityType::PlayerProjectile, pos, dir);
+
+            self.p1_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        if self.p2_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 1, Action::Fire)
+            && self.health(self.players[1]) > 0
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            let player = self.player(1);
+            let (pos, dir) = (player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+            self.spawn(EntityType::PlayerProjectile, pos, dir);
+
+            self.p2_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        if self.e_attack_timer <= 0.0 {
+            let shots: Vec<(Vec2, f32)> = self
+                .enemies()
+                .filter(|(id, _)| self.health(*id) > 0)
+                .map(|(_, enemy)| (enemy.pos + dir_to_vec2(enemy.dir) * 15.0, enemy.dir))
+                .collect();
+            for (pos, dir) in shots {
+                self.spawn(EntityType::EnemyProjectile, pos, dir);
+            }
+
+            self.e_attack_timer = ENEMY_ATTACK_COOLDOWN_TIME;
+        }
+
+        for p_i in 0..2 {
+            let id = self.players[p_i];
+            let throttle = self.controls.throttle(input, p_i);
+            let player = &mut self.entities[id];
+            let vel = self
+                .components
+                .drive(id, dir_to_vec2(player.dir), throttle, dt);
+            player.pos += vel * dt;
+        }
+
+        let enemies = self
+            .entities
+            .iter_mut()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy);
+        for (id, enemy) in enemies {
+            if self.rng.gen_bool((ENEMY_TURN_RATE * dt as f64).min(1.0)) {
+                enemy.dir = match self.rng.gen_range(0..4) {
+                    0 => PI,
+                    1 => 0.0,
+                    2 => 3.0 * FRAC_PI_2,
+                    3 => FRAC_PI_2,
+                    _ => panic!(),
+                };
+            }
+            let vel = self.components.drive(id, dir_to_vec2(enemy.dir), 1.0, dt);
+            enemy.pos += vel * dt;
+        }
+
+        // projectiles bounce off the walls they'd hit on the way, rather than passing
+        // through thin ones; `ricochet` only has to deal with ones fired into a wall
+        let level = &self.levels[self.current_level];
+        let mut stopped = Vec::new();
+        for (id, projectile) in self.entities.iter_mut() {
+            if !projectile.alive || !self.components.bounces.contains(id) {
+                continue;
+            }
+            let moved = sweep_bounce(
+                projectile.shape_rect().bounds(),
+                projectile.dir,
+                PROJECTILE_SPEED * dt,
+                level,
+                |tile| tile.solid,
+                || self.components.spend_bounce(id),
+            );
+            projectile.pos += moved.delta;
+            projectile.dir = moved.dir;
+            if moved.stopped {
+                stopped.push(id);
+            }
+        }
+        for id in stopped {
+            self.despawn(id, DespawnCause::OutOfBounces);
+        }
+        for id in self.components.tick_lifetimes(dt) {
+            if self.entities[id].etype == EntityType::Burst {
+                self.entities[id].alive = false;
+            } else {
+                self.despawn(id, DespawnCause::Expired);
+            }
+        }
+
+        // Collision Detection & Response:
+        let hits = self.collisions().detect(self.level());
+        dispatch(self, &hits);
+
+        // dead enemies and spent projectiles make room for new ones (dead players
+        // stay, but aren't drawn)
+        let components = &self.components;
+        self.entities.retain(|id, entity| {
+            let dead = components.health.get(id).is_some_and(Health::is_dead);
+            components.controller.contains(id) || (entity.alive && !dead)
+        });
+        self.components.retain_alive(&self.entities);
+
+        // a door takes both players along when either one walks onto it
+        let players =
+            [0, 1].map(|p_i| (self.player(p_i).rect(), self.health(self.players[p_i]) > 0));
+        let door = self
+            .levels
+            .door_taken(self.current_level, &players, &mut self.on_door);
+        if let Some((to, pos)) = door {
+            self.go_through_door(to, pos);
+        }
+    }
+}
+
+// What `--headless` prints at the end of a run
+impl std::fmt::Display for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} after {} frames", self.level().name(), self.frame)?;
+        let people = self
+            .entities
+            .iter()
+            .filter(|(id, _)| self.components.health.contains(*id));
+        for (id, entity) in people {
+            writeln!(
+                f,
+                "{:?} with {} health at ({:.1}, {:.1}) facing {:.2}",
+                entity.etype,
+                self.health(id),
+                entity.pos.x,
+                entity.pos.y,
+                entity.dir
+            )?;
+        }
+        for (_, projectile) in self.projectiles() {
+            writeln!(
+                f,
+                "{:?} {} at ({:.1}, {:.1}) facing {:.2}",
+                projectile.etype,
+                if projectile.alive { "alive" } else { "dead" },
+                projectile.pos.x,
+                projectile.pos.y,
+                projectile.dir
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::DT;
+
+    // two corridors with a door in the middle of each; b's door lands right on a's
+    const A: &str = "a 9 3
+====
+. o 594 55 16 16
+# s 419 61 16 16
+====
+#########
+#.......#
+#########
+====
+player1 1 1
+enemy 7 1
+door b 1 1 4 1
+";
+    const B: &str = "b 9 3
+====
+. o 594 55 16 16
+# s 419 61 16 16
+====
+#########
+#.......#
+#########
+====
+enemy 7 1
+enemy 6 1
+door a 4 1 4 1
+";
+
+    fn health(world: &World) -> Vec<u8> {
+        world.enemies().map(|(id, _)| world.health(id)).collect()
+    }
+
+    fn world() -> World {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(A).unwrap());
+        levels.add(Level::parse(B).unwrap());
+        World::new(levels, 0)
+    }
+
+    #[test]
+    fn test_doors_keep_level_state() {
+        let mut world = world();
+        let input = InputState::default();
+        let door = Vec2 { x: 72.0, y: 24.0 };
+        world.step(&input, DT);
+        assert_eq!(world.level().name(), "a");
+        // pretend we shot the enemy, then walk onto the door
+        let (enemy, _) = world.enemies().next().unwrap();
+        world.components.health.get_mut(enemy).unwrap().hp = 0;
+        world.player_mut(0).pos = door;
+        world.step(&input, DT);
+        assert_eq!(world.level().name(), "b");
+        assert_eq!(world.player(0).pos, Vec2 { x: 24.0, y: 24.0 });
+        assert_eq!(world.player(1).pos, world.player(0).pos);
+        assert_eq!(health(&world), vec![3, 3]);
+        assert!(world.projectiles().next().is_none());
+        // coming back, a's enemy is still gone
+        world.player_mut(1).pos = door;
+        world.step(&input, DT);
+        assert_eq!(world.level().name(), "a");
+        assert!(world.enemies().next().is_none());
+        // we arrived standing on a's door, which shouldn't send us straight back
+        assert_eq!(world.player(0).pos, door);
+        for _ in 0..10 {
+            world.step(&input, DT);
+        }
+        assert_eq!(world.level().name(), "a");
+    }
+
+    #[test]
+    fn test_projectiles_only_hurt_the_other_side() {
+        let mut world = world();
+        let input = InputState::default();
+        // keep the enemy from shooting on its own
+        world.e_attack_timer = ENEMY_ATTACK_COOLDOWN_TIME;
+        let enemy = |world: &World| world.enemies().next().unwrap().1.pos;
+        let e_shot = world.spawn(EntityType::EnemyProjectile, enemy(&world), 0.0);
+        world.step(&input, DT);
+        assert_eq!(health(&world), vec![3]);
+        assert!(world.entities.contains(e_shot));
+
+        let p_shot = world.spawn(EntityType::PlayerProjectile, enemy(&world), 0.0);
+        world.step(&input, DT);
+        assert_eq!(health(&world), vec![2]);
+        // and knock it back the way they were going
+        let (hit, _) = world.enemies().next().unwrap();
+        assert!(world.components.knockback.contains(hit));
+        assert_eq!(
+            world.components.velocity.get(hit),
+            Some(&Velocity(Vec2 {
+                x: KNOCKBACK_SPEED,
+                y: 0.0
+            }))
+        );
+        // spent projectiles are cleared away, so they can't keep hurting
+        assert!(!world.entities.contains(p_shot));
+        world.step(&input, DT);
+        assert_eq!(health(&world), vec![2]);
+
+        // enemy projectiles hit players (both start in the same spot, so move one)
+        world.player_mut(1).pos = Vec2 { x: 88.0, y: 24.0 };
+        world.spawn(EntityType::EnemyProjectile, world.player(0).pos, 0.0);
+        world.step(&input, DT);
+        assert_eq!(world.health(world.players[0]), 0);
+        assert_eq!(world.health(world.players[1]), 1);
+    }
+
+    #[test]
+    fn test_projectiles_run_out_of_bounces() {
+        let mut world = world();
+        let input = InputState::default();
+        world.e_attack_timer = ENEMY_ATTACK_COOLDOWN_TIME;
+        // straight up and down in the corridor, so the walls come every few pixels
+        let shot = world.spawn(
+            EntityType::PlayerProjectile,
+            Vec2 { x: 56.0, y: 24.0 },
+            std::f32::consts::FRAC_PI_2,
+        );
+        let mut despawned = vec![];
+        for _ in 0..30 {
+            world.step(&input, DT);
+            if !world.despawned.is_empty() {
+                despawned.extend(world.despawned.iter().copied());
+                // it leaves a burst where it was
+                let burst = world
+                    .entities
+                    .values()
+                    .find(|entity| entity.etype == EntityType::Burst)
+                    .unwrap();
+                assert_eq!(burst.pos, despawned[0].pos);
+            }
+        }
+        assert_eq!(despawned.len(), 1);
+        assert_eq!(despawned[0].id, shot);
+        assert_eq!(despawned[0].cause, DespawnCause::OutOfBounces);
+        assert!(!world.entities.contains(shot));
+        // and the burst fades
+        for _ in 0..20 {
+            world.step(&input, DT);
+        }
+        assert!(world
+            .entities
+            .values()
+            .all(|e| e.etype != EntityType::Burst));
+        assert_eq!(health(&world), vec![3]);
+    }
+}
@@ -0,0 +1,66 @@
+// `cargo bench -p engine`: times contact gathering for a screen packed with bullets
+// against the players, the enemies and each other, and checks it fits in one frame.
+use engine::collision::gather_contacts;
+use engine::geom::Rect;
+use engine::level::DEFAULT_TILE_SZ;
+use engine::DT;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+const BULLETS: usize = 5000;
+const RUNS: u32 = 100;
+// hash cells a tile across, as the games use
+const CELL_SZ: f32 = DEFAULT_TILE_SZ as f32;
+
+fn rects(rng: &mut StdRng, n: usize, sz: u16) -> Vec<Rect> {
+    (0..n)
+        .map(|_| Rect {
+            x: rng.gen_range(0.0..320.0),
+            y: rng.gen_range(0.0..240.0),
+            w: sz,
+            h: sz,
+        })
+        .collect()
+}
+
+fn naive(objs_a: &[Rect], objs_b: &[Rect]) -> usize {
+    let mut count = 0;
+    for a in objs_a {
+        for b in objs_b {
+            count += a.overlap(*b).is_some() as usize;
+        }
+    }
+    count
+}
+
+fn time(label: &str, mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut contacts = 0;
+    for _ in 0..RUNS {
+        contacts = std::hint::black_box(f());
+    }
+    let each = start.elapsed() / RUNS;
+    println!("{label:>40}: {each:>10.2?} per tick ({contacts} contacts)");
+    each
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let bullets = rects(&mut rng, BULLETS, 4);
+    let players = rects(&mut rng, 2, 12);
+    let enemies = rects(&mut rng, 50, 12);
+
+    let hashed = time("bullets x (players, enemies, bullets)", || {
+        gather_contacts(&bullets, &players, CELL_SZ).len()
+            + gather_contacts(&bullets, &enemies, CELL_SZ).len()
+            + gather_contacts(&bullets, &bullets, CELL_SZ).len()
+    });
+    time("the same without a spatial hash", || {
+        naive(&bullets, &players) + naive(&bullets, &enemies) + naive(&bullets, &bullets)
+    });
+    let budget = Duration::from_secs_f32(DT);
+    assert!(
+        hashed < budget,
+        "{BULLETS} bullets took {hashed:?}, over the {budget:?} frame budget"
+    );
+}
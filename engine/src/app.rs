@@ -0,0 +1,235 @@
+use crate::gamepad::system_pads;
+use crate::input::InputState;
+use crate::split::Split;
+use crate::stepper::FixedStepper;
+use frenderer::Immediate;
+use std::path::PathBuf;
+
+pub const DT: f32 = 1.0 / 60.0;
+
+// A game plugs into the event loop by implementing these hooks.
+pub trait App {
+    fn simulate(&mut self, input: &InputState, dt: f32);
+    // `alpha` is how far (0 to 1) real time has got from the last step towards the
+    // next, for drawing things partway between where they were and where they are
+    fn render(&mut self, frend: &mut Immediate, alpha: f32);
+    // Called once a frame before simulating, so games can pick up hot-reloaded assets
+    fn hot_reload(&mut self, _frend: &mut Immediate) {}
+    // Called when the window is closed, e.g. to save a recording
+    fn exit(&mut self) {}
+}
+
+// Opens a window and runs the game on a fixed DT timestep (see `FixedStepper`).
+// `init` is called once the renderer exists so the game can upload its textures and
+// sprite groups.
+pub fn run<A: App + 'static>(
+    title: &str,
+    render_size: (u32, u32),
+    init: impl FnOnce(&mut Immediate) -> A + 'static,
+) {
+    let drv = frenderer::Driver::new(
+        winit::window::WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(winit::dpi::LogicalSize::new(1024.0, 768.0)),
+        Some(render_size),
+    );
+
+    let mut input = InputState::default();
+    let mut pads = system_pads();
+
+    let mut now = frenderer::clock::Instant::now();
+    let mut stepper = FixedStepper::new(DT);
+    drv.run_event_loop::<(), _>(
+        move |window, frend| {
+            let mut frend = Immediate::new(frend);
+            let app = init(&mut frend);
+            (window, app, frend)
+        },
+        move |event, target, (window, ref mut app, ref mut frend)| {
+            use winit::event::{Event, WindowEvent};
+            match event {
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } => {
+                    app.exit();
+                    target.exit();
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(size),
+                    ..
+                } => {
+                    if !frend.gpu().is_web() {
+                        frend.resize_surface(size.width, size.height);
+                    }
+                    window.request_redraw();
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::RedrawRequested,
+                    ..
+                } => {
+                    let steps = stepper.advance(now.elapsed().as_secs_f32());
+                    now = frenderer::clock::Instant::now();
+                    app.hot_reload(frend);
+                    if let Some(pads) = &mut pads {
+                        input.poll_pads(pads.as_mut());
+                    }
+                    for _ in 0..steps {
+                        app.simulate(&input, DT);
+                        input.next_frame();
+                    }
+                    app.render(frend, stepper.alpha());
+                    frend.render();
+                    window.request_redraw();
+                }
+                event => {
+                    input.process_input_event(&event);
+                }
+            }
+        },
+    )
+    .expect("event loop error");
+}
+
+// The command-line options every game accepts:
+//   --level NAME                start here instead of the game's usual first level
+//   --seed N                    seed the game's RNG so a run can be repeated
+//   --record FILE               save the keys pressed each tick (with the level and seed)
+//                               to FILE when the window closes
+//   --replay FILE               play FILE back instead of reading the keyboard; its
+//                               level and seed replace --level and --seed
+//   --split vertical|horizontal
+//                               give each player their own half of the screen when
+//                               they're too far apart to share it (see `SplitScreen`)
+//   --headless --frames N       skip the window, step the world N times with no keys
+//                               held (or through the replay), and print where everything
+//                               ended up.  With --replay, --frames defaults to its length.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub headless: bool,
+    pub frames: Option<usize>,
+    pub level: Option<String>,
+    pub seed: Option<u64>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub split: Option<Split>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => parsed.headless = true,
+                "--frames" => parsed.frames = Some(number(args.next(), "--frames")?),
+                "--seed" => parsed.seed = Some(number(args.next(), "--seed")?),
+                "--level" => parsed.level = Some(args.next().ok_or("--level needs a level name")?),
+                "--record" => parsed.record = Some(path(args.next(), "--record")?),
+                "--replay" => parsed.replay = Some(path(args.next(), "--replay")?),
+                "--split" => {
+                    let split = args.next().ok_or("--split needs vertical or horizontal")?;
+                    parsed.split = Some(split.parse()?);
+                }
+                other => return Err(format!("unrecognized argument {other:?}")),
+            }
+        }
+        if parsed.frames.is_some() && !parsed.headless {
+            return Err("--frames only makes sense with --headless".to_string());
+        }
+        if parsed.headless && parsed.frames.is_none() && parsed.replay.is_none() {
+            return Err("--headless needs --frames N or --replay FILE".to_string());
+        }
+        if parsed.replay.is_some() && (parsed.level.is_some() || parsed.seed.is_some()) {
+            return Err("a replay already has its own level and seed".to_string());
+        }
+        Ok(parsed)
+    }
+    // The seed to use: --seed if given, then the level's own `seed` line, and
+    // otherwise a random one (print it so the run can be repeated)
+    pub fn seed_or(&self, level_seed: Option<u64>) -> u64 {
+        self.seed.or(level_seed).unwrap_or_else(rand::random)
+    }
+}
+
+fn number<T: std::str::FromStr>(arg: Option<String>, flag: &str) -> Result<T, String> {
+    let arg = arg.ok_or(format!("{flag} needs a number"))?;
+    arg.parse()
+        .map_err(|_| format!("couldn't parse {arg:?} as a number for {flag}"))
+}
+
+fn path(arg: Option<String>, flag: &str) -> Result<PathBuf, String> {
+    arg.map(PathBuf::from)
+        .ok_or(format!("{flag} needs a file name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Result<Args, String> {
+        Args::parse(s.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_args() {
+        assert_eq!(args(""), Ok(Args::default()));
+        assert_eq!(
+            args("--headless --frames 120"),
+            Ok(Args {
+                headless: true,
+                frames: Some(120),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args("--level level6 --headless --frames 1 --seed 7"),
+            Ok(Args {
+                headless: true,
+                frames: Some(1),
+                level: Some("level6".to_string()),
+                seed: Some(7),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args("--level level6"),
+            Ok(Args {
+                level: Some("level6".to_string()),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args("--headless --replay glitch.txt"),
+            Ok(Args {
+                headless: true,
+                replay: Some(PathBuf::from("glitch.txt")),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args("--split vertical"),
+            Ok(Args {
+                split: Some(Split::Vertical),
+                ..Args::default()
+            })
+        );
+        assert!(args("--split").is_err());
+        assert!(args("--split diagonal").is_err());
+        assert!(args("--headless").is_err());
+        assert!(args("--replay glitch.txt --seed 3").is_err());
+        assert!(args("--record").is_err());
+        assert!(args("--frames 10").is_err());
+        assert!(args("--headless --frames ten").is_err());
+        assert!(args("--headless --frames").is_err());
+        assert!(args("--seed").is_err());
+        assert!(args("--fullscreen").is_err());
+    }
+
+    #[test]
+    fn test_seed_order() {
+        let seeded = args("--seed 7").unwrap();
+        assert_eq!(seeded.seed_or(Some(3)), 7);
+        assert_eq!(Args::default().seed_or(Some(3)), 3);
+    }
+}
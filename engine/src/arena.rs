@@ -0,0 +1,204 @@
+// A handle to something in an `Arena`.  Removing the thing bumps its slot's
+// generation, so old handles (say, in a contact from earlier in the frame) stop
+// finding anything instead of finding whatever got put in that slot next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+impl EntityId {
+    // which slot it's in, for keeping other things (like components) beside it
+    pub(crate) fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+// A Vec that reuses the slots of removed things.  Iteration goes in slot order, which
+// only depends on what was inserted and removed when, so seeded games still replay
+// the same way.
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    // empty slots, most recently freed last
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+    pub fn insert(&mut self, value: T) -> EntityId {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            return EntityId {
+                index,
+                generation: slot.generation,
+            };
+        }
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+        EntityId {
+            index: self.slots.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation += 1;
+        self.free.push(id.index);
+        self.len -= 1;
+        Some(value)
+    }
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_ref()
+    }
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_mut()
+    }
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = EntityId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.value.as_ref().map(|value| (id, value))
+        })
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let id = EntityId {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+                slot.value.as_mut().map(|value| (id, value))
+            })
+    }
+    pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+    // Takes out everything `which` picks (in slot order) and reclaims their slots
+    pub fn remove_where(&mut self, mut which: impl FnMut(EntityId, &T) -> bool) -> Vec<T> {
+        let doomed: Vec<EntityId> = self
+            .iter()
+            .filter_map(|(id, value)| which(id, value).then_some(id))
+            .collect();
+        doomed
+            .into_iter()
+            .filter_map(|id| self.remove(id))
+            .collect()
+    }
+    // Removes everything `keep` says no to
+    pub fn retain(&mut self, mut keep: impl FnMut(EntityId, &T) -> bool) {
+        self.remove_where(|id, value| !keep(id, value));
+    }
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+}
+
+impl<T> std::ops::Index<EntityId> for Arena<T> {
+    type Output = T;
+    fn index(&self, id: EntityId) -> &T {
+        self.get(id)
+            .expect("no entity with that id (was it removed?)")
+    }
+}
+
+impl<T> std::ops::IndexMut<EntityId> for Arena<T> {
+    fn index_mut(&mut self, id: EntityId) -> &mut T {
+        self.get_mut(id)
+            .expect("no entity with that id (was it removed?)")
+    }
+}
+
+impl<T> FromIterator<T> for Arena<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut arena = Self::new();
+        for value in iter {
+            arena.insert(value);
+        }
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_ids() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        assert_eq!(arena.remove(a), Some("a"));
+        assert_eq!(arena.remove(a), None);
+        // c gets a's slot, but a's id doesn't find it
+        let c = arena.insert("c");
+        assert_ne!(a, c);
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena[c], "c");
+        assert_eq!(arena[b], "b");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.values().collect::<Vec<_>>(), vec![&"c", &"b"]);
+
+        arena.retain(|_, value| *value != "b");
+        assert!(!arena.contains(b));
+        let d = arena.insert("d");
+        assert_eq!(arena.remove_where(|id, _| id != d), vec!["c"]);
+        assert_eq!(arena.remove_where(|_, _| false), Vec::<&str>::new());
+        assert_eq!(arena.ids().collect::<Vec<_>>(), vec![d]);
+        arena.clear();
+        assert!(arena.is_empty());
+        assert_eq!(arena.get(d), None);
+    }
+}
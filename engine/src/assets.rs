@@ -0,0 +1,74 @@
+use assets_manager::{asset::Png, AssetCache, ReloadId, Storable};
+use frenderer::{wgpu, Immediate};
+
+// Remembers which version of an asset we last used, so a game can notice when
+// hot-reloading swaps in a new one (call `AssetCache::hot_reload` once a frame first).
+pub struct AssetWatch {
+    id: String,
+    seen: ReloadId,
+}
+
+impl AssetWatch {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            seen: ReloadId::NEVER,
+        }
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    // true once each time the cached asset is reloaded
+    pub fn changed<T: Storable>(&mut self, cache: &AssetCache) -> bool {
+        cache
+            .get_cached::<T>(&self.id)
+            .is_some_and(|handle| self.seen.update(handle.last_reload_id()))
+    }
+}
+
+// Uploads the PNG asset `id` as a one-layer array texture, ready for a sprite group
+pub fn load_array_texture(
+    frend: &Immediate,
+    cache: &AssetCache,
+    id: &str,
+) -> Result<wgpu::Texture, assets_manager::Error> {
+    load_array_texture_layers(frend, cache, &[id])
+}
+
+// Uploads several PNG assets as the layers of one array texture, in order, so a
+// sprite group can draw from all of them (pick one with `SheetRegion::with_sheet`).
+// Layers all have to be the same size, so smaller images sit in the top left corner
+// of a layer as big as the biggest one.
+pub fn load_array_texture_layers(
+    frend: &Immediate,
+    cache: &AssetCache,
+    ids: &[&str],
+) -> Result<wgpu::Texture, assets_manager::Error> {
+    let imgs = ids
+        .iter()
+        .map(|id| Ok(cache.load::<Png>(id)?.read().0.to_rgba8()))
+        .collect::<Result<Vec<_>, assets_manager::Error>>()?;
+    let w = imgs.iter().map(|img| img.width()).max().unwrap_or(1);
+    let h = imgs.iter().map(|img| img.height()).max().unwrap_or(1);
+    let layers: Vec<image::RgbaImage> = imgs
+        .into_iter()
+        .map(|img| {
+            if img.dimensions() == (w, h) {
+                return img;
+            }
+            let mut layer = image::RgbaImage::new(w, h);
+            image::imageops::replace(&mut layer, &img, 0, 0);
+            layer
+        })
+        .collect();
+    let layers: Vec<&[u8]> = layers
+        .iter()
+        .map(|layer| layer.as_raw().as_slice())
+        .collect();
+    Ok(frend.create_array_texture(
+        &layers,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        (w, h),
+        Some(&ids.join("+")),
+    ))
+}
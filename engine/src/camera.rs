@@ -0,0 +1,318 @@
+use crate::geom::Vec2;
+use crate::level::Level;
+use frenderer::sprites::Camera2D;
+
+// Keeps the players in view on levels bigger than the screen.  It pans (and zooms
+// out, up to `max_zoom`) to frame everyone it's asked to follow, eases towards that
+// rather than jumping, never shows past the level's edges, and can shake.  It knows
+// nothing about the renderer besides handing out a `Camera2D`, so a test can follow
+// some points around and ask where they'd land on screen.
+pub struct Camera {
+    // how much of the world fits on screen at zoom 1, in pixels (the game's render size)
+    view: Vec2,
+    // the level being shown and its size in pixels
+    level: Option<String>,
+    bounds: Vec2,
+    // the middle of the view in world pixels, and how many world pixels each screen
+    // pixel covers (2 shows twice as much world each way)
+    center: Vec2,
+    zoom: f32,
+    // where it was before the last `follow`, for drawing between steps
+    prev_center: Vec2,
+    prev_zoom: f32,
+    pub max_zoom: f32,
+    // room to leave between whoever it's following and the edge of the view
+    pub margin: f32,
+    // how hard it's shaking (in pixels), for how many more seconds out of how many
+    shake_strength: f32,
+    shake_left: f32,
+    shake_time: f32,
+    // seconds followed so far, which drives the shake
+    clock: f32,
+}
+
+// the camera closes this much of the distance to where it wants to be, per second,
+// as a rate (so about 1 - e^-8 of it after one second)
+const FOLLOW_RATE: f32 = 8.0;
+
+impl Camera {
+    pub fn new(view_w: f32, view_h: f32) -> Self {
+        let view = Vec2 {
+            x: view_w,
+            y: view_h,
+        };
+        Self {
+            view,
+            level: None,
+            bounds: view,
+            center: view * 0.5,
+            zoom: 1.0,
+            prev_center: view * 0.5,
+            prev_zoom: 1.0,
+            max_zoom: 2.0,
+            margin: 32.0,
+            shake_strength: 0.0,
+            shake_left: 0.0,
+            shake_time: 0.0,
+            clock: 0.0,
+        }
+    }
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+    // Moves `dt` seconds' worth towards framing `targets` in `level`.  On a level it
+    // wasn't showing before it cuts straight there instead.
+    pub fn follow<E>(&mut self, level: &Level<E>, targets: &[Vec2], dt: f32) {
+        self.prev_center = self.center;
+        self.prev_zoom = self.zoom;
+        self.clock += dt;
+        self.shake_left = (self.shake_left - dt).max(0.0);
+        let tile_sz = level.tile_sz() as f32;
+        self.bounds = Vec2 {
+            x: level.width() as f32 * tile_sz,
+            y: level.height() as f32 * tile_sz,
+        };
+        let (center, zoom) = self.target(targets);
+        if self.level.as_deref() != Some(level.name()) {
+            self.level = Some(level.name().to_string());
+            self.center = center;
+            self.zoom = zoom;
+            self.prev_center = center;
+            self.prev_zoom = zoom;
+            return;
+        }
+        let t = 1.0 - (-FOLLOW_RATE * dt).exp();
+        self.zoom += (zoom - self.zoom) * t;
+        self.center = self.clamp(self.center.lerp(center, t), self.view * self.zoom);
+    }
+    // Shakes the view up to `strength` pixels off, fading out over `time` seconds.
+    // A weaker shake doesn't cut a stronger one short.
+    pub fn shake(&mut self, strength: f32, time: f32) {
+        if strength >= self.shake_offset_strength() {
+            self.shake_strength = strength;
+            self.shake_left = time;
+            self.shake_time = time;
+        }
+    }
+    // How far the shake has the view off right now
+    pub fn shake_offset(&self) -> Vec2 {
+        let strength = self.shake_offset_strength();
+        Vec2 {
+            x: (self.clock * 71.0).sin() * strength,
+            y: (self.clock * 53.0).cos() * strength,
+        }
+    }
+    // The camera to draw with, `alpha` of the way from before the last `follow` to now
+    pub fn view(&self, alpha: f32) -> Camera2D {
+        let zoom = self.prev_zoom + (self.zoom - self.prev_zoom) * alpha;
+        let size = self.view * zoom;
+        let corner = self.prev_center.lerp(self.center, alpha) - size * 0.5 + self.shake_offset();
+        Camera2D {
+            screen_pos: [corner.x, corner.y],
+            screen_size: [size.x, size.y],
+        }
+    }
+    // Where `pos` shows up in the game's render target, in its pixels from the
+    // bottom left
+    pub fn world_to_screen(&self, pos: Vec2) -> Vec2 {
+        let camera = self.view(1.0);
+        Vec2 {
+            x: (pos.x - camera.screen_pos[0]) / self.zoom,
+            y: (pos.y - camera.screen_pos[1]) / self.zoom,
+        }
+    }
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        let camera = self.view(1.0);
+        Vec2 {
+            x: pos.x * self.zoom + camera.screen_pos[0],
+            y: pos.y * self.zoom + camera.screen_pos[1],
+        }
+    }
+
+    // How far out it would have to zoom to fit all of `targets` (plus the margin),
+    // ignoring `max_zoom` but no further than it takes to show the whole level.  Past
+    // `max_zoom` is where a split screen gives up on sharing.
+    pub fn fit_zoom(&self, targets: &[Vec2]) -> f32 {
+        let Some((min, max)) = extent(targets) else {
+            return self.zoom;
+        };
+        let need = max - min + Vec2 { x: 2.0, y: 2.0 } * self.margin;
+        let whole_level = (self.bounds.x / self.view.x).max(self.bounds.y / self.view.y);
+        (need.x / self.view.x)
+            .max(need.y / self.view.y)
+            .min(whole_level)
+            .max(1.0)
+    }
+
+    fn shake_offset_strength(&self) -> f32 {
+        if self.shake_left <= 0.0 {
+            0.0
+        } else {
+            self.shake_strength * self.shake_left / self.shake_time
+        }
+    }
+    // The middle and zoom that'd fit all of `targets` (plus the margin) on screen,
+    // zooming out no further than `max_zoom` or than it takes to show the whole level
+    fn target(&self, targets: &[Vec2]) -> (Vec2, f32) {
+        let Some((min, max)) = extent(targets) else {
+            return (self.center, self.zoom);
+        };
+        let zoom = self.fit_zoom(targets).min(self.max_zoom.max(1.0));
+        let center = (min + max) * 0.5;
+        (self.clamp(center, self.view * zoom), zoom)
+    }
+    // Moves a view of `size` centered on `center` back inside the level.  Along an
+    // axis where the level's smaller than the view, the level goes in the middle.
+    fn clamp(&self, center: Vec2, size: Vec2) -> Vec2 {
+        let axis = |center: f32, size: f32, bound: f32| {
+            if size >= bound {
+                bound / 2.0
+            } else {
+                center.clamp(size / 2.0, bound - size / 2.0)
+            }
+        };
+        Vec2 {
+            x: axis(center.x, size.x, self.bounds.x),
+            y: axis(center.y, size.y, self.bounds.y),
+        }
+    }
+}
+
+// the corners of the smallest box holding all of `points`
+fn extent(points: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    let first = *points.first()?;
+    Some(points.iter().fold((first, first), |(min, max), p| {
+        (
+            Vec2 {
+                x: min.x.min(p.x),
+                y: min.y.min(p.y),
+            },
+            Vec2 {
+                x: max.x.max(p.x),
+                y: max.y.max(p.y),
+            },
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::StartKind;
+
+    struct NoStarts;
+    impl StartKind for NoStarts {
+        fn from_start(_kind: &str, _args: &[&str]) -> Option<Self> {
+            None
+        }
+    }
+
+    // an empty level `w` by `h` tiles, like level5's 49x19
+    fn level(name: &str, w: usize, h: usize) -> Level<NoStarts> {
+        let rows = vec![".".repeat(w); h].join("\n");
+        Level::parse(&format!(
+            "{name} {w} {h}\n====\n. o 0 0 16 16\n====\n{rows}\n====\n"
+        ))
+        .unwrap()
+    }
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a - b).mag_sq() < 1e-2
+    }
+
+    #[test]
+    fn test_follows_and_clamps() {
+        let big = level("big", 49, 19);
+        let mut camera = Camera::new(320.0, 240.0);
+        // the first follow cuts straight to the player, and the screen's middle is them
+        let player = Vec2 { x: 400.0, y: 150.0 };
+        camera.follow(&big, &[player], 1.0 / 60.0);
+        assert_eq!(camera.center(), player);
+        assert!(close(
+            camera.world_to_screen(player),
+            Vec2 { x: 160.0, y: 120.0 }
+        ));
+        assert!(close(
+            camera.screen_to_world(Vec2 { x: 0.0, y: 0.0 }),
+            player - Vec2 { x: 160.0, y: 120.0 }
+        ));
+
+        // walking right, it eases after them instead of jumping
+        let moved = Vec2 { x: 500.0, y: 150.0 };
+        camera.follow(&big, &[moved], 1.0 / 60.0);
+        assert!(camera.center().x > 400.0 && camera.center().x < 450.0);
+        for _ in 0..120 {
+            camera.follow(&big, &[moved], 1.0 / 60.0);
+        }
+        assert!(close(camera.center(), moved));
+        // halfway between steps it's drawn halfway
+        camera.follow(&big, &[player], 1.0 / 60.0);
+        let (before, after) = (camera.prev_center, camera.center());
+        let halfway = camera.view(0.5);
+        assert!((halfway.screen_pos[0] + 160.0 - (before.x + after.x) / 2.0).abs() < 1e-3);
+
+        // near the corner it stops at the level's edge (784x304 pixels)
+        let mut camera = Camera::new(320.0, 240.0);
+        camera.follow(&big, &[Vec2 { x: 10.0, y: 300.0 }], 1.0 / 60.0);
+        assert_eq!(camera.view(1.0).screen_pos, [0.0, 64.0]);
+        // a level smaller than the screen sits in the middle of it
+        let small = level("small", 10, 5);
+        camera.follow(&small, &[Vec2 { x: 10.0, y: 10.0 }], 1.0 / 60.0);
+        assert_eq!(camera.center(), Vec2 { x: 80.0, y: 40.0 });
+        assert_eq!(camera.zoom(), 1.0);
+    }
+
+    #[test]
+    fn test_frames_both_players() {
+        let big = level("big", 49, 19);
+        let mut camera = Camera::new(320.0, 240.0);
+        // 400 pixels apart plus the margins needs 464 across: a zoom of 1.45
+        let players = [Vec2 { x: 100.0, y: 150.0 }, Vec2 { x: 500.0, y: 150.0 }];
+        camera.follow(&big, &players, 1.0 / 60.0);
+        assert!((camera.zoom() - 1.45).abs() < 1e-4);
+        for player in players {
+            let on_screen = camera.world_to_screen(player);
+            assert!(on_screen.x > 0.0 && on_screen.x < 320.0);
+        }
+        // but no further out than max_zoom, even if that leaves someone off screen
+        camera.follow(
+            &big,
+            &[Vec2 { x: 10.0, y: 150.0 }, Vec2 { x: 770.0, y: 150.0 }],
+            10.0,
+        );
+        assert!((camera.zoom() - 2.0).abs() < 1e-4);
+        let size = camera.view(1.0).screen_size;
+        assert!((size[0] - 640.0).abs() < 1e-3 && (size[1] - 480.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_shake_fades() {
+        let big = level("big", 49, 19);
+        let mut camera = Camera::new(320.0, 240.0);
+        let player = Vec2 { x: 400.0, y: 150.0 };
+        camera.follow(&big, &[player], 1.0 / 60.0);
+        camera.shake(4.0, 0.5);
+        // a smaller shake doesn't cut it short
+        camera.shake(0.5, 1.0);
+        assert_eq!(camera.shake_time, 0.5);
+        let mut biggest: f32 = 0.0;
+        for _ in 0..30 {
+            camera.follow(&big, &[player], 1.0 / 60.0);
+            let offset = camera.shake_offset();
+            assert!(offset.x.abs() <= 4.0 && offset.y.abs() <= 4.0);
+            biggest = biggest.max(offset.mag());
+        }
+        assert!(biggest > 1.0);
+        for _ in 0..10 {
+            camera.follow(&big, &[player], 1.0 / 60.0);
+        }
+        assert_eq!(camera.shake_offset(), Vec2::ZERO);
+        assert!(close(
+            camera.world_to_screen(player),
+            Vec2 { x: 160.0, y: 120.0 }
+        ));
+    }
+}
@@ -0,0 +1,438 @@
+use crate::geom::*;
+use crate::level::{Level, TileData};
+use crate::spatial::SpatialHash;
+
+//necessary structs and functions for collision detection
+pub struct Contact {
+    pub a_i: usize,
+    pub a_r: Rect,
+    pub b_i: usize,
+    pub b_r: Rect,
+    pub displacement: Vec2,
+}
+
+// Every overlapping (a, b) pair, in the order a nested loop over a then b would find
+// them.  Only pairs that share a `cell_sz` cell of a spatial hash get tested; the
+// level's tile size (`Level::tile_sz`) suits things about a tile across.
+pub fn gather_contacts(objs_a: &[Rect], objs_b: &[Rect], cell_sz: f32) -> Vec<Contact> {
+    let mut contacts: Vec<Contact> = Vec::new();
+
+    let hash = SpatialHash::build(cell_sz, objs_b);
+    for (a_idx, b_idx) in hash.candidate_pairs(objs_a) {
+        let (a_rect, b_rect) = (objs_a[a_idx], objs_b[b_idx]);
+        if let Some(overlap) = a_rect.overlap(b_rect) {
+            contacts.push(Contact {
+                a_i: a_idx,
+                a_r: a_rect,
+                b_i: b_idx,
+                b_r: b_rect,
+                displacement: overlap,
+            })
+        }
+    }
+    contacts
+}
+
+pub fn gather_level_contacts<E>(objs: &[Rect], level: &Level<E>) -> Vec<Contact> {
+    gather_level_contacts_where(objs, level, |tile_data| tile_data.solid)
+}
+
+// like gather_level_contacts, but only against the tiles `which` picks out
+// (e.g. the slippery ones)
+pub fn gather_level_contacts_where<E>(
+    objs: &[Rect],
+    level: &Level<E>,
+    which: impl Fn(&TileData) -> bool,
+) -> Vec<Contact> {
+    let mut contacts: Vec<Contact> = Vec::new();
+
+    for (a_idx, a_rect) in objs.iter().enumerate() {
+        for (b_idx, (b_rect, tile_data)) in level.tiles_within(*a_rect).enumerate() {
+            if which(tile_data) {
+                if let Some(overlap) = a_rect.overlap(b_rect) {
+                    contacts.push(Contact {
+                        a_i: a_idx,
+                        a_r: *a_rect,
+                        b_i: b_idx,
+                        b_r: b_rect,
+                        displacement: overlap,
+                    });
+                }
+            }
+        }
+    }
+    contacts
+}
+
+// Like Contact, but `displacement` is a signed push that moves a out of b and
+// `normal` is its direction (see `Penetration`)
+pub struct Contact2 {
+    pub a_i: usize,
+    pub a_r: Shape,
+    pub b_i: usize,
+    pub b_r: Shape,
+    pub displacement: Vec2,
+    pub normal: Vec2,
+}
+
+// gather_contacts, for shapes
+pub fn gather_contacts_2(objs_a: &[Shape], objs_b: &[Shape], cell_sz: f32) -> Vec<Contact2> {
+    let mut contacts: Vec<Contact2> = Vec::new();
+
+    let bounds_a: Vec<Rect> = objs_a.iter().map(Shape::bounds).collect();
+    let bounds_b: Vec<Rect> = objs_b.iter().map(Shape::bounds).collect();
+    let hash = SpatialHash::build(cell_sz, &bounds_b);
+    for (a_idx, b_idx) in hash.candidate_pairs(&bounds_a) {
+        let (a_shape, b_shape) = (objs_a[a_idx], objs_b[b_idx]);
+        if let Some(pen) = a_shape.penetration(b_shape) {
+            contacts.push(Contact2 {
+                a_i: a_idx,
+                a_r: a_shape,
+                b_i: b_idx,
+                b_r: b_shape,
+                displacement: pen.mtv(),
+                normal: pen.normal,
+            })
+        }
+    }
+    contacts
+}
+
+pub fn gather_level_contacts_2<E>(objs: &[Shape], level: &Level<E>) -> Vec<Contact2> {
+    let mut contacts: Vec<Contact2> = Vec::new();
+    let mut a_rect: Rect;
+
+    //edit tiles_within
+    for (a_idx, a_shape) in objs.iter().enumerate() {
+        match a_shape {
+            Shape::Circle(circle) => {
+                a_rect = circle.bounds();
+            }
+            Shape::Rect(rect) => {
+                a_rect = *rect;
+            }
+            Shape::Oriented(orect) => {
+                a_rect = orect.bounds();
+            }
+        }
+
+        for (b_idx, (b_rect, tile_data)) in level.tiles_within(a_rect).enumerate() {
+            let b_shape = Shape::Rect(b_rect);
+
+            if tile_data.solid {
+                if let Some(pen) = a_shape.penetration(b_shape) {
+                    contacts.push(Contact2 {
+                        a_i: a_idx,
+                        a_r: *a_shape,
+                        b_i: b_idx,
+                        b_r: b_shape,
+                        displacement: pen.mtv(),
+                        normal: pen.normal,
+                    });
+                }
+            }
+        }
+    }
+    contacts
+}
+
+// sort_contacts, for shape contacts
+pub fn sort_contacts_2(contacts: &mut [Contact2]) {
+    contacts.sort_by(|a, b| {
+        b.displacement
+            .mag_sq()
+            .partial_cmp(&a.displacement.mag_sq())
+            .unwrap()
+    });
+}
+
+// biggest overlaps first, so the deepest penetration gets resolved before the rest
+pub fn sort_contacts(contacts: &mut [Contact]) {
+    contacts.sort_by(|a, b| {
+        b.displacement
+            .mag_sq()
+            .partial_cmp(&a.displacement.mag_sq())
+            .unwrap()
+    });
+}
+
+// Where a box moving by `delta` first runs into something: `toi` (time of impact)
+// is how much of `delta` it gets through, from 0 to 1, and `normal` is the face it hit
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SweepHit {
+    pub toi: f32,
+    pub normal: Vec2,
+}
+
+// Swept AABB test of `a` moving by `delta` against a still `b`.  Boxes that already
+// overlap are hit straight away (at 0, with the normal of the shortest way out) if
+// they're moving further in, so nothing that starts a step partway into a wall can
+// tunnel through it.  Ones moving apart don't count, so something resting on or
+// stuck in a wall can always leave it.
+pub fn sweep_rect(a: Rect, delta: Vec2, b: Rect) -> Option<SweepHit> {
+    if let Some(pen) = Shape::Rect(a).penetration(Shape::Rect(b)) {
+        if pen.depth > 0.0 {
+            return (delta.dot(pen.normal) < 0.0).then_some(SweepHit {
+                toi: 0.0,
+                normal: pen.normal,
+            });
+        }
+    }
+    // when a enters and leaves b's span on one axis
+    fn span(a0: f32, a1: f32, b0: f32, b1: f32, d: f32) -> (f32, f32) {
+        if d > 0.0 {
+            ((b0 - a1) / d, (b1 - a0) / d)
+        } else if d < 0.0 {
+            ((b1 - a0) / d, (b0 - a1) / d)
+        } else if a1 > b0 && a0 < b1 {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        }
+    }
+    let (ax1, ay1) = (a.x + a.w as f32, a.y + a.h as f32);
+    let (bx1, by1) = (b.x + b.w as f32, b.y + b.h as f32);
+    let (x_in, x_out) = span(a.x, ax1, b.x, bx1, delta.x);
+    let (y_in, y_out) = span(a.y, ay1, b.y, by1, delta.y);
+    let toi = x_in.max(y_in);
+    if toi >= x_out.min(y_out) || !(0.0..=1.0).contains(&toi) {
+        return None;
+    }
+    let normal = if x_in >= y_in {
+        Vec2 {
+            x: -delta.x.signum(),
+            y: 0.0,
+        }
+    } else {
+        Vec2 {
+            x: 0.0,
+            y: -delta.y.signum(),
+        }
+    };
+    Some(SweepHit { toi, normal })
+}
+
+// The first tile `which` picks out that `rect` hits on its way along `delta`, so fast
+// things can't skip over thin walls between frames
+pub fn sweep_level<E>(
+    rect: Rect,
+    delta: Vec2,
+    level: &Level<E>,
+    which: impl Fn(&TileData) -> bool,
+) -> Option<SweepHit> {
+    // every tile the box passes over
+    let area = Rect {
+        x: rect.x + delta.x.min(0.0),
+        y: rect.y + delta.y.min(0.0),
+        w: rect.w + delta.x.abs().ceil() as u16,
+        h: rect.h + delta.y.abs().ceil() as u16,
+    };
+    level
+        .tiles_within(area)
+        .filter(|(_, tile_data)| which(tile_data))
+        .filter_map(|(tile, _)| sweep_rect(rect, delta, tile))
+        .min_by(|a, b| a.toi.total_cmp(&b.toi))
+}
+
+// Where something ended up after `sweep_bounce`: how far it moved, which way it's
+// heading now, and whether it stopped at a wall it wasn't allowed to bounce off
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bounced {
+    pub delta: Vec2,
+    pub dir: f32,
+    pub stopped: bool,
+}
+
+// Moves a box `dist` along heading `dir` (radians, as for `dir_to_vec2`) through the
+// tiles `which` picks out.  If it would hit one on the way it stops there, reflects
+// about the face's normal and spends the rest of `dist` going the new way, so even
+// fast things can't pass through thin walls.  `bounce` is asked before each
+// reflection (so it can spend one of a projectile's bounces); if it says no, the box
+// stops at the wall instead.
+pub fn sweep_bounce<E>(
+    rect: Rect,
+    dir: f32,
+    dist: f32,
+    level: &Level<E>,
+    which: impl Fn(&TileData) -> bool,
+    mut bounce: impl FnMut() -> bool,
+) -> Bounced {
+    let mut moved = Bounced {
+        delta: Vec2::ZERO,
+        dir,
+        stopped: false,
+    };
+    let mut left = dist;
+    // a few bounces at most, in case it's wedged into a corner
+    for _ in 0..4 {
+        let vel = dir_to_vec2(moved.dir);
+        let step = vel * left;
+        let from = Rect {
+            x: rect.x + moved.delta.x,
+            y: rect.y + moved.delta.y,
+            ..rect
+        };
+        let Some(hit) = sweep_level(from, step, level, &which) else {
+            moved.delta += step;
+            break;
+        };
+        moved.delta += step * hit.toi;
+        if !bounce() {
+            moved.stopped = true;
+            break;
+        }
+        moved.dir = vec2_to_dir(vel - hit.normal * (2.0 * vel.dot(hit.normal)));
+        left *= 1.0 - hit.toi;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_hashed_contacts_match_every_pair() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut rects = |n| -> Vec<Rect> {
+            (0..n)
+                .map(|_| Rect {
+                    x: rng.gen_range(-20.0..200.0),
+                    y: rng.gen_range(-20.0..200.0),
+                    w: rng.gen_range(0..40),
+                    h: rng.gen_range(0..40),
+                })
+                .collect()
+        };
+        let (a, b) = (rects(300), rects(200));
+        let mut expected = Vec::new();
+        for (a_i, a_r) in a.iter().enumerate() {
+            for (b_i, b_r) in b.iter().enumerate() {
+                if let Some(overlap) = a_r.overlap(*b_r) {
+                    expected.push((a_i, b_i, overlap));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        // whatever the cell size (e.g. a `tile 32` level's), only the speed changes
+        for cell_sz in [16.0, 32.0, 7.0] {
+            let found: Vec<_> = gather_contacts(&a, &b, cell_sz)
+                .into_iter()
+                .map(|c| (c.a_i, c.b_i, c.displacement))
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_sweep_rect() {
+        let wall = Rect {
+            x: 16.0,
+            y: 0.0,
+            w: 16,
+            h: 16,
+        };
+        let bullet = Rect {
+            x: 4.0,
+            y: 6.0,
+            w: 4,
+            h: 4,
+        };
+        let right = |x| Vec2 { x, y: 0.0 };
+        // far enough to pass right through the wall, but it stops at the near face
+        assert_eq!(
+            sweep_rect(bullet, right(40.0), wall),
+            Some(SweepHit {
+                toi: 0.2,
+                normal: right(-1.0)
+            })
+        );
+        assert_eq!(sweep_rect(bullet, right(7.0), wall), None);
+        assert_eq!(sweep_rect(bullet, right(-40.0), wall), None);
+        // coming in at an angle over the top, it's the top face it meets
+        let hit = sweep_rect(
+            Rect {
+                x: 20.0,
+                y: 20.0,
+                ..bullet
+            },
+            Vec2 { x: 2.0, y: -8.0 },
+            wall,
+        )
+        .unwrap();
+        assert_eq!(hit.normal, Vec2 { x: 0.0, y: 1.0 });
+        assert_eq!(hit.toi, 0.5);
+        // sliding along the top doesn't catch on it
+        let on_top = Rect {
+            x: 0.0,
+            y: 16.0,
+            ..bullet
+        };
+        assert_eq!(sweep_rect(on_top, right(40.0), wall), None);
+        // and something resting against it can back away
+        let touching = Rect { x: 12.0, ..bullet };
+        assert_eq!(sweep_rect(touching, right(-4.0), wall), None);
+        assert_eq!(sweep_rect(touching, right(4.0), wall).unwrap().toi, 0.0);
+        // one that starts a little way in hits it right away, unless it's leaving
+        let inside = Rect { x: 13.0, ..bullet };
+        assert_eq!(
+            sweep_rect(inside, right(40.0), wall),
+            Some(SweepHit {
+                toi: 0.0,
+                normal: right(-1.0)
+            })
+        );
+        assert_eq!(sweep_rect(inside, right(-4.0), wall), None);
+    }
+
+    struct NoStarts;
+    impl crate::level::StartKind for NoStarts {
+        fn from_start(_kind: &str, _args: &[&str]) -> Option<Self> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_sweep_bounce() {
+        // a one-tile wall from x = 48 to 64
+        let level = Level::<NoStarts>::parse(
+            "thin 7 3\n====\n. o 0 0 16 16\n# s 16 0 16 16\n====\n#######\n#..#..#\n#######\n",
+        )
+        .unwrap();
+        let bullet = Rect {
+            x: 22.0,
+            y: 22.0,
+            w: 4,
+            h: 4,
+        };
+        // 40 pixels would take it right past the wall, but it meets it after 22 and
+        // comes back the other 18
+        let mut bounces = 0;
+        let moved = sweep_bounce(
+            bullet,
+            0.0,
+            40.0,
+            &level,
+            |tile| tile.solid,
+            || {
+                bounces += 1;
+                true
+            },
+        );
+        assert_eq!(bounces, 1);
+        assert!((moved.delta.x - 4.0).abs() < 1e-3);
+        assert!(moved.delta.y.abs() < 1e-3);
+        assert!(dir_to_vec2(moved.dir).x < -0.99);
+        assert!(!moved.stopped);
+        // without a bounce to spend it stops at the wall, still heading into it
+        let moved = sweep_bounce(bullet, 0.0, 40.0, &level, |tile| tile.solid, || false);
+        assert!((moved.delta.x - 22.0).abs() < 1e-3);
+        assert_eq!(moved.dir, 0.0);
+        assert!(moved.stopped);
+        // and nothing in the way means it just goes
+        let moved = sweep_bounce(bullet, 0.0, 10.0, &level, |tile| tile.solid, || false);
+        assert!((moved.delta.x - 10.0).abs() < 1e-3);
+        assert!(!moved.stopped);
+    }
+}
@@ -0,0 +1,505 @@
+use crate::gamepad::PadAxis;
+use crate::input::{InputState, Key};
+use assets_manager::AssetCache;
+
+// What a player can ask their tank (or bird) to do.  Games read these instead of keys,
+// so which keys mean what lives in one place: content/controls.txt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    TurnLeft,
+    TurnRight,
+    Forward,
+    Back,
+    Fire,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Forward,
+        Action::Back,
+        Action::Fire,
+    ];
+    // how it's written in controls.txt
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Forward => "forward",
+            Action::Back => "back",
+            Action::Fire => "fire",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// The keys controls.txt can name, written the way `{:?}` prints them
+const KEYS: &[Key] = &[
+    Key::KeyA,
+    Key::KeyB,
+    Key::KeyC,
+    Key::KeyD,
+    Key::KeyE,
+    Key::KeyF,
+    Key::KeyG,
+    Key::KeyH,
+    Key::KeyI,
+    Key::KeyJ,
+    Key::KeyK,
+    Key::KeyL,
+    Key::KeyM,
+    Key::KeyN,
+    Key::KeyO,
+    Key::KeyP,
+    Key::KeyQ,
+    Key::KeyR,
+    Key::KeyS,
+    Key::KeyT,
+    Key::KeyU,
+    Key::KeyV,
+    Key::KeyW,
+    Key::KeyX,
+    Key::KeyY,
+    Key::KeyZ,
+    Key::Digit0,
+    Key::Digit1,
+    Key::Digit2,
+    Key::Digit3,
+    Key::Digit4,
+    Key::Digit5,
+    Key::Digit6,
+    Key::Digit7,
+    Key::Digit8,
+    Key::Digit9,
+    Key::ArrowUp,
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::Space,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::AltLeft,
+    Key::AltRight,
+    Key::Comma,
+    Key::Period,
+    Key::Slash,
+    Key::Semicolon,
+    Key::Quote,
+    Key::BracketLeft,
+    Key::BracketRight,
+    Key::Backslash,
+    Key::Minus,
+    Key::Equal,
+    Key::Numpad0,
+    Key::Numpad1,
+    Key::Numpad2,
+    Key::Numpad3,
+    Key::Numpad4,
+    Key::Numpad5,
+    Key::Numpad6,
+    Key::Numpad7,
+    Key::Numpad8,
+    Key::Numpad9,
+    Key::NumpadEnter,
+    Key::NumpadAdd,
+    Key::NumpadSubtract,
+];
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEYS.iter().copied().find(|key| format!("{key:?}") == name)
+}
+
+// A named set of keys for every action, e.g. the arrow keys plus space
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scheme {
+    pub name: String,
+    // by `Action` (in `Action::ALL` order)
+    keys: [Vec<Key>; 5],
+}
+
+impl Scheme {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            keys: Default::default(),
+        }
+    }
+    pub fn keys(&self, action: Action) -> &[Key] {
+        &self.keys[action as usize]
+    }
+    // Adds another key for `action`
+    pub fn add(&mut self, action: Action, key: Key) {
+        if !self.keys[action as usize].contains(&key) {
+            self.keys[action as usize].push(key);
+        }
+    }
+    // Makes `key` the only key for `action`, taking it away from any other action
+    pub fn bind(&mut self, action: Action, key: Key) {
+        for keys in self.keys.iter_mut() {
+            keys.retain(|k| *k != key);
+        }
+        self.keys[action as usize] = vec![key];
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ControlsError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl std::fmt::Display for ControlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for ControlsError {}
+
+// Every scheme controls.txt defines, and the one each local player is using.  Players
+// get their own copy of their scheme, so rebinding player 2 doesn't move player 1's
+// keys even if they started out the same.  There can be any number of players.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Controls {
+    schemes: Vec<Scheme>,
+    players: Vec<Scheme>,
+}
+
+// The controls.txt this was built with: arrows and space for player 1, WASD and Q
+// for player 2
+impl Default for Controls {
+    fn default() -> Self {
+        include_str!("../content/controls.txt")
+            .parse()
+            .expect("content/controls.txt should parse")
+    }
+}
+
+impl std::str::FromStr for Controls {
+    type Err = ControlsError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Controls {
+    // The format is a line per key, grouped under the scheme it's for:
+    //   scheme NAME
+    //   ACTION KEY        (e.g. `fire Space`; repeat the action to give it more keys)
+    //   player NAME       (the next player starts with that scheme)
+    // Blank lines and lines starting with # are skipped.
+    pub fn parse(text: &str) -> Result<Self, ControlsError> {
+        let mut schemes: Vec<Scheme> = Vec::new();
+        let mut player_schemes = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let error = |msg: String| ControlsError { line: line_no, msg };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+                [comment, ..] if comment.starts_with('#') => {}
+                ["scheme", name] => {
+                    if schemes.iter().any(|scheme| scheme.name == name) {
+                        return Err(error(format!("scheme {name} is defined twice")));
+                    }
+                    schemes.push(Scheme::new(name));
+                }
+                ["player", name] => player_schemes.push((line_no, name)),
+                [action, key] => {
+                    let action = Action::from_name(action)
+                        .ok_or_else(|| error(format!("unknown action {action:?}")))?;
+                    let key =
+                        key_from_name(key).ok_or_else(|| error(format!("unknown key {key:?}")))?;
+                    schemes
+                        .last_mut()
+                        .ok_or_else(|| error("keys need a `scheme NAME` line first".to_string()))?
+                        .add(action, key);
+                }
+                _ => return Err(error(format!("couldn't make sense of {line:?}"))),
+            }
+        }
+        let mut players = Vec::new();
+        for (line, name) in player_schemes {
+            let scheme = schemes
+                .iter()
+                .find(|scheme| scheme.name == name)
+                .ok_or_else(|| ControlsError {
+                    line,
+                    msg: format!("no scheme named {name}"),
+                })?;
+            players.push(scheme.clone());
+        }
+        Ok(Self { schemes, players })
+    }
+    // Loads controls.txt (or whichever text asset `id` names) from `cache`
+    pub fn load(cache: &AssetCache, id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let handle = cache.load::<String>(id)?;
+        let controls = Self::parse(&handle.read())?;
+        Ok(controls)
+    }
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+    pub fn scheme(&self, player: usize) -> &Scheme {
+        &self.players[player]
+    }
+    pub fn schemes(&self) -> impl Iterator<Item = &Scheme> {
+        self.schemes.iter()
+    }
+    // Gives `player` a fresh copy of the scheme called `name`; false if there isn't one
+    pub fn set_scheme(&mut self, player: usize, name: &str) -> bool {
+        let Some(scheme) = self.schemes.iter().find(|scheme| scheme.name == name) else {
+            return false;
+        };
+        self.players[player] = scheme.clone();
+        true
+    }
+    // Trades two players' keys, rebindings and all
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.players.swap(a, b);
+    }
+    pub fn bind(&mut self, player: usize, action: Action, key: Key) {
+        self.players[player].bind(action, key);
+    }
+    // Every key any player's action uses, which is what a replay needs to record
+    pub fn keys(&self) -> Vec<Key> {
+        let mut keys: Vec<Key> = Vec::new();
+        for scheme in self.players.iter() {
+            for action in Action::ALL {
+                for key in scheme.keys(action) {
+                    if !keys.contains(key) {
+                        keys.push(*key);
+                    }
+                }
+            }
+        }
+        keys
+    }
+    // On the player's keys or their gamepad.  Players past the last `player` line have
+    // no keys, but can still have a pad.
+    pub fn is_down(&self, input: &InputState, player: usize, action: Action) -> bool {
+        let key_down = self.players.get(player).is_some_and(|scheme| {
+            scheme
+                .keys(action)
+                .iter()
+                .any(|key| input.is_key_down(*key))
+        });
+        key_down || input.pads().is_down(player, action)
+    }
+    // Down this frame but not last frame (on any of its keys, or the pad)
+    pub fn is_pressed(&self, input: &InputState, player: usize, action: Action) -> bool {
+        let key_pressed = self.players.get(player).is_some_and(|scheme| {
+            let keys = scheme.keys(action);
+            keys.iter().any(|key| input.is_key_pressed(*key))
+                && !keys
+                    .iter()
+                    .any(|key| input.is_key_down(*key) && !input.is_key_pressed(*key))
+        });
+        key_pressed || input.pads().is_pressed(player, action)
+    }
+    // How hard to turn left, from -1 (full right) to 1.  Keys and the d-pad turn all
+    // the way; the left stick turns as far as it's pushed.
+    pub fn turn(&self, input: &InputState, player: usize) -> f32 {
+        let digital = self.axis(input, player, Action::TurnRight, Action::TurnLeft);
+        let stick = -input.pads().axis(player, PadAxis::LeftStickX);
+        (digital + stick).clamp(-1.0, 1.0)
+    }
+    // How hard to drive forward, from -1 (full reverse) to 1
+    pub fn throttle(&self, input: &InputState, player: usize) -> f32 {
+        let digital = self.axis(input, player, Action::Back, Action::Forward);
+        let stick = input.pads().axis(player, PadAxis::LeftStickY);
+        (digital + stick).clamp(-1.0, 1.0)
+    }
+    // -1 if only `down` is held, 1 if only `up` is, 0 otherwise
+    fn axis(&self, input: &InputState, player: usize, down: Action, up: Action) -> f32 {
+        (if self.is_down(input, player, down) {
+            -1.0
+        } else {
+            0.0
+        }) + (if self.is_down(input, player, up) {
+            1.0
+        } else {
+            0.0
+        })
+    }
+}
+
+// The rebinding menu.  F1, F2, ... start rebinding that player's keys: it asks for each
+// action in turn and binds it to the next key pressed (Escape keeps the rest as they
+// were).  F9 swaps players 1 and 2's keys.  While it's asking, the game should pause.
+#[derive(Default)]
+pub struct ControlsMenu {
+    // the player being rebound, and which action is next
+    rebinding: Option<(usize, usize)>,
+    messages: Vec<String>,
+}
+
+const MENU_KEYS: [Key; 8] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+];
+
+impl ControlsMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn is_open(&self) -> bool {
+        self.rebinding.is_some()
+    }
+    // What the menu is asking for: which player and which action
+    pub fn prompt(&self) -> Option<(usize, Action)> {
+        self.rebinding
+            .map(|(player, next)| (player, Action::ALL[next]))
+    }
+    // Handles this frame's keys.  True means the menu used them, so the game shouldn't
+    // step this frame.
+    pub fn update(&mut self, input: &InputState, controls: &mut Controls) -> bool {
+        let Some((player, next)) = self.rebinding else {
+            if input.is_key_pressed(Key::F9) && controls.player_count() >= 2 {
+                controls.swap(0, 1);
+                self.messages
+                    .push("Swapped player 1 and player 2's controls".to_string());
+                return true;
+            }
+            let player = MENU_KEYS
+                .iter()
+                .take(controls.player_count())
+                .position(|key| input.is_key_pressed(*key));
+            if let Some(player) = player {
+                self.rebinding = Some((player, 0));
+                self.ask();
+                return true;
+            }
+            return false;
+        };
+        if input.is_key_pressed(Key::Escape) {
+            self.rebinding = None;
+            self.messages
+                .push(format!("Kept the rest of player {}'s keys", player + 1));
+            return true;
+        }
+        // the key that opened the menu was pressed last frame, so it can't count here
+        let Some(key) = input.pressed_keys().find(|key| KEYS.contains(key)) else {
+            return true;
+        };
+        controls.bind(player, Action::ALL[next], key);
+        if next + 1 < Action::ALL.len() {
+            self.rebinding = Some((player, next + 1));
+            self.ask();
+        } else {
+            self.rebinding = None;
+            self.messages
+                .push(format!("Player {}'s keys are set", player + 1));
+        }
+        true
+    }
+    // Things to tell the players (what to press next, what changed) since last asked
+    pub fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+    fn ask(&mut self) {
+        if let Some((player, action)) = self.prompt() {
+            self.messages.push(format!(
+                "Player {}: press a key for {} (Escape to stop)",
+                player + 1,
+                action.name()
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_query() {
+        let controls = Controls::default();
+        assert_eq!(controls.player_count(), 2);
+        assert_eq!(controls.scheme(0).name, "arrows");
+        assert_eq!(controls.scheme(1).keys(Action::Fire), &[Key::KeyQ]);
+        let mut input = InputState::default();
+        input.press(Key::KeyQ);
+        assert!(controls.is_pressed(&input, 1, Action::Fire));
+        assert!(!controls.is_pressed(&input, 0, Action::Fire));
+        // nobody's keys belong to a third player
+        assert!(!controls.is_down(&input, 2, Action::Fire));
+        input.next_frame();
+        assert!(controls.is_down(&input, 1, Action::Fire));
+        assert!(!controls.is_pressed(&input, 1, Action::Fire));
+
+        // any number of players, and more than one key per action
+        let three = Controls::parse(
+            "scheme pad\nfire Space\nfire Enter\nforward ArrowUp\nplayer pad\nplayer pad\nplayer pad\n",
+        )
+        .unwrap();
+        assert_eq!(three.player_count(), 3);
+        assert_eq!(three.keys(), vec![Key::ArrowUp, Key::Space, Key::Enter]);
+        let mut input = InputState::default();
+        input.press(Key::Enter);
+        assert!(three.is_pressed(&input, 2, Action::Fire));
+
+        let err = |text: &str| Controls::parse(text).unwrap_err();
+        assert_eq!(err("fire Space\n").line, 1);
+        assert_eq!(err("scheme a\nfire Spacebar\n").line, 2);
+        assert_eq!(err("scheme a\njump Space\n").line, 2);
+        assert_eq!(err("scheme a\nplayer b\n").line, 2);
+        assert_eq!(err("scheme a\nscheme a\n").line, 2);
+    }
+
+    #[test]
+    fn test_rebind_and_swap() {
+        let mut controls = Controls::default();
+        let mut menu = ControlsMenu::new();
+        let mut input = InputState::default();
+        assert!(!menu.update(&input, &mut controls));
+        input.press(Key::F2);
+        assert!(menu.update(&input, &mut controls));
+        assert_eq!(menu.prompt(), Some((1, Action::TurnLeft)));
+        input.next_frame();
+        input.release(Key::F2);
+        // waiting for a key still pauses the game
+        assert!(menu.update(&input, &mut controls));
+        input.press(Key::KeyZ);
+        menu.update(&input, &mut controls);
+        input.next_frame();
+        input.release(Key::KeyZ);
+        // turn_right takes D's place, and player 2's old left key is forgotten
+        input.press(Key::KeyA);
+        menu.update(&input, &mut controls);
+        input.next_frame();
+        input.release(Key::KeyA);
+        input.press(Key::Escape);
+        menu.update(&input, &mut controls);
+        assert!(!menu.is_open());
+        assert_eq!(controls.scheme(1).keys(Action::TurnLeft), &[Key::KeyZ]);
+        assert_eq!(controls.scheme(1).keys(Action::TurnRight), &[Key::KeyA]);
+        assert_eq!(controls.scheme(1).keys(Action::Fire), &[Key::KeyQ]);
+        // player 1 and the scheme itself are untouched
+        assert_eq!(controls.scheme(0).keys(Action::TurnLeft), &[Key::ArrowLeft]);
+        assert_eq!(menu.take_messages().len(), 4);
+
+        input.next_frame();
+        input.release(Key::Escape);
+        input.press(Key::F9);
+        assert!(menu.update(&input, &mut controls));
+        assert_eq!(controls.scheme(0).keys(Action::TurnLeft), &[Key::KeyZ]);
+        assert!(controls.set_scheme(0, "ijkl"));
+        assert_eq!(controls.scheme(0).keys(Action::Fire), &[Key::KeyU]);
+        assert!(!controls.set_scheme(0, "joystick"));
+    }
+}
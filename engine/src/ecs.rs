@@ -0,0 +1,639 @@
+use crate::arena::{Arena, EntityId};
+use crate::geom::Vec2;
+
+// Optional pieces of an entity, kept beside the game's own `Entity` (which only needs
+// to say where it is and what it is) and looked up by its `EntityId`.  A game
+// attaches the ones it uses; nothing has to grow every game's `Entity` struct.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Health {
+    pub hp: u8,
+    pub max: u8,
+}
+
+impl Health {
+    pub fn new(max: u8) -> Self {
+        Self { hp: max, max }
+    }
+    pub fn is_dead(&self) -> bool {
+        self.hp == 0
+    }
+    pub fn hurt(&mut self, damage: u8) {
+        self.hp = self.hp.saturating_sub(damage);
+    }
+}
+
+// pixels per second
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity(pub Vec2);
+
+// How a kind of thing speeds up and slows down (see `Components::drive`).  Speeds
+// are in pixels per second, `accel` and `friction` in pixels per second per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Motion {
+    pub max_speed: f32,
+    // how fast it gets up to the speed it's driven at
+    pub accel: f32,
+    // how fast it coasts to a stop when nothing's driving it
+    pub friction: f32,
+}
+
+// Where it was and which way it faced at the start of the last step, so it can be
+// drawn partway between there and where it is now (see `Components::interpolate`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Previous {
+    pub pos: Vec2,
+    pub dir: f32,
+}
+
+// seconds left being knocked back, during which it can't steer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Knockback(pub f32);
+
+// which collision layer it's on and which it collides with (see `layers`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionLayer {
+    pub layer: u32,
+    pub mask: u32,
+}
+
+// seconds left to live
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lifetime(pub f32);
+
+// walls left to bounce off
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounces(pub u8);
+
+// Why something went away on its own, as opposed to being shot or collected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DespawnCause {
+    // hit a wall with no `Bounces` left
+    OutOfBounces,
+    // its `Lifetime` ran out
+    Expired,
+}
+
+// Reported by a game's step for each thing that despawned, and where, so it can
+// draw a burst there (or play a sound, or count it)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Despawn {
+    pub id: EntityId,
+    pub pos: Vec2,
+    pub cause: DespawnCause,
+}
+
+// Which local player's controls drive it.  `locked` takes the controls away for a
+// while (e.g. sliding on ice) without forgetting whose they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Controller {
+    pub player: usize,
+    pub locked: bool,
+}
+
+impl Controller {
+    pub fn new(player: usize) -> Self {
+        Self {
+            player,
+            locked: false,
+        }
+    }
+}
+
+// One kind of component for whichever entities have it, in a slot per arena index
+// so lookups don't hash.  Iteration goes in arena slot order.
+pub struct Storage<T> {
+    slots: Vec<Option<(EntityId, T)>>,
+    len: usize,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+    // Gives `id` this component, returning the one it replaced
+    pub fn insert(&mut self, id: EntityId, value: T) -> Option<T> {
+        let index = id.index();
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        let old = self.slots[index].replace((id, value));
+        match old {
+            // an entity that's since been removed from the arena doesn't count
+            Some((old_id, old)) if old_id == id => Some(old),
+            Some(_) => None,
+            None => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        let slot = self.slots.get_mut(id.index())?;
+        if !matches!(slot, Some((slot_id, _)) if *slot_id == id) {
+            return None;
+        }
+        self.len -= 1;
+        slot.take().map(|(_, value)| value)
+    }
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        match self.slots.get(id.index())? {
+            Some((slot_id, value)) if *slot_id == id => Some(value),
+            _ => None,
+        }
+    }
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        match self.slots.get_mut(id.index())? {
+            Some((slot_id, value)) if *slot_id == id => Some(value),
+            _ => None,
+        }
+    }
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|(id, value)| (*id, value)))
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| slot.as_mut().map(|(id, value)| (*id, value)))
+    }
+    // Drops the components of everything no longer in `arena`
+    pub fn retain_alive<E>(&mut self, arena: &Arena<E>) {
+        for slot in self.slots.iter_mut() {
+            if matches!(slot, Some((id, _)) if !arena.contains(*id)) {
+                *slot = None;
+                self.len -= 1;
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+}
+
+// Everything with both an `a` and a `b`, e.g. `join(&entities, &components.health)`
+// walks the entities that have health.  `A` can be the arena itself.
+pub fn join<'a, A: Lookup<'a>, B>(
+    a: A,
+    b: &'a Storage<B>,
+) -> impl Iterator<Item = (EntityId, A::Item, &'a B)> {
+    a.entries()
+        .filter_map(move |(id, a)| b.get(id).map(|b| (id, a, b)))
+}
+
+// Like `join`, but the `a` side can be changed
+pub fn join_mut<'a, A, B>(
+    a: &'a mut Storage<A>,
+    b: &'a Storage<B>,
+) -> impl Iterator<Item = (EntityId, &'a mut A, &'a B)> {
+    a.iter_mut()
+        .filter_map(move |(id, a)| b.get(id).map(|b| (id, a, b)))
+}
+
+// What `join` can walk: an arena or a component storage
+pub trait Lookup<'a> {
+    type Item;
+    fn entries(self) -> impl Iterator<Item = (EntityId, Self::Item)>;
+}
+
+impl<'a, T> Lookup<'a> for &'a Arena<T> {
+    type Item = &'a T;
+    fn entries(self) -> impl Iterator<Item = (EntityId, &'a T)> {
+        self.iter()
+    }
+}
+
+impl<'a, T> Lookup<'a> for &'a Storage<T> {
+    type Item = &'a T;
+    fn entries(self) -> impl Iterator<Item = (EntityId, &'a T)> {
+        self.iter()
+    }
+}
+
+// What `Components` needs from a game's own `Entity`: where it is, which way it
+// faces, and whether it's still in play (the game removes what isn't at the end of
+// the step)
+pub trait Actor {
+    fn pos(&self) -> Vec2;
+    fn dir(&self) -> f32;
+    fn place(&mut self, pos: Vec2, dir: f32);
+    fn alive(&self) -> bool;
+    fn kill(&mut self);
+}
+
+// Marks `entity` for removal at the end of the step and reports why in `despawned`.
+// False (and no report) if it was already on its way out.
+pub fn despawn<E: Actor>(
+    entity: &mut E,
+    id: EntityId,
+    cause: DespawnCause,
+    despawned: &mut Vec<Despawn>,
+) -> bool {
+    if !entity.alive() {
+        return false;
+    }
+    entity.kill();
+    despawned.push(Despawn {
+        id,
+        pos: entity.pos(),
+        cause,
+    });
+    true
+}
+
+// Every kind of component the engine knows about, for one arena's entities
+#[derive(Default)]
+pub struct Components {
+    pub health: Storage<Health>,
+    pub velocity: Storage<Velocity>,
+    pub motion: Storage<Motion>,
+    pub knockback: Storage<Knockback>,
+    pub previous: Storage<Previous>,
+    pub collision: Storage<CollisionLayer>,
+    pub lifetime: Storage<Lifetime>,
+    pub bounces: Storage<Bounces>,
+    pub controller: Storage<Controller>,
+}
+
+impl Components {
+    // Takes every component away from `id` (say, because it's being removed)
+    pub fn remove(&mut self, id: EntityId) {
+        self.health.remove(id);
+        self.velocity.remove(id);
+        self.motion.remove(id);
+        self.knockback.remove(id);
+        self.previous.remove(id);
+        self.collision.remove(id);
+        self.lifetime.remove(id);
+        self.bounces.remove(id);
+        self.controller.remove(id);
+    }
+    // Call after removing things from `arena`, to drop what they left behind
+    pub fn retain_alive<E>(&mut self, arena: &Arena<E>) {
+        self.health.retain_alive(arena);
+        self.velocity.retain_alive(arena);
+        self.motion.retain_alive(arena);
+        self.knockback.retain_alive(arena);
+        self.previous.retain_alive(arena);
+        self.collision.retain_alive(arena);
+        self.lifetime.retain_alive(arena);
+        self.bounces.retain_alive(arena);
+        self.controller.retain_alive(arena);
+    }
+    // Takes everything without a `Controller` out of `arena`, components and all, say
+    // when the players go through a door: only they come along.  They'll be put
+    // somewhere new, so they forget where they were rather than being drawn sliding
+    // over from there.
+    pub fn keep_controlled<E>(&mut self, arena: &mut Arena<E>) {
+        let controllers = &self.controller;
+        arena.retain(|id, _| controllers.contains(id));
+        self.retain_alive(arena);
+        self.previous.clear();
+    }
+    // Uses up one of `id`'s bounces.  False means it had none left and should
+    // despawn instead of bouncing; things without `Bounces` bounce forever.
+    pub fn spend_bounce(&mut self, id: EntityId) -> bool {
+        match self.bounces.get_mut(id) {
+            Some(Bounces(0)) => false,
+            Some(Bounces(left)) => {
+                *left -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+    // Counts every `Lifetime` down by `dt` and returns whoever's ran out (in slot
+    // order).  They keep being returned until the game removes them.
+    pub fn tick_lifetimes(&mut self, dt: f32) -> Vec<EntityId> {
+        self.lifetime
+            .iter_mut()
+            .filter_map(|(id, Lifetime(left))| {
+                *left -= dt;
+                (*left <= 0.0).then_some(id)
+            })
+            .collect()
+    }
+    // Pushes `id`'s velocity towards `throttle` (-1 to 1) of its top speed along
+    // `heading` (a unit vector) for `dt` seconds, or lets friction slow it if the
+    // throttle's 0.  While it's being knocked back it just counts that down instead.
+    // Returns the velocity to move it by; things without `Motion` keep theirs.
+    pub fn drive(&mut self, id: EntityId, heading: Vec2, throttle: f32, dt: f32) -> Vec2 {
+        let Some(Velocity(vel)) = self.velocity.get_mut(id) else {
+            return Vec2::ZERO;
+        };
+        if let Some(Knockback(left)) = self.knockback.get_mut(id) {
+            *left -= dt;
+            if *left <= 0.0 {
+                self.knockback.remove(id);
+            }
+            return *vel;
+        }
+        if let Some(motion) = self.motion.get(id) {
+            *vel = if throttle == 0.0 {
+                vel.move_towards(Vec2::ZERO, motion.friction * dt)
+            } else {
+                let target = heading * (throttle.clamp(-1.0, 1.0) * motion.max_speed);
+                vel.move_towards(target, motion.accel * dt)
+            };
+        }
+        *vel
+    }
+    // Shoves `id` along at `vel` for `time` seconds, whatever it was doing
+    pub fn knock_back(&mut self, id: EntityId, vel: Vec2, time: f32) {
+        self.velocity.insert(id, Velocity(vel));
+        self.knockback.insert(id, Knockback(time));
+    }
+    // Where to draw something that's now at `pos` facing `dir`, `alpha` of the way
+    // from its `Previous` place to there.  Without one (it's new, or just went through
+    // a door) it's drawn where it is.
+    pub fn interpolate(&self, id: EntityId, pos: Vec2, dir: f32, alpha: f32) -> (Vec2, f32) {
+        match self.previous.get(id) {
+            Some(prev) => (
+                prev.pos.lerp(pos, alpha),
+                prev.dir + (dir - prev.dir) * alpha,
+            ),
+            None => (pos, dir),
+        }
+    }
+    // Notes where everything in `arena` is before it moves, for `interpolated`
+    pub fn remember_positions<E: Actor>(&mut self, arena: &Arena<E>) {
+        for (id, entity) in arena.iter() {
+            let previous = Previous {
+                pos: entity.pos(),
+                dir: entity.dir(),
+            };
+            self.previous.insert(id, previous);
+        }
+    }
+    // `id` as it should be drawn, `alpha` of the way from where it was at the start
+    // of the last step to where it is now (see `interpolate`)
+    pub fn interpolated<E: Actor + Clone>(&self, arena: &Arena<E>, id: EntityId, alpha: f32) -> E {
+        let mut entity = arena[id].clone();
+        let (pos, dir) = self.interpolate(id, entity.pos(), entity.dir(), alpha);
+        entity.place(pos, dir);
+        entity
+    }
+    pub fn clear(&mut self) {
+        self.health.clear();
+        self.velocity.clear();
+        self.motion.clear();
+        self.knockback.clear();
+        self.previous.clear();
+        self.collision.clear();
+        self.lifetime.clear();
+        self.bounces.clear();
+        self.controller.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let player = arena.insert("player");
+        let enemy = arena.insert("enemy");
+        let bullet = arena.insert("bullet");
+        components.health.insert(player, Health::new(1));
+        components.health.insert(enemy, Health::new(3));
+        components.controller.insert(player, Controller::new(0));
+        components.bounces.insert(bullet, Bounces(3));
+
+        let healthy: Vec<_> = join(&arena, &components.health)
+            .map(|(_, name, health)| (*name, health.hp))
+            .collect();
+        assert_eq!(healthy, vec![("player", 1), ("enemy", 3)]);
+        let controlled: Vec<_> = join(&components.health, &components.controller)
+            .map(|(id, _, _)| id)
+            .collect();
+        assert_eq!(controlled, vec![player]);
+
+        for (_, health, _) in join_mut(&mut components.health, &components.controller) {
+            health.hurt(5);
+        }
+        assert!(components.health.get(player).unwrap().is_dead());
+        assert_eq!(components.health.get(enemy).unwrap().hp, 3);
+
+        // once the bullet's gone, its bounces go too, and its slot's next occupant
+        // doesn't inherit them
+        arena.remove(bullet);
+        components.retain_alive(&arena);
+        assert!(components.bounces.is_empty());
+        let next = arena.insert("next bullet");
+        assert_eq!(components.bounces.get(next), None);
+        components.bounces.insert(next, Bounces(1));
+        assert_eq!(components.bounces.len(), 1);
+
+        components.remove(player);
+        assert!(!components.health.contains(player));
+        assert!(!components.controller.contains(player));
+        assert_eq!(components.health.len(), 1);
+    }
+
+    #[test]
+    fn test_bounces_and_lifetimes() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let bullet = arena.insert(());
+        let wall = arena.insert(());
+        let spark = arena.insert(());
+        components.bounces.insert(bullet, Bounces(2));
+        components.lifetime.insert(bullet, Lifetime(1.0));
+        components.lifetime.insert(spark, Lifetime(0.25));
+        assert!(components.spend_bounce(bullet));
+        assert!(components.spend_bounce(bullet));
+        assert!(!components.spend_bounce(bullet));
+        assert_eq!(components.bounces.get(bullet), Some(&Bounces(0)));
+        // no budget means no limit
+        assert!(components.spend_bounce(wall));
+
+        assert_eq!(components.tick_lifetimes(0.125), vec![]);
+        assert_eq!(components.tick_lifetimes(0.125), vec![spark]);
+        arena.remove(spark);
+        components.retain_alive(&arena);
+        assert_eq!(components.tick_lifetimes(0.75), vec![bullet]);
+    }
+
+    #[test]
+    fn test_drive_and_knockback() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let tank = arena.insert(());
+        let crate_ = arena.insert(());
+        components.velocity.insert(tank, Velocity(Vec2::ZERO));
+        components.motion.insert(
+            tank,
+            Motion {
+                max_speed: 64.0,
+                accel: 256.0,
+                friction: 512.0,
+            },
+        );
+        components
+            .velocity
+            .insert(crate_, Velocity(Vec2 { x: 1.0, y: 0.0 }));
+        let right = Vec2 { x: 1.0, y: 0.0 };
+
+        // a quarter second to get up to speed, then it stays there
+        for _ in 0..4 {
+            components.drive(tank, right, 1.0, 0.0625);
+        }
+        assert_eq!(components.drive(tank, right, 1.0, 0.0625).x, 64.0);
+        // half throttle backwards slows it down first
+        let vel = components.drive(tank, right, -0.5, 0.0625);
+        assert_eq!(vel, Vec2 { x: 48.0, y: 0.0 });
+        // and with no throttle friction stops it, without going past 0
+        components.drive(tank, right, 0.0, 0.0625);
+        assert_eq!(components.drive(tank, right, 0.0, 0.0625), Vec2::ZERO);
+
+        // knocked back, it ignores the throttle until the time's up
+        let up = Vec2 { x: 0.0, y: 128.0 };
+        components.knock_back(tank, up, 0.25);
+        assert_eq!(components.drive(tank, right, 1.0, 0.125), up);
+        assert_eq!(components.drive(tank, right, 1.0, 0.125), up);
+        assert!(!components.knockback.contains(tank));
+        assert_ne!(components.drive(tank, right, 1.0, 0.125), up);
+
+        // no `Motion` means it keeps going however it was going
+        assert_eq!(components.drive(crate_, up, 1.0, 1.0), right);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let tank = arena.insert(());
+        let pos = Vec2 { x: 10.0, y: 4.0 };
+        assert_eq!(components.interpolate(tank, pos, 1.0, 0.5), (pos, 1.0));
+        components.previous.insert(
+            tank,
+            Previous {
+                pos: Vec2 { x: 6.0, y: 4.0 },
+                dir: 0.0,
+            },
+        );
+        assert_eq!(
+            components.interpolate(tank, pos, 1.0, 0.25),
+            (Vec2 { x: 7.0, y: 4.0 }, 0.25)
+        );
+        assert_eq!(components.interpolate(tank, pos, 1.0, 1.0), (pos, 1.0));
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Thing {
+        alive: bool,
+        pos: Vec2,
+        dir: f32,
+    }
+    impl Actor for Thing {
+        fn pos(&self) -> Vec2 {
+            self.pos
+        }
+        fn dir(&self) -> f32 {
+            self.dir
+        }
+        fn place(&mut self, pos: Vec2, dir: f32) {
+            self.pos = pos;
+            self.dir = dir;
+        }
+        fn alive(&self) -> bool {
+            self.alive
+        }
+        fn kill(&mut self) {
+            self.alive = false;
+        }
+    }
+
+    #[test]
+    fn test_remember_and_despawn() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let bullet = arena.insert(Thing {
+            alive: true,
+            pos: Vec2 { x: 6.0, y: 4.0 },
+            dir: 0.0,
+        });
+        components.remember_positions(&arena);
+        arena[bullet].place(Vec2 { x: 10.0, y: 4.0 }, 1.0);
+        let drawn = components.interpolated(&arena, bullet, 0.25);
+        assert_eq!((drawn.pos, drawn.dir), (Vec2 { x: 7.0, y: 4.0 }, 0.25));
+        // the arena's copy stays where it is
+        assert_eq!(arena[bullet].pos.x, 10.0);
+
+        let mut despawned = Vec::new();
+        let cause = DespawnCause::Expired;
+        assert!(despawn(&mut arena[bullet], bullet, cause, &mut despawned));
+        // already going, so it isn't reported twice
+        assert!(!despawn(&mut arena[bullet], bullet, cause, &mut despawned));
+        assert!(!arena[bullet].alive);
+        assert_eq!(
+            despawned,
+            vec![Despawn {
+                id: bullet,
+                pos: Vec2 { x: 10.0, y: 4.0 },
+                cause,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_keep_controlled() {
+        let mut arena = Arena::new();
+        let mut components = Components::default();
+        let player = arena.insert("player");
+        let enemy = arena.insert("enemy");
+        components.controller.insert(player, Controller::new(0));
+        components.health.insert(enemy, Health::new(2));
+        for id in [player, enemy] {
+            let previous = Previous {
+                pos: Vec2::ZERO,
+                dir: 0.0,
+            };
+            components.previous.insert(id, previous);
+        }
+        components.keep_controlled(&mut arena);
+        assert_eq!(arena.values().collect::<Vec<_>>(), vec![&"player"]);
+        assert!(components.controller.contains(player));
+        assert!(components.health.is_empty());
+        assert!(components.previous.is_empty());
+    }
+
+    #[test]
+    fn test_stale_ids_miss() {
+        let mut arena = Arena::new();
+        let mut health = Storage::new();
+        let old = arena.insert(());
+        health.insert(old, Health::new(2));
+        arena.remove(old);
+        let new = arena.insert(());
+        // the new entity reuses old's slot, but not its health
+        assert_eq!(health.get(new), None);
+        assert_eq!(health.insert(new, Health::new(1)), None);
+        assert_eq!(health.get(old), None);
+        assert_eq!(health.remove(old), None);
+        assert_eq!(health.len(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::geom::Vec2;
+use assets_manager::AssetCache;
+use frenderer::sprites::{SheetRegion, Transform};
+use std::collections::HashMap;
+
+// A bitmap font cut out of a glyph sheet, as described by font.txt: which image the
+// glyphs are in, how big each cell is, and which character is in which cell.  Text
+// is laid out as one sprite per character, so it can go into any sprite group whose
+// texture has the glyph image on layer `with_sheet`.
+#[derive(Clone, Debug)]
+pub struct Font {
+    image: String,
+    cell_w: u16,
+    cell_h: u16,
+    glyphs: HashMap<char, SheetRegion>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FontError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for FontError {}
+
+impl Default for Font {
+    fn default() -> Self {
+        include_str!("../content/font.txt")
+            .parse()
+            .expect("content/font.txt should parse")
+    }
+}
+
+impl std::str::FromStr for Font {
+    type Err = FontError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl Font {
+    pub fn parse(text: &str) -> Result<Self, FontError> {
+        let mut image = None;
+        let mut cell = None;
+        let mut glyphs = HashMap::new();
+        let mut rows = 0;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let error = |msg: String| FontError { line: line_no, msg };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+                [comment, ..] if comment.starts_with('#') => {}
+                ["sheet", name] => image = Some(name.to_string()),
+                ["cell", w, h] => {
+                    let size = |n: &str| {
+                        n.parse::<u16>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| error(format!("{n:?} isn't a cell size")))
+                    };
+                    cell = Some((size(w)?, size(h)?));
+                }
+                ["row", chars] => {
+                    let (w, h) =
+                        cell.ok_or_else(|| error("rows need a `cell W H` line first".to_string()))?;
+                    for (col, c) in chars.chars().enumerate() {
+                        let region =
+                            SheetRegion::rect(col as u16 * w, rows * h, w as i16, h as i16);
+                        if glyphs.insert(c, region).is_some() {
+                            return Err(error(format!("{c:?} is in the sheet twice")));
+                        }
+                    }
+                    rows += 1;
+                }
+                _ => return Err(error(format!("couldn't make sense of {line:?}"))),
+            }
+        }
+        let last = text.lines().count();
+        let image = image.ok_or_else(|| FontError {
+            line: last,
+            msg: "no `sheet IMAGE` line".to_string(),
+        })?;
+        let (cell_w, cell_h) = cell.ok_or_else(|| FontError {
+            line: last,
+            msg: "no `cell W H` line".to_string(),
+        })?;
+        Ok(Self {
+            image,
+            cell_w,
+            cell_h,
+            glyphs,
+        })
+    }
+    // Loads font.txt (or whichever text asset `id` names) from `cache`
+    pub fn load(cache: &AssetCache, id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let handle = cache.load::<String>(id)?;
+        let font = Self::parse(&handle.read())?;
+        Ok(font)
+    }
+    // the PNG asset the glyphs are in
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+    // Points every glyph at layer `sheet` of whatever texture it's drawn with
+    pub fn with_sheet(mut self, sheet: u16) -> Self {
+        for glyph in self.glyphs.values_mut() {
+            *glyph = glyph.with_sheet(sheet);
+        }
+        self
+    }
+    pub fn glyph(&self, c: char) -> Option<SheetRegion> {
+        self.glyphs
+            .get(&c)
+            .or_else(|| self.glyphs.get(&c.to_ascii_uppercase()))
+            .copied()
+    }
+    // How much room `text` takes up at `scale` screen pixels per sheet pixel
+    pub fn measure(&self, text: &str, scale: u16) -> Vec2 {
+        Vec2 {
+            x: (text.chars().count() as u16 * self.cell_w * scale) as f32,
+            y: (self.cell_h * scale) as f32,
+        }
+    }
+    // One sprite per glyph of `text`, starting with its bottom left corner at
+    // `corner`.  Characters the sheet doesn't have leave a gap, like spaces.
+    pub fn layout(
+        &self,
+        text: &str,
+        corner: Vec2,
+        scale: u16,
+        depth: u16,
+    ) -> Vec<(Transform, SheetRegion)> {
+        let (w, h) = (self.cell_w * scale, self.cell_h * scale);
+        text.chars()
+            .enumerate()
+            .filter_map(|(i, c)| {
+                let glyph = self.glyph(c)?;
+                let trf = Transform {
+                    x: corner.x + (i as u16 * w) as f32 + w as f32 / 2.0,
+                    y: corner.y + h as f32 / 2.0,
+                    w,
+                    h,
+                    rot: 0.0,
+                };
+                Some((trf, glyph.with_depth(depth)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_layout() {
+        let font = Font::default();
+        assert_eq!(font.image(), "font");
+        // the second row, and lowercase borrows the capitals
+        let q = font.glyph('q').unwrap();
+        assert_eq!((q.x, q.y, q.w, q.h), (0, 8, 6, 8));
+        assert!(font.glyph('~').is_none());
+
+        let font = font.with_sheet(1);
+        assert_eq!(font.measure("P1 WINS", 2), Vec2 { x: 84.0, y: 16.0 });
+        let sprites = font.layout("P1 WINS", Vec2 { x: 10.0, y: 20.0 }, 2, 0);
+        // the space doesn't get a sprite but still takes up room
+        assert_eq!(sprites.len(), 6);
+        let (trf, glyph) = sprites[2];
+        assert_eq!(
+            (trf.x, trf.y, trf.w, trf.h),
+            (10.0 + 36.0 + 6.0, 28.0, 12, 16)
+        );
+        assert_eq!(glyph.sheet, 1);
+
+        let err = |text: &str| Font::parse(text).unwrap_err();
+        assert_eq!(err("sheet font\nrow AB\n").line, 2);
+        assert_eq!(err("sheet font\ncell 6 8\nrow ABA\n").line, 3);
+        assert_eq!(err("cell 0 8\n").line, 1);
+        assert_eq!(err("cell 6 8\nrow AB\n").msg, "no `sheet IMAGE` line");
+    }
+}
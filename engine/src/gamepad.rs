@@ -0,0 +1,345 @@
+use crate::controls::Action;
+use std::collections::VecDeque;
+
+// Gamepads, as the rest of the engine sees them.  A `PadSource` reports what
+// happened (gilrs for real pads with the `gamepad` feature, `FakePads` in tests) and
+// `Gamepads` keeps track of each pad's buttons and sticks and which player it drives.
+
+// which pad, as the source numbers them
+pub type PadId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    RightTrigger,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+// sticks go from -1 to 1, with up and right positive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadEvent {
+    Connected(PadId),
+    Disconnected(PadId),
+    Button(PadId, PadButton, bool),
+    Axis(PadId, PadAxis, f32),
+}
+
+pub trait PadSource {
+    // Everything that's happened since the last poll, oldest first
+    fn poll(&mut self) -> Vec<PadEvent>;
+}
+
+// A pretend gamepad driver: plug pads in, press their buttons and tilt their sticks,
+// and the next `poll` reports it all
+#[derive(Default)]
+pub struct FakePads {
+    events: VecDeque<PadEvent>,
+}
+
+impl FakePads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn connect(&mut self, pad: PadId) {
+        self.events.push_back(PadEvent::Connected(pad));
+    }
+    pub fn disconnect(&mut self, pad: PadId) {
+        self.events.push_back(PadEvent::Disconnected(pad));
+    }
+    pub fn press(&mut self, pad: PadId, button: PadButton) {
+        self.events.push_back(PadEvent::Button(pad, button, true));
+    }
+    pub fn release(&mut self, pad: PadId, button: PadButton) {
+        self.events.push_back(PadEvent::Button(pad, button, false));
+    }
+    pub fn tilt(&mut self, pad: PadId, axis: PadAxis, value: f32) {
+        self.events.push_back(PadEvent::Axis(pad, axis, value));
+    }
+}
+
+impl PadSource for FakePads {
+    fn poll(&mut self) -> Vec<PadEvent> {
+        self.events.drain(..).collect()
+    }
+}
+
+// stick tilts smaller than this count as centered, since worn sticks don't quite
+// come back to 0
+const DEAD_ZONE: f32 = 0.2;
+
+#[derive(Clone, Debug)]
+struct Pad {
+    id: PadId,
+    now_buttons: Vec<PadButton>,
+    prev_buttons: Vec<PadButton>,
+    // by `PadAxis`
+    axes: [f32; 4],
+}
+
+// Every connected pad, and which player slot each one drives.  A pad that's plugged
+// in takes the first slot without one; unplugging it frees the slot (that player
+// still has the keyboard), and the next pad plugged in takes it over.
+#[derive(Clone, Debug, Default)]
+pub struct Gamepads {
+    pads: Vec<Pad>,
+    // the pad driving each player, by player
+    slots: Vec<Option<PadId>>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn apply(&mut self, event: PadEvent) {
+        match event {
+            PadEvent::Connected(id) => {
+                if self.pads.iter().any(|pad| pad.id == id) {
+                    return;
+                }
+                self.pads.push(Pad {
+                    id,
+                    now_buttons: Vec::new(),
+                    prev_buttons: Vec::new(),
+                    axes: [0.0; 4],
+                });
+                match self.slots.iter().position(Option::is_none) {
+                    Some(slot) => self.slots[slot] = Some(id),
+                    None => self.slots.push(Some(id)),
+                }
+            }
+            PadEvent::Disconnected(id) => {
+                self.pads.retain(|pad| pad.id != id);
+                for slot in self.slots.iter_mut().filter(|slot| **slot == Some(id)) {
+                    *slot = None;
+                }
+            }
+            PadEvent::Button(id, button, down) => {
+                if let Some(pad) = self.pad_mut(id) {
+                    pad.now_buttons.retain(|b| *b != button);
+                    if down {
+                        pad.now_buttons.push(button);
+                    }
+                }
+            }
+            PadEvent::Axis(id, axis, value) => {
+                if let Some(pad) = self.pad_mut(id) {
+                    pad.axes[axis as usize] = value.clamp(-1.0, 1.0);
+                }
+            }
+        }
+    }
+    // Catches up on everything `source` has to report
+    pub fn poll(&mut self, source: &mut dyn PadSource) {
+        for event in source.poll() {
+            self.apply(event);
+        }
+    }
+    // Call after each step, like `InputState::next_frame`
+    pub fn next_frame(&mut self) {
+        for pad in self.pads.iter_mut() {
+            pad.prev_buttons.clone_from(&pad.now_buttons);
+        }
+    }
+    // the pad driving `player`, if there is one
+    pub fn player_pad(&self, player: usize) -> Option<PadId> {
+        self.slots.get(player).copied().flatten()
+    }
+    pub fn is_button_down(&self, player: usize, button: PadButton) -> bool {
+        self.player(player)
+            .is_some_and(|pad| pad.now_buttons.contains(&button))
+    }
+    pub fn is_button_pressed(&self, player: usize, button: PadButton) -> bool {
+        self.player(player).is_some_and(|pad| {
+            pad.now_buttons.contains(&button) && !pad.prev_buttons.contains(&button)
+        })
+    }
+    // How far `player`'s stick is tilted along `axis`, or 0 inside the dead zone
+    pub fn axis(&self, player: usize, axis: PadAxis) -> f32 {
+        let value = self
+            .player(player)
+            .map_or(0.0, |pad| pad.axes[axis as usize]);
+        if value.abs() < DEAD_ZONE {
+            0.0
+        } else {
+            value
+        }
+    }
+    // The d-pad and face buttons work like keys; the left stick also steers (see
+    // `Controls::turn` and `Controls::throttle`)
+    pub fn is_down(&self, player: usize, action: Action) -> bool {
+        action_buttons(action)
+            .iter()
+            .any(|button| self.is_button_down(player, *button))
+    }
+    pub fn is_pressed(&self, player: usize, action: Action) -> bool {
+        action_buttons(action)
+            .iter()
+            .any(|button| self.is_button_pressed(player, *button))
+    }
+    fn player(&self, player: usize) -> Option<&Pad> {
+        let id = self.player_pad(player)?;
+        self.pads.iter().find(|pad| pad.id == id)
+    }
+    fn pad_mut(&mut self, id: PadId) -> Option<&mut Pad> {
+        self.pads.iter_mut().find(|pad| pad.id == id)
+    }
+}
+
+fn action_buttons(action: Action) -> &'static [PadButton] {
+    match action {
+        Action::TurnLeft => &[PadButton::DPadLeft],
+        Action::TurnRight => &[PadButton::DPadRight],
+        Action::Forward => &[PadButton::DPadUp],
+        Action::Back => &[PadButton::DPadDown],
+        Action::Fire => &[PadButton::South, PadButton::RightTrigger],
+    }
+}
+
+// The machine's real gamepads, if the engine was built with the `gamepad` feature
+// and the OS lets us at them
+pub fn system_pads() -> Option<Box<dyn PadSource>> {
+    #[cfg(feature = "gamepad")]
+    {
+        match gilrs_pads::GilrsPads::new() {
+            Ok(pads) => return Some(Box::new(pads)),
+            Err(err) => eprintln!("No gamepads: {err}"),
+        }
+    }
+    None
+}
+
+#[cfg(feature = "gamepad")]
+mod gilrs_pads {
+    use super::*;
+    use gilrs::{Axis, Button, EventType, Gilrs};
+
+    pub struct GilrsPads {
+        gilrs: Gilrs,
+        // pads that were plugged in before we started, which gilrs doesn't announce
+        already_connected: Vec<PadId>,
+    }
+
+    impl GilrsPads {
+        // gilrs's error is big enough that clippy wants it out of the Result
+        pub fn new() -> Result<Self, String> {
+            let gilrs = Gilrs::new().map_err(|err| err.to_string())?;
+            let already_connected = gilrs.gamepads().map(|(id, _)| id.into()).collect();
+            Ok(Self {
+                gilrs,
+                already_connected,
+            })
+        }
+    }
+
+    impl PadSource for GilrsPads {
+        fn poll(&mut self) -> Vec<PadEvent> {
+            let mut events: Vec<PadEvent> = self
+                .already_connected
+                .drain(..)
+                .map(PadEvent::Connected)
+                .collect();
+            while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+                let id = id.into();
+                let event = match event {
+                    EventType::Connected => Some(PadEvent::Connected(id)),
+                    EventType::Disconnected => Some(PadEvent::Disconnected(id)),
+                    EventType::ButtonPressed(button, _) => {
+                        pad_button(button).map(|button| PadEvent::Button(id, button, true))
+                    }
+                    EventType::ButtonReleased(button, _) => {
+                        pad_button(button).map(|button| PadEvent::Button(id, button, false))
+                    }
+                    EventType::AxisChanged(axis, value, _) => {
+                        pad_axis(axis).map(|axis| PadEvent::Axis(id, axis, value))
+                    }
+                    _ => None,
+                };
+                events.extend(event);
+            }
+            events
+        }
+    }
+
+    fn pad_button(button: Button) -> Option<PadButton> {
+        Some(match button {
+            Button::South => PadButton::South,
+            Button::East => PadButton::East,
+            Button::North => PadButton::North,
+            Button::West => PadButton::West,
+            Button::LeftTrigger | Button::LeftTrigger2 => PadButton::LeftTrigger,
+            Button::RightTrigger | Button::RightTrigger2 => PadButton::RightTrigger,
+            Button::Start => PadButton::Start,
+            Button::DPadUp => PadButton::DPadUp,
+            Button::DPadDown => PadButton::DPadDown,
+            Button::DPadLeft => PadButton::DPadLeft,
+            Button::DPadRight => PadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    fn pad_axis(axis: Axis) -> Option<PadAxis> {
+        Some(match axis {
+            Axis::LeftStickX => PadAxis::LeftStickX,
+            Axis::LeftStickY => PadAxis::LeftStickY,
+            Axis::RightStickX => PadAxis::RightStickX,
+            Axis::RightStickY => PadAxis::RightStickY,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotplug_assigns_slots() {
+        let mut fake = FakePads::new();
+        let mut pads = Gamepads::new();
+        fake.connect(7);
+        fake.connect(3);
+        pads.poll(&mut fake);
+        assert_eq!(pads.player_pad(0), Some(7));
+        assert_eq!(pads.player_pad(1), Some(3));
+        assert_eq!(pads.player_pad(2), None);
+
+        fake.press(3, PadButton::South);
+        fake.tilt(3, PadAxis::LeftStickX, -0.6);
+        fake.tilt(7, PadAxis::LeftStickY, 0.1);
+        pads.poll(&mut fake);
+        assert!(pads.is_pressed(1, Action::Fire));
+        assert!(!pads.is_down(0, Action::Fire));
+        assert_eq!(pads.axis(1, PadAxis::LeftStickX), -0.6);
+        // inside the dead zone
+        assert_eq!(pads.axis(0, PadAxis::LeftStickY), 0.0);
+        pads.next_frame();
+        assert!(pads.is_down(1, Action::Fire));
+        assert!(!pads.is_pressed(1, Action::Fire));
+
+        // player 1's pad goes away and a new one takes over the slot
+        fake.disconnect(7);
+        pads.poll(&mut fake);
+        assert_eq!(pads.player_pad(0), None);
+        assert_eq!(pads.axis(0, PadAxis::LeftStickY), 0.0);
+        fake.connect(9);
+        pads.poll(&mut fake);
+        assert_eq!(pads.player_pad(0), Some(9));
+        assert_eq!(pads.player_pad(1), Some(3));
+    }
+}
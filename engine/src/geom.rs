@@ -11,99 +11,227 @@ pub struct Rect {
     pub h: u16,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub struct Circle {
     pub x: f32,
     pub y: f32,
     pub r: f32,
 }
 
+// A w by h box centered on (x, y) and turned `rot` radians counterclockwise, the same
+// way a sprite `Transform` is.  Unlike `Rect::overlap`, its `mtv` gives back the real
+// minimum translation vector: the smallest move that pushes it out of the other shape.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct OrientedRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub rot: f32,
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Shape {
     Circle(Circle),
     Rect(Rect),
+    Oriented(OrientedRect),
+}
+
+// How far one shape has sunk into another.  `normal` is a unit vector pointing away
+// from the other shape, so moving the first shape by `mtv()` (the minimum translation
+// vector) separates them.  Touching shapes have a depth of 0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Penetration {
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+impl Penetration {
+    pub fn mtv(&self) -> Vec2 {
+        self.normal * self.depth
+    }
+    // the same contact seen from the other shape
+    pub fn flip(self) -> Self {
+        Penetration {
+            normal: -self.normal,
+            depth: self.depth,
+        }
+    }
 }
 
 impl Shape {
-    pub fn overlap(&self, other: Shape) -> Option<Vec2> {
+    pub fn penetration(&self, other: Shape) -> Option<Penetration> {
+        match (*self, other) {
+            (Shape::Circle(s), Shape::Circle(o)) => s.penetration(o),
+            (Shape::Circle(s), Shape::Rect(o)) => s.rect_penetration(o),
+            (Shape::Circle(s), Shape::Oriented(o)) => {
+                o.circle_penetration(s).map(Penetration::flip)
+            }
+            (Shape::Rect(s), Shape::Circle(o)) => o.rect_penetration(s).map(Penetration::flip),
+            (Shape::Rect(s), Shape::Rect(o)) => s.penetration(o),
+            (Shape::Rect(s), Shape::Oriented(o)) => OrientedRect::from(s).penetration(o),
+            (Shape::Oriented(s), Shape::Circle(o)) => s.circle_penetration(o),
+            (Shape::Oriented(s), Shape::Rect(o)) => s.penetration(o.into()),
+            (Shape::Oriented(s), Shape::Oriented(o)) => s.penetration(o),
+        }
+    }
+    // The minimum translation vector: the signed move that pushes this shape out of
+    // `other`, if they touch (see `Penetration::mtv`)
+    pub fn mtv(&self, other: Shape) -> Option<Vec2> {
+        self.penetration(other).map(|pen| pen.mtv())
+    }
+    // an axis-aligned rect that holds the whole shape
+    pub fn bounds(&self) -> Rect {
         match self {
-            Shape::Circle(s_circle) => match other {
-                Shape::Circle(o_circle) => s_circle.overlap(o_circle),
-                Shape::Rect(o_rect) => {
-                    let mut test_x: f32 = s_circle.x;
-                    let mut test_y: f32 = s_circle.y;
-
-                    if s_circle.x < o_rect.x {
-                        test_x = o_rect.x;
-                    } else if s_circle.x > o_rect.x + o_rect.w as f32 {
-                        test_x = o_rect.x + o_rect.w as f32;
-                    }
-
-                    if s_circle.y < o_rect.y {
-                        test_y = o_rect.y;
-                    } else if s_circle.y > o_rect.y + o_rect.h as f32 {
-                        test_y = o_rect.y + o_rect.h as f32;
-                    }
-
-                    let dist_x = s_circle.x - test_x;
-                    let dist_y = s_circle.y - test_y;
-                    let dist = f32::sqrt((dist_x * dist_x) + (dist_y * dist_y));
-
-                    if dist <= s_circle.r {
-                        Some(Vec2 {
-                            x: s_circle.r - dist_x,
-                            y: s_circle.r - dist_y,
-                        })
-                    } else {
-                        None
-                    }
+            Shape::Circle(circle) => circle.bounds(),
+            Shape::Rect(rect) => *rect,
+            Shape::Oriented(orect) => orect.bounds(),
+        }
+    }
+    pub fn center(&self) -> Vec2 {
+        match self {
+            Shape::Circle(circle) => circle.origin(),
+            Shape::Rect(rect) => rect.rect_to_pos(),
+            Shape::Oriented(orect) => orect.center(),
+        }
+    }
+}
+
+impl OrientedRect {
+    pub fn center(&self) -> Vec2 {
+        Vec2 {
+            x: self.x,
+            y: self.y,
+        }
+    }
+    // unit vectors along the box's width and height
+    fn axes(&self) -> [Vec2; 2] {
+        let (sin, cos) = self.rot.sin_cos();
+        [Vec2 { x: cos, y: sin }, Vec2 { x: -sin, y: cos }]
+    }
+    pub fn corners(&self) -> [Vec2; 4] {
+        let [u, v] = self.axes();
+        let (u, v) = (u * (self.w / 2.0), v * (self.h / 2.0));
+        let c = self.center();
+        [c - u - v, c + u - v, c + u + v, c - u + v]
+    }
+    // The smallest axis-aligned rect that holds the whole box
+    pub fn bounds(&self) -> Rect {
+        let corners = self.corners();
+        let x0 = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let y0 = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let x1 = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+        let y1 = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+        Rect {
+            x: x0,
+            y: y0,
+            w: (x1 - x0) as u16,
+            h: (y1 - y0) as u16,
+        }
+    }
+    // half the length of the box's shadow on `axis`
+    fn extent_along(&self, axis: Vec2) -> f32 {
+        let [u, v] = self.axes();
+        self.w / 2.0 * u.dot(axis).abs() + self.h / 2.0 * v.dot(axis).abs()
+    }
+    // Separating axis test: two boxes overlap unless their shadows come apart on one
+    // of the four edge directions, and the axis they overlap least on is the way out.
+    // Touching counts as overlapping, like `Rect::overlap`.
+    pub fn penetration(&self, other: OrientedRect) -> Option<Penetration> {
+        let offset = self.center() - other.center();
+        let mut best: Option<Penetration> = None;
+        for axis in self.axes().into_iter().chain(other.axes()) {
+            let along = offset.dot(axis);
+            let depth = self.extent_along(axis) + other.extent_along(axis) - along.abs();
+            if depth < 0.0 {
+                return None;
+            }
+            if best.is_none_or(|best| depth < best.depth) {
+                let normal = if along < 0.0 { -axis } else { axis };
+                best = Some(Penetration { normal, depth });
+            }
+        }
+        best
+    }
+    // the signed move that pushes this box out of `other`, like `Shape::mtv`
+    pub fn mtv(&self, other: OrientedRect) -> Option<Vec2> {
+        self.penetration(other).map(|pen| pen.mtv())
+    }
+    pub fn circle_penetration(&self, circle: Circle) -> Option<Penetration> {
+        let [u, v] = self.axes();
+        let (hw, hh) = (self.w / 2.0, self.h / 2.0);
+        // the circle's center in the box's own frame
+        let offset = circle.origin() - self.center();
+        let (lx, ly) = (offset.dot(u), offset.dot(v));
+        let (cx, cy) = (lx.clamp(-hw, hw), ly.clamp(-hh, hh));
+        let (dx, dy) = (lx - cx, ly - cy);
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist == 0.0 {
+            // the center is inside the box, so back out through the nearest side
+            let (depth_x, depth_y) = (hw - lx.abs() + circle.r, hh - ly.abs() + circle.r);
+            return Some(if depth_x < depth_y {
+                Penetration {
+                    normal: if lx < 0.0 { u } else { -u },
+                    depth: depth_x,
                 }
-            },
-            Shape::Rect(s_rect) => match other {
-                // redundant code for now - same as above but flipped
-                Shape::Circle(o_circle) => {
-                    let mut test_x: f32 = o_circle.x;
-                    let mut test_y: f32 = o_circle.y;
-
-                    if o_circle.x < s_rect.x {
-                        test_x = s_rect.x;
-                    } else if o_circle.x > s_rect.x + s_rect.w as f32 {
-                        test_x = s_rect.x + s_rect.w as f32;
-                    }
-
-                    if o_circle.y < s_rect.y {
-                        test_y = s_rect.y;
-                    } else if o_circle.y > s_rect.y + s_rect.h as f32 {
-                        test_y = s_rect.y + s_rect.h as f32;
-                    }
-
-                    let dist_x = o_circle.x - test_x;
-                    let dist_y = o_circle.y - test_y;
-                    let dist = f32::sqrt((dist_x * dist_x) + (dist_y * dist_y));
-
-                    if dist <= o_circle.r {
-                        Some(Vec2 {
-                            x: o_circle.r - dist_x,
-                            y: o_circle.r - dist_y,
-                        })
-                    } else {
-                        None
-                    }
+            } else {
+                Penetration {
+                    normal: if ly < 0.0 { v } else { -v },
+                    depth: depth_y,
                 }
-                Shape::Rect(o_rect) => s_rect.overlap(o_rect),
-            },
+            });
+        }
+        if dist > circle.r {
+            return None;
+        }
+        Some(Penetration {
+            normal: (u * dx + v * dy) * (-1.0 / dist),
+            depth: circle.r - dist,
+        })
+    }
+    pub fn circle_mtv(&self, circle: Circle) -> Option<Vec2> {
+        self.circle_penetration(circle).map(|pen| pen.mtv())
+    }
+}
+
+impl From<Rect> for OrientedRect {
+    fn from(rect: Rect) -> Self {
+        OrientedRect {
+            x: rect.x + rect.w as f32 / 2.0,
+            y: rect.y + rect.h as f32 / 2.0,
+            w: rect.w as f32,
+            h: rect.h as f32,
+            rot: 0.0,
         }
     }
 }
 
 impl Rect {
+    // How far the two rects overlap along each axis, as unsigned magnitudes (touching
+    // edges count, as 0).  For which way to push, use `penetration` (or `Shape::mtv`
+    // for the signed push itself).
     pub fn overlap(&self, other: Rect) -> Option<Vec2> {
         let x_overlap =
             (self.x + self.w as f32).min(other.x + other.w as f32) - self.x.max(other.x);
         let y_overlap =
             (self.y + self.h as f32).min(other.y + other.h as f32) - self.y.max(other.y);
         if x_overlap >= 0.0 && y_overlap >= 0.0 {
-            // This will return the magnitude of overlap in each axis.
             Some(Vec2 {
                 x: x_overlap,
                 y: y_overlap,
@@ -112,6 +240,28 @@ impl Rect {
             None
         }
     }
+    // Unlike `overlap`, a signed push out along whichever axis overlaps least
+    pub fn penetration(&self, other: Rect) -> Option<Penetration> {
+        let overlap = self.overlap(other)?;
+        let offset = self.rect_to_pos() - other.rect_to_pos();
+        Some(if overlap.x < overlap.y {
+            Penetration {
+                normal: Vec2 {
+                    x: if offset.x < 0.0 { -1.0 } else { 1.0 },
+                    y: 0.0,
+                },
+                depth: overlap.x,
+            }
+        } else {
+            Penetration {
+                normal: Vec2 {
+                    x: 0.0,
+                    y: if offset.y < 0.0 { -1.0 } else { 1.0 },
+                },
+                depth: overlap.y,
+            }
+        })
+    }
     //converts from rectangle coordinates to center coordinates to match position
     pub fn rect_to_pos(&self) -> Vec2 {
         Vec2 {
@@ -138,25 +288,70 @@ impl Circle {
             r: 0.0,
         }
     }
-    pub fn overlap(&self, other: Circle) -> Option<Vec2> {
-        let distance_sq = f32::abs(
-            (self.x - other.x) * (self.x - other.x) + (self.y - other.y) * (self.y - other.y),
-        );
-        let radius_sq = (self.r + other.r) * (self.r + other.r);
-
-        if distance_sq < radius_sq {
-            let midpoint_x = (self.x + other.x) / 2.0;
-            let midpoint_y = (self.y + other.y) / 2.0;
-
-            let x_overlap = midpoint_x - self.x;
-            let y_overlap = midpoint_y - self.y;
-
-            Some(Vec2 {
-                x: x_overlap,
-                y: y_overlap,
-            })
-        } else {
-            None
+    // Touching counts, like `Rect::overlap`
+    pub fn penetration(&self, other: Circle) -> Option<Penetration> {
+        let offset = self.origin() - other.origin();
+        let dist = offset.mag_sq().sqrt();
+        if dist > self.r + other.r {
+            return None;
+        }
+        Some(Penetration {
+            // right on top of each other, so any way out will do
+            normal: if dist == 0.0 {
+                Vec2 { x: 0.0, y: 1.0 }
+            } else {
+                offset * (1.0 / dist)
+            },
+            depth: self.r + other.r - dist,
+        })
+    }
+    // the signed move that pushes this circle out of `other`, like `Shape::mtv`
+    pub fn mtv(&self, other: Circle) -> Option<Vec2> {
+        self.penetration(other).map(|pen| pen.mtv())
+    }
+    // Touching counts, like `Rect::overlap`
+    pub fn rect_penetration(&self, rect: Rect) -> Option<Penetration> {
+        let (x0, y0) = (rect.x, rect.y);
+        let (x1, y1) = (rect.x + rect.w as f32, rect.y + rect.h as f32);
+        // the closest point in the rect to our center
+        let closest = Vec2 {
+            x: self.x.clamp(x0, x1),
+            y: self.y.clamp(y0, y1),
+        };
+        let offset = self.origin() - closest;
+        let dist = offset.mag_sq().sqrt();
+        if dist > self.r {
+            return None;
+        }
+        if dist > 0.0 {
+            return Some(Penetration {
+                normal: offset * (1.0 / dist),
+                depth: self.r - dist,
+            });
+        }
+        // the center is in the rect, so back out through the nearest side
+        let sides = [
+            (self.x - x0, Vec2 { x: -1.0, y: 0.0 }),
+            (x1 - self.x, Vec2 { x: 1.0, y: 0.0 }),
+            (self.y - y0, Vec2 { x: 0.0, y: -1.0 }),
+            (y1 - self.y, Vec2 { x: 0.0, y: 1.0 }),
+        ];
+        let (to_side, normal) = sides
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        Some(Penetration {
+            normal,
+            depth: to_side + self.r,
+        })
+    }
+    // the square the circle fits in
+    pub fn bounds(&self) -> Rect {
+        Rect {
+            x: self.x - self.r,
+            y: self.y - self.r,
+            w: (self.r * 2.0).ceil() as u16,
+            h: (self.r * 2.0).ceil() as u16,
         }
     }
     pub fn circ_to_pos(&self) -> Vec2 {
@@ -186,6 +381,26 @@ impl std::ops::Add for Vec2 {
         }
     }
 }
+impl std::ops::Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::Output {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+impl std::ops::Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Self::Output {
+        Self::Output {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
 impl std::ops::Mul<f32> for Vec2 {
     type Output = Vec2;
 
@@ -203,7 +418,270 @@ impl std::ops::AddAssign for Vec2 {
     }
 }
 impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
     pub fn mag_sq(&self) -> f32 {
         self.x * self.x + self.y * self.y
     }
+    pub fn mag(&self) -> f32 {
+        self.mag_sq().sqrt()
+    }
+    // `t` of the way from here to `to`
+    pub fn lerp(self, to: Vec2, t: f32) -> Vec2 {
+        self + (to - self) * t
+    }
+    // Moves at most `step` from here towards `to`, without overshooting
+    pub fn move_towards(self, to: Vec2, step: f32) -> Vec2 {
+        let diff = to - self;
+        let dist = diff.mag();
+        if dist <= step {
+            to
+        } else {
+            self + diff * (step / dist)
+        }
+    }
+    pub fn dot(&self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+}
+
+// directions are angles in radians, counterclockwise from +x
+pub fn dir_to_vec2(dir: f32) -> Vec2 {
+    Vec2 {
+        x: f32::cos(dir),
+        y: f32::sin(dir),
+    }
+}
+pub fn vec2_to_dir(vec2: Vec2) -> f32 {
+    vec2.y.atan2(vec2.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a - b).mag_sq() < 1e-4
+    }
+
+    fn tank(x: f32, y: f32, rot: f32) -> OrientedRect {
+        OrientedRect {
+            x,
+            y,
+            w: 12.0,
+            h: 12.0,
+            rot,
+        }
+    }
+
+    #[test]
+    fn test_oriented_vs_rect() {
+        let wall = Rect {
+            x: 16.0,
+            y: 0.0,
+            w: 16,
+            h: 16,
+        };
+        // unrotated, it's the same as the AABB but with a signed push
+        let mtv = tank(12.0, 8.0, 0.0).mtv(wall.into()).unwrap();
+        assert!(close(mtv, Vec2 { x: -2.0, y: 0.0 }));
+        // turned 45 degrees its corner reaches 6 * sqrt(2) ~= 8.49 out
+        assert_eq!(tank(8.0, 8.0, 0.0).mtv(wall.into()), None);
+        let mtv = tank(8.0, 8.0, FRAC_PI_4).mtv(wall.into()).unwrap();
+        assert!(close(mtv, Vec2 { x: -0.485, y: 0.0 }));
+        assert_eq!(tank(6.0, 8.0, FRAC_PI_4).mtv(wall.into()), None);
+        // and moving it by the MTV leaves it just touching
+        let mut moved = tank(10.0, 8.0, FRAC_PI_4);
+        let mtv = Shape::Oriented(moved).mtv(Shape::Rect(wall)).unwrap();
+        moved.x += mtv.x;
+        moved.y += mtv.y;
+        let left = moved.mtv(wall.into());
+        assert!(left.is_none_or(|v| v.mag_sq() < 1e-4));
+        // from the rect's side it's the other way
+        let back = Shape::Rect(wall)
+            .mtv(Shape::Oriented(tank(10.0, 8.0, FRAC_PI_4)))
+            .unwrap();
+        assert!(close(back, -mtv));
+    }
+
+    #[test]
+    fn test_oriented_vs_oriented() {
+        // two diamonds corner to corner
+        let a = tank(0.0, 0.0, FRAC_PI_4);
+        let b = tank(16.0, 0.0, FRAC_PI_4);
+        let mtv = a.mtv(b).unwrap();
+        assert!(mtv.x < 0.0);
+        assert!(close(
+            mtv,
+            Vec2 {
+                x: -0.485,
+                y: -0.485
+            }
+        ));
+        assert_eq!(a.mtv(tank(17.0, 0.0, FRAC_PI_4)), None);
+        // a square off the diamond's side misses, even though their bounds overlap
+        assert_eq!(a.mtv(tank(12.0, 12.0, 0.0)), None);
+        assert!(a.bounds().overlap(tank(12.0, 12.0, 0.0).bounds()).is_some());
+    }
+
+    #[test]
+    fn test_oriented_vs_circle() {
+        let box_ = tank(0.0, 0.0, 0.0);
+        let circle = Circle {
+            x: 8.0,
+            y: 0.0,
+            r: 4.0,
+        };
+        assert!(close(
+            box_.circle_mtv(circle).unwrap(),
+            Vec2 { x: -2.0, y: 0.0 }
+        ));
+        // past the corner, the push is along the diagonal
+        let corner = Circle {
+            x: 8.0,
+            y: 8.0,
+            r: 4.0,
+        };
+        let mtv = box_.circle_mtv(corner).unwrap();
+        assert!(mtv.x < 0.0 && (mtv.x - mtv.y).abs() < 1e-4);
+        assert_eq!(box_.circle_mtv(Circle { r: 2.0, ..corner }), None);
+        // a circle swallowed by the box backs out the nearest side
+        let inside = Circle {
+            x: 0.0,
+            y: -5.0,
+            r: 1.0,
+        };
+        assert!(close(
+            box_.circle_mtv(inside).unwrap(),
+            Vec2 { x: 0.0, y: 2.0 }
+        ));
+        let shape = Shape::Circle(inside).mtv(Shape::Oriented(box_)).unwrap();
+        assert!(close(shape, Vec2 { x: 0.0, y: -2.0 }));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let bounds = tank(8.0, 8.0, FRAC_PI_4).bounds();
+        assert_eq!(
+            bounds,
+            Rect {
+                x: -1.0,
+                y: -1.0,
+                w: 18,
+                h: 18
+            }
+        );
+    }
+
+    #[test]
+    fn test_circle_vs_rect() {
+        let wall = Rect {
+            x: 16.0,
+            y: 0.0,
+            w: 16,
+            h: 16,
+        };
+        let pen = |x, y| {
+            Circle { x, y, r: 4.0 }
+                .rect_penetration(wall)
+                .map(|pen| (pen.normal, pen.depth))
+        };
+        // against one face only that face's axis gets pushed
+        assert_eq!(pen(13.0, 8.0), Some((Vec2 { x: -1.0, y: 0.0 }, 1.0)));
+        assert_eq!(pen(24.0, 19.0), Some((Vec2 { x: 0.0, y: 1.0 }, 1.0)));
+        assert_eq!(pen(12.0, 8.0), Some((Vec2 { x: -1.0, y: 0.0 }, 0.0)));
+        assert_eq!(pen(11.0, 8.0), None);
+        // off a corner it's along the diagonal
+        let (normal, depth) = pen(14.0, 18.0).unwrap();
+        assert!(close(
+            normal,
+            Vec2 {
+                x: -FRAC_1_SQRT_2,
+                y: FRAC_1_SQRT_2
+            }
+        ));
+        assert!((depth - (4.0 - 8f32.sqrt())).abs() < 1e-4);
+        assert_eq!(pen(13.0, 19.0), None);
+        // a center inside the rect backs out the nearest side
+        assert_eq!(pen(30.0, 4.0), Some((Vec2 { x: 1.0, y: 0.0 }, 6.0)));
+        // and the shapes agree on it from either end
+        let circle = Shape::Circle(Circle {
+            x: 13.0,
+            y: 8.0,
+            r: 4.0,
+        });
+        assert_eq!(
+            circle.mtv(Shape::Rect(wall)),
+            Some(Vec2 { x: -1.0, y: 0.0 })
+        );
+        assert_eq!(Shape::Rect(wall).mtv(circle), Some(Vec2 { x: 1.0, y: 0.0 }));
+    }
+
+    #[test]
+    fn test_circle_vs_circle() {
+        let a = Circle {
+            x: 0.0,
+            y: 0.0,
+            r: 3.0,
+        };
+        let b = Circle {
+            x: 3.0,
+            y: 4.0,
+            r: 3.0,
+        };
+        let pen = a.penetration(b).unwrap();
+        assert!(close(pen.normal, Vec2 { x: -0.6, y: -0.8 }));
+        assert!((pen.depth - 1.0).abs() < 1e-4);
+        assert!(close(b.mtv(a).unwrap(), Vec2 { x: 0.6, y: 0.8 }));
+        assert_eq!(a.penetration(Circle { r: 1.5, ..b }), None);
+        // even dead center there's a way out
+        assert_eq!(a.penetration(a).map(|pen| pen.depth), Some(6.0));
+    }
+
+    #[test]
+    fn test_touching_circles() {
+        let a = Circle {
+            x: 0.0,
+            y: 0.0,
+            r: 3.0,
+        };
+        let b = Circle {
+            x: 6.0,
+            y: 0.0,
+            r: 3.0,
+        };
+        let pen = a.penetration(b).unwrap();
+        assert_eq!(pen.depth, 0.0);
+        assert_eq!(pen.normal, Vec2 { x: -1.0, y: 0.0 });
+        assert_eq!(
+            Shape::Circle(b).mtv(Shape::Circle(a)),
+            Some(Vec2 { x: 0.0, y: 0.0 })
+        );
+        assert_eq!(a.penetration(Circle { x: 6.5, ..b }), None);
+    }
+
+    #[test]
+    fn test_rect_vs_rect() {
+        let a = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 12,
+            h: 12,
+        };
+        let b = Rect {
+            x: 10.0,
+            y: 4.0,
+            w: 16,
+            h: 16,
+        };
+        assert_eq!(a.overlap(b), Some(Vec2 { x: 2.0, y: 8.0 }));
+        assert_eq!(
+            Shape::Rect(a).mtv(Shape::Rect(b)),
+            Some(Vec2 { x: -2.0, y: 0.0 })
+        );
+        assert_eq!(
+            Shape::Rect(b).mtv(Shape::Rect(a)),
+            Some(Vec2 { x: 2.0, y: 0.0 })
+        );
+    }
 }
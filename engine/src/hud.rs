@@ -0,0 +1,271 @@
+use crate::font::Font;
+use crate::geom::Vec2;
+use frenderer::sprites::{Camera2D, SheetRegion, Transform};
+use frenderer::Immediate;
+
+// Hearts, bars and text drawn over the game in screen pixels, so they stay put
+// however the cameras pan, zoom or split.  A game fills one in each frame and draws
+// it into a sprite group of its own, after the world's, whose camera is `camera()`.
+// It all goes in front of the world, which stays at depth 2 and up.
+pub struct Hud {
+    size: Vec2,
+    font: Font,
+    // any opaque sheet region, for bars; they're tinted whatever color they need
+    solid: SheetRegion,
+    sprites: Vec<(Transform, SheetRegion)>,
+}
+
+// Which side of its anchor something goes: `Left` starts there and runs right,
+// `Right` ends there
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    // the left edge of something `w` wide, anchored at `x`
+    fn left(self, x: f32, w: f32) -> f32 {
+        match self {
+            Align::Left => x,
+            Align::Center => x - w / 2.0,
+            Align::Right => x - w,
+        }
+    }
+}
+
+// the depths of the HUD's backgrounds and of what goes on them
+const BACK: u16 = 1;
+const FRONT: u16 = 0;
+// hearts that have been lost are drawn in this shade
+const LOST: [u8; 4] = [40, 40, 40, 200];
+// the empty part of a bar
+const EMPTY: [u8; 4] = [20, 20, 20, 255];
+// how much bigger banner text is than the rest, and the strip behind it
+const BANNER_SCALE: u16 = 2;
+const BANNER_BACK: [u8; 4] = [0, 0, 0, 255];
+
+impl Hud {
+    pub fn new(screen_w: f32, screen_h: f32, font: Font, solid: SheetRegion) -> Self {
+        Self {
+            size: Vec2 {
+                x: screen_w,
+                y: screen_h,
+            },
+            font,
+            solid,
+            sprites: Vec::new(),
+        }
+    }
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+    // The camera for the HUD's sprite group: one world pixel per screen pixel, with
+    // (0, 0) at the bottom left
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            screen_pos: [0.0, 0.0],
+            screen_size: [self.size.x, self.size.y],
+        }
+    }
+    pub fn sprites(&self) -> &[(Transform, SheetRegion)] {
+        &self.sprites
+    }
+    // Forget last frame's HUD
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+    // A row of `max` hearts with the top of the row at `anchor`, the first `full`
+    // of them filled in (counting from the anchor's side, so player 2's run from
+    // the right)
+    pub fn hearts(&mut self, anchor: Vec2, align: Align, full: u8, max: u8, heart: SheetRegion) {
+        let (w, h) = (heart.w.unsigned_abs(), heart.h.unsigned_abs());
+        let step = (w + 1) as f32;
+        let left = align.left(anchor.x, step * max as f32 - 1.0);
+        for i in 0..max {
+            // on the right, count from the right
+            let slot = if align == Align::Right {
+                max - 1 - i
+            } else {
+                i
+            };
+            let region = if i < full {
+                heart
+            } else {
+                heart.with_colormod(LOST)
+            };
+            self.sprites.push((
+                Transform {
+                    x: left + slot as f32 * step + w as f32 / 2.0,
+                    y: anchor.y - h as f32 / 2.0,
+                    w,
+                    h,
+                    rot: 0.0,
+                },
+                region.with_depth(FRONT),
+            ));
+        }
+    }
+    // A bar `size` big with its top at `anchor`, filled `fill` (0 to 1) of the way
+    // from the anchor's side in `color`
+    pub fn bar(&mut self, anchor: Vec2, align: Align, size: Vec2, fill: f32, color: [u8; 4]) {
+        let left = align.left(anchor.x, size.x);
+        let y = anchor.y - size.y / 2.0;
+        let full = (size.x * fill.clamp(0.0, 1.0)).round();
+        let (w, h) = (size.x as u16, size.y as u16);
+        // the empty part's under the full part, so it doesn't matter where it ends
+        self.sprites.push((
+            Transform {
+                x: left + size.x / 2.0,
+                y,
+                w,
+                h,
+                rot: 0.0,
+            },
+            self.solid.with_colormod(EMPTY).with_depth(BACK),
+        ));
+        if full > 0.0 {
+            let start = if align == Align::Right {
+                left + size.x - full
+            } else {
+                left
+            };
+            self.sprites.push((
+                Transform {
+                    x: start + full / 2.0,
+                    y,
+                    w: full as u16,
+                    h,
+                    rot: 0.0,
+                },
+                self.solid.with_colormod(color).with_depth(FRONT),
+            ));
+        }
+    }
+    // A line of text with its top at `anchor`
+    pub fn text(&mut self, anchor: Vec2, align: Align, text: &str, scale: u16) {
+        let size = self.font.measure(text, scale);
+        let corner = Vec2 {
+            x: align.left(anchor.x, size.x),
+            y: anchor.y - size.y,
+        };
+        self.sprites
+            .extend(self.font.layout(text, corner, scale, FRONT));
+    }
+    // Big text across the middle of the screen on a dark strip, like "PLAYER 1 WINS"
+    pub fn banner(&mut self, text: &str) {
+        let size = self.font.measure(text, BANNER_SCALE);
+        let middle = self.size * 0.5;
+        let strip = Vec2 {
+            x: self.size.x,
+            y: size.y + 8.0,
+        };
+        self.sprites.push((
+            Transform {
+                x: middle.x,
+                y: middle.y,
+                w: strip.x as u16,
+                h: strip.y as u16,
+                rot: 0.0,
+            },
+            self.solid.with_colormod(BANNER_BACK).with_depth(BACK),
+        ));
+        let top = Vec2 {
+            x: middle.x,
+            y: middle.y + size.y / 2.0,
+        };
+        self.text(top, Align::Center, text, BANNER_SCALE);
+    }
+    // Puts this frame's HUD into sprite group `group`
+    pub fn draw(&self, frend: &mut Immediate, group: usize) {
+        let (trfs, uvs) = frend.draw_sprites(group, self.sprites.len());
+        for ((trf, uv), (to_trf, to_uv)) in
+            self.sprites.iter().zip(trfs.iter_mut().zip(uvs.iter_mut()))
+        {
+            *to_trf = *trf;
+            *to_uv = *uv;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEART: SheetRegion = SheetRegion::rect(525, 35, 8, 8);
+    const SOLID: SheetRegion = SheetRegion::rect(419, 61, 16, 16);
+
+    fn hud() -> Hud {
+        Hud::new(320.0, 240.0, Font::default(), SOLID)
+    }
+
+    // the left, bottom, right and top edges of a sprite
+    fn edges(trf: &Transform) -> [f32; 4] {
+        let (w, h) = (trf.w as f32 / 2.0, trf.h as f32 / 2.0);
+        [trf.x - w, trf.y - h, trf.x + w, trf.y + h]
+    }
+
+    #[test]
+    fn test_hearts_and_bars() {
+        let mut hud = hud();
+        let top_left = Vec2 { x: 4.0, y: 236.0 };
+        hud.hearts(top_left, Align::Left, 2, 3, HEART);
+        let hearts = hud.sprites().to_vec();
+        assert_eq!(hearts.len(), 3);
+        assert_eq!(edges(&hearts[0].0), [4.0, 228.0, 12.0, 236.0]);
+        assert_eq!(edges(&hearts[2].0)[0], 22.0);
+        assert_eq!(hearts[1].1.colormod, HEART.colormod);
+        assert_eq!(hearts[2].1.colormod, LOST);
+
+        // player 2's hearts end at the right edge and lose from the left
+        hud.clear();
+        hud.hearts(Vec2 { x: 316.0, y: 236.0 }, Align::Right, 1, 3, HEART);
+        let rightmost = hud.sprites()[0];
+        assert_eq!(edges(&rightmost.0)[2], 316.0);
+        assert_eq!(rightmost.1.colormod, HEART.colormod);
+        assert_eq!(hud.sprites()[1].1.colormod, LOST);
+
+        // a bar that's a quarter full, from the right
+        hud.clear();
+        let size = Vec2 { x: 40.0, y: 3.0 };
+        hud.bar(
+            Vec2 { x: 316.0, y: 226.0 },
+            Align::Right,
+            size,
+            0.25,
+            [255, 0, 0, 255],
+        );
+        let [back, front] = [hud.sprites()[0], hud.sprites()[1]];
+        assert_eq!(edges(&back.0), [276.0, 223.0, 316.0, 226.0]);
+        assert_eq!(edges(&front.0), [306.0, 223.0, 316.0, 226.0]);
+        assert_eq!(front.1.colormod, [255, 0, 0, 255]);
+        // an empty one's just the background
+        hud.clear();
+        hud.bar(top_left, Align::Left, size, 0.0, [255, 0, 0, 255]);
+        assert_eq!(hud.sprites().len(), 1);
+    }
+
+    #[test]
+    fn test_text_and_banner() {
+        let mut hud = hud();
+        hud.text(Vec2 { x: 160.0, y: 236.0 }, Align::Center, "ENEMIES 3", 1);
+        // nine cells of 6 pixels, less the space, centered
+        assert_eq!(hud.sprites().len(), 8);
+        assert_eq!(edges(&hud.sprites()[0].0), [133.0, 228.0, 139.0, 236.0]);
+
+        hud.clear();
+        hud.banner("PLAYER 1 WINS");
+        let strip = hud.sprites()[0].0;
+        assert_eq!((strip.x, strip.y, strip.w), (160.0, 120.0, 320));
+        // the letters are all on the strip, in the middle of the screen
+        let letters = &hud.sprites()[1..];
+        assert_eq!(letters.len(), 11);
+        for (trf, _) in letters {
+            let [left, bottom, right, top] = edges(trf);
+            assert!(left >= 0.0 && right <= 320.0);
+            assert!(bottom >= 112.0 && top <= 128.0);
+        }
+        assert_eq!(hud.camera().screen_size, [320.0, 240.0]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::gamepad::{Gamepads, PadSource};
+pub use frenderer::input::Key;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::keyboard::PhysicalKey;
+
+// The keyboard (and gamepad) state a game sees each step.  `run` fills it in from window
+// events (just like frenderer's `Input`) and the gamepad driver, but tests and headless
+// runs can press keys on it and plug in fake pads directly.
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    now_keys: Vec<Key>,
+    prev_keys: Vec<Key>,
+    pads: Gamepads,
+}
+
+impl InputState {
+    pub fn process_input_event<T>(&mut self, ev: &Event<T>) {
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(key),
+                            state,
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } = ev
+        {
+            match state {
+                ElementState::Pressed => self.press(*key),
+                ElementState::Released => self.release(*key),
+            }
+        }
+    }
+    // Holds `key` down starting this frame
+    pub fn press(&mut self, key: Key) {
+        if !self.now_keys.contains(&key) {
+            self.now_keys.push(key);
+        }
+    }
+    pub fn release(&mut self, key: Key) {
+        self.now_keys.retain(|k| *k != key);
+    }
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.now_keys.contains(&key)
+    }
+    pub fn is_key_up(&self, key: Key) -> bool {
+        !self.now_keys.contains(&key)
+    }
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.now_keys.contains(&key) && !self.prev_keys.contains(&key)
+    }
+    pub fn is_key_released(&self, key: Key) -> bool {
+        !self.now_keys.contains(&key) && self.prev_keys.contains(&key)
+    }
+    // the keys that went down this frame, in the order they were pressed
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.now_keys
+            .iter()
+            .copied()
+            .filter(|key| !self.prev_keys.contains(key))
+    }
+    // -1 if only `down` is held, 1 if only `up` is, 0 otherwise
+    pub fn key_axis(&self, down: Key, up: Key) -> f32 {
+        (if self.is_key_down(down) { -1.0 } else { 0.0 })
+            + (if self.is_key_down(up) { 1.0 } else { 0.0 })
+    }
+    pub fn pads(&self) -> &Gamepads {
+        &self.pads
+    }
+    // Catches up on what the gamepads have been doing
+    pub fn poll_pads(&mut self, source: &mut dyn PadSource) {
+        self.pads.poll(source);
+    }
+    // Call after each step so keys held since last frame stop counting as pressed
+    pub fn next_frame(&mut self) {
+        self.prev_keys.clear();
+        self.prev_keys.extend_from_slice(&self.now_keys);
+        self.pads.next_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressed_then_held() {
+        let mut input = InputState::default();
+        input.press(Key::Space);
+        assert!(input.is_key_pressed(Key::Space));
+        assert!(input.is_key_down(Key::Space));
+        input.next_frame();
+        assert!(!input.is_key_pressed(Key::Space));
+        assert!(input.is_key_down(Key::Space));
+        input.release(Key::Space);
+        assert!(input.is_key_released(Key::Space));
+        assert!(input.is_key_up(Key::Space));
+        input.press(Key::KeyW);
+        assert_eq!(input.key_axis(Key::KeyS, Key::KeyW), 1.0);
+    }
+}
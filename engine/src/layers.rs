@@ -0,0 +1,312 @@
+use crate::collision::{gather_contacts_2, gather_level_contacts_2, sort_contacts_2};
+use crate::geom::*;
+use crate::level::Level;
+
+// Collision layers are bits; games pick their own (players, enemies, bullets, ...) and
+// the level's solid tiles are always this one
+pub const TILES: u32 = 1 << 31;
+
+// Something that can collide.  `key` is how the game finds the entity again (e.g. which
+// vec it's in and where), `layer` is what it is and `mask` is which layers it
+// collides with.
+#[derive(Clone, Copy, Debug)]
+pub struct Collider<K> {
+    pub key: K,
+    pub shape: Shape,
+    pub layer: u32,
+    pub mask: u32,
+}
+
+// One contact for a handler: `b` is None when `a` ran into the level.
+// `displacement` pushes a out of b, and `normal` is its direction.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit<K> {
+    pub a: K,
+    pub a_shape: Shape,
+    pub b: Option<K>,
+    pub b_shape: Shape,
+    pub displacement: Vec2,
+    pub normal: Vec2,
+}
+
+pub type Handler<W, K> = fn(&mut W, &Hit<K>);
+
+// The colliders for one tick, plus what to do when each pair of layers meets.
+// Registering `on(BULLET, ENEMY, damage)` means every bullet collider whose mask
+// includes ENEMY gets `damage` called for each enemy collider (whose mask includes
+// BULLET) it touches.
+pub struct Collisions<W, K> {
+    colliders: Vec<Collider<K>>,
+    handlers: Vec<(u32, u32, Handler<W, K>)>,
+}
+
+impl<W, K> Default for Collisions<W, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W, K> Collisions<W, K> {
+    pub fn new() -> Self {
+        Self {
+            colliders: Vec::new(),
+            handlers: Vec::new(),
+        }
+    }
+    // Handlers run in the order they're registered
+    pub fn on(&mut self, a_layer: u32, b_layer: u32, handler: Handler<W, K>) -> &mut Self {
+        self.handlers.push((a_layer, b_layer, handler));
+        self
+    }
+    pub fn add(&mut self, key: K, shape: Shape, layer: u32, mask: u32) {
+        self.colliders.push(Collider {
+            key,
+            shape,
+            layer,
+            mask,
+        });
+    }
+    pub fn clear(&mut self) {
+        self.colliders.clear();
+    }
+}
+
+impl<W, K: Copy> Collisions<W, K> {
+    // Every contact, paired with the handler for it.  Each handler's contacts with the
+    // level come deepest first.  This only looks; call `dispatch` to respond, once
+    // nothing borrows the level any more.
+    pub fn detect<E>(&self, level: &Level<E>) -> Vec<(Handler<W, K>, Hit<K>)> {
+        let mut hits = Vec::new();
+        for &(a_layer, b_layer, handler) in self.handlers.iter() {
+            let a_side: Vec<&Collider<K>> = self
+                .colliders
+                .iter()
+                .filter(|c| c.layer & a_layer != 0 && c.mask & b_layer != 0)
+                .collect();
+            let a_shapes: Vec<Shape> = a_side.iter().map(|c| c.shape).collect();
+            if b_layer == TILES {
+                let mut contacts = gather_level_contacts_2(&a_shapes, level);
+                sort_contacts_2(&mut contacts);
+                hits.extend(contacts.into_iter().map(|contact| {
+                    let hit = Hit {
+                        a: a_side[contact.a_i].key,
+                        a_shape: contact.a_r,
+                        b: None,
+                        b_shape: contact.b_r,
+                        displacement: contact.displacement,
+                        normal: contact.normal,
+                    };
+                    (handler, hit)
+                }));
+                continue;
+            }
+            let b_side: Vec<&Collider<K>> = self
+                .colliders
+                .iter()
+                .filter(|c| c.layer & b_layer != 0 && c.mask & a_layer != 0)
+                .collect();
+            let b_shapes: Vec<Shape> = b_side.iter().map(|c| c.shape).collect();
+            hits.extend(
+                gather_contacts_2(&a_shapes, &b_shapes, level.tile_sz() as f32)
+                    .into_iter()
+                    .map(|contact| {
+                        let hit = Hit {
+                            a: a_side[contact.a_i].key,
+                            a_shape: contact.a_r,
+                            b: Some(b_side[contact.b_i].key),
+                            b_shape: contact.b_r,
+                            displacement: contact.displacement,
+                            normal: contact.normal,
+                        };
+                        (handler, hit)
+                    }),
+            );
+        }
+        hits
+    }
+}
+
+pub fn dispatch<W, K>(world: &mut W, hits: &[(Handler<W, K>, Hit<K>)]) {
+    for (handler, hit) in hits {
+        handler(world, hit);
+    }
+}
+
+// What the stock handlers need to move an entity
+pub trait Body {
+    // what it collides as where it is right now (the shape it was added with)
+    fn shape(&self) -> Shape;
+    fn pos_mut(&mut self) -> &mut Vec2;
+    // heading in radians, as for `dir_to_vec2`
+    fn dir_mut(&mut self) -> &mut f32;
+}
+
+// Lets the stock handlers find the entity a collider's key names
+pub trait Bodies<K> {
+    type Body: Body;
+    fn body_mut(&mut self, key: K) -> Option<&mut Self::Body>;
+}
+
+// How far `a` is into what it hit from where it is now, if it still is; an earlier
+// contact this tick may already have pushed it clear
+fn still_hit<B: Body, K>(body: &B, hit: &Hit<K>) -> Option<Penetration> {
+    body.shape().penetration(hit.b_shape)
+}
+
+// Moves `a` out of whatever it hit
+pub fn push_out<W: Bodies<K>, K: Copy>(world: &mut W, hit: &Hit<K>) {
+    if let Some(body) = world.body_mut(hit.a) {
+        if let Some(pen) = still_hit(body, hit) {
+            *body.pos_mut() += pen.mtv();
+        }
+    }
+}
+
+// Moves `a` out and reflects its heading off the surface, unless an earlier contact
+// this tick already turned it away
+pub fn bounce<W: Bodies<K>, K: Copy>(world: &mut W, hit: &Hit<K>) {
+    if let Some(body) = world.body_mut(hit.a) {
+        let Some(pen) = still_hit(body, hit) else {
+            return;
+        };
+        *body.pos_mut() += pen.mtv();
+        let vel = dir_to_vec2(*body.dir_mut());
+        let into = vel.dot(pen.normal);
+        if into < 0.0 {
+            *body.dir_mut() = vec2_to_dir(vel - pen.normal * (2.0 * into));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Thing {
+        pos: Vec2,
+        dir: f32,
+        hits: usize,
+    }
+    // a 10x10 box with its top left corner at `pos`
+    impl Body for Thing {
+        fn shape(&self) -> Shape {
+            Shape::Rect(Rect {
+                x: self.pos.x,
+                y: self.pos.y,
+                w: 10,
+                h: 10,
+            })
+        }
+        fn pos_mut(&mut self) -> &mut Vec2 {
+            &mut self.pos
+        }
+        fn dir_mut(&mut self) -> &mut f32 {
+            &mut self.dir
+        }
+    }
+    struct World(Vec<Thing>);
+    impl Bodies<usize> for World {
+        type Body = Thing;
+        fn body_mut(&mut self, key: usize) -> Option<&mut Thing> {
+            self.0.get_mut(key)
+        }
+    }
+
+    struct NoStarts;
+    impl crate::level::StartKind for NoStarts {
+        fn from_start(_kind: &str, _args: &[&str]) -> Option<Self> {
+            None
+        }
+    }
+
+    const A: u32 = 1;
+    const B: u32 = 2;
+
+    fn count(world: &mut World, hit: &Hit<usize>) {
+        world.0[hit.a].hits += 1;
+    }
+
+    fn square(x: f32) -> Shape {
+        Shape::Rect(Rect {
+            x,
+            y: 20.0,
+            w: 10,
+            h: 10,
+        })
+    }
+
+    fn thing(pos: Vec2) -> Thing {
+        Thing {
+            pos,
+            dir: 0.0,
+            hits: 0,
+        }
+    }
+
+    fn room() -> Level<NoStarts> {
+        Level::parse(
+            "room 4 4\n====\n. o 0 0 16 16\n# s 16 0 16 16\n====\n####\n#..#\n#..#\n####\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_layers_and_masks() {
+        let level = room();
+        let mut world = World(
+            [12.0, 18.0, 18.0, 20.0]
+                .map(|x| thing(Vec2 { x, y: 20.0 }))
+                .into(),
+        );
+        let mut collisions = Collisions::new();
+        collisions.on(A, B, count).on(A, TILES, push_out);
+        // 0 and 1 overlap each other, and 0 is in the left wall
+        collisions.add(0, square(12.0), A, B | TILES);
+        collisions.add(1, square(18.0), B, A);
+        // 2 overlaps 1 too, but is on layer B itself
+        collisions.add(2, square(18.0), B, B);
+        // 3 is on A but doesn't collide with B
+        collisions.add(3, square(20.0), A, TILES);
+        let hits = collisions.detect(&level);
+        dispatch(&mut world, &hits);
+        assert_eq!(world.0[0].hits, 1);
+        assert_eq!(world.0[3].hits, 0);
+        assert_eq!(world.0[0].pos, Vec2 { x: 16.0, y: 20.0 });
+        assert_eq!(world.0[3].pos, Vec2 { x: 20.0, y: 20.0 });
+    }
+
+    #[test]
+    fn test_push_out_of_wide_wall() {
+        // flat against two tiles of the left wall at once, which only push it out once
+        let level = room();
+        let pos = Vec2 { x: 12.0, y: 24.0 };
+        let mut world = World(vec![thing(pos)]);
+        let mut collisions = Collisions::new();
+        collisions.on(A, TILES, push_out);
+        collisions.add(0, world.0[0].shape(), A, TILES);
+        let hits = collisions.detect(&level);
+        assert_eq!(hits.len(), 2);
+        dispatch(&mut world, &hits);
+        assert_eq!(world.0[0].pos, Vec2 { x: 16.0, y: 24.0 });
+    }
+
+    #[test]
+    fn test_bounce() {
+        let mut world = World(vec![thing(Vec2 { x: 0.0, y: 20.0 })]);
+        let hit = Hit {
+            a: 0,
+            a_shape: square(0.0),
+            b: None,
+            b_shape: square(8.0),
+            displacement: Vec2 { x: -2.0, y: 0.0 },
+            normal: Vec2 { x: -1.0, y: 0.0 },
+        };
+        bounce(&mut world, &hit);
+        assert_eq!(world.0[0].pos, Vec2 { x: -2.0, y: 20.0 });
+        assert!(dir_to_vec2(world.0[0].dir).x < -0.99);
+        // a second contact with the same wall neither moves it again nor flips it back
+        bounce(&mut world, &hit);
+        assert_eq!(world.0[0].pos, Vec2 { x: -2.0, y: 20.0 });
+        assert!(dir_to_vec2(world.0[0].dir).x < -0.99);
+    }
+}
@@ -1,8 +1,5 @@
 use crate::geom::*;
 use crate::grid::{self, Grid};
-use crate::EntityType;
-use crate::TileData;
-use crate::TILE_SZ;
 use frenderer::{
     sprites::{SheetRegion, Transform},
     Immediate,
@@ -10,23 +7,212 @@ use frenderer::{
 use std::collections::HashMap;
 use std::str::FromStr;
 
+// Used when a level doesn't declare a `tile` size in its metadata
+pub const DEFAULT_TILE_SZ: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TileData {
+    pub solid: bool,
+    pub sheet_region: SheetRegion,
+    pub slippery: bool,
+}
+
+// Each game has its own entity types; implementing this lets `Level` build them
+// from the starts section.  `kind` is the first word of a start line and `args`
+// are the words between it and the trailing X Y, e.g. `door level1 3 11 3 11`
+// gives kind "door" and args ["level1", "3", "11"].
+pub trait StartKind: Sized {
+    fn from_start(kind: &str, args: &[&str]) -> Option<Self>;
+    // If this start is a door, the name of the level it leads to and the grid X Y
+    // to arrive at there.
+    fn door(&self) -> Option<(&str, u16, u16)> {
+        None
+    }
+}
+
+// What went wrong while parsing a level file, and where (1-based line and column).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelError {
+    pub line: usize,
+    pub col: usize,
+    pub kind: LevelErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelErrorKind {
+    // the line ended before this field
+    Missing(&'static str),
+    BadNumber(String),
+    DuplicateMetadata(&'static str),
+    MissingName,
+    BadFlag(String),
+    BadProperty(String),
+    DuplicateSymbol(String),
+    TooManySymbols,
+    BadRowMode(String),
+    ZeroTileSize,
+    LongSymbol(String),
+    EmptyLegend,
+    UnknownSymbol(String),
+    ShortRow { expected: usize, found: usize },
+    LongRow { expected: usize, found: usize },
+    MissingRows { expected: usize, found: usize },
+    ExtraRow,
+    UnknownEntity(String),
+    StartOutOfBounds(u16, u16),
+    TrailingContent,
+}
+
+impl std::fmt::Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.col)?;
+        match &self.kind {
+            LevelErrorKind::Missing(what) => write!(f, "expected {what}"),
+            LevelErrorKind::BadNumber(text) => write!(f, "couldn't parse {text:?} as a number"),
+            LevelErrorKind::DuplicateMetadata(what) => write!(f, "two {what} entries in metadata"),
+            LevelErrorKind::MissingName => write!(f, "no LEVELNAME W H line in metadata"),
+            LevelErrorKind::BadFlag(flag) => {
+                write!(f, "tile flag {flag:?} should be o(pen) or s(olid)")
+            }
+            LevelErrorKind::BadProperty(prop) => {
+                write!(f, "tile property {prop:?} should be n(one) or s(lippery)")
+            }
+            LevelErrorKind::DuplicateSymbol(sym) => write!(f, "symbol {sym:?} already in legend"),
+            LevelErrorKind::TooManySymbols => write!(f, "legend has more than 256 symbols"),
+            LevelErrorKind::BadRowMode(mode) => {
+                write!(f, "rows mode {mode:?} should be packed or spaced")
+            }
+            LevelErrorKind::ZeroTileSize => write!(f, "tile size must be more than 0"),
+            LevelErrorKind::LongSymbol(sym) => {
+                write!(f, "symbol {sym:?} must be one character in a packed map")
+            }
+            LevelErrorKind::EmptyLegend => write!(f, "legend is empty"),
+            LevelErrorKind::UnknownSymbol(sym) => write!(f, "symbol {sym:?} isn't in the legend"),
+            LevelErrorKind::ShortRow { expected, found } => {
+                write!(
+                    f,
+                    "map row has {found} tiles but the level is {expected} wide"
+                )
+            }
+            LevelErrorKind::LongRow { expected, found } => {
+                write!(
+                    f,
+                    "map row has {found} tiles but the level is {expected} wide"
+                )
+            }
+            LevelErrorKind::MissingRows { expected, found } => {
+                write!(f, "map has {found} rows but the level is {expected} tall")
+            }
+            LevelErrorKind::ExtraRow => write!(f, "map has more rows than the level is tall"),
+            LevelErrorKind::UnknownEntity(kind) => {
+                write!(f, "unrecognized entity type or arguments for {kind:?}")
+            }
+            LevelErrorKind::StartOutOfBounds(x, y) => {
+                write!(f, "start position {x} {y} is outside the map")
+            }
+            LevelErrorKind::TrailingContent => {
+                write!(f, "unexpected content after parsing finished")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+// The whitespace-separated words of one line, remembering which column each starts at
+// so errors can point right at them.
+struct Words<'a> {
+    text: &'a str,
+    line: usize,
+    end: usize,
+    words: std::vec::IntoIter<(usize, &'a str)>,
+}
+
+impl<'a> Words<'a> {
+    fn new(line: usize, text: &'a str) -> Self {
+        let mut words = vec![];
+        let mut start = None;
+        let mut end = 1;
+        for (col, (i, c)) in text.char_indices().enumerate() {
+            if c.is_whitespace() {
+                if let Some((b, wc)) = start.take() {
+                    words.push((wc, &text[b..i]));
+                }
+            } else if start.is_none() {
+                start = Some((i, col + 1));
+            }
+            end = col + 2;
+        }
+        if let Some((b, wc)) = start {
+            words.push((wc, &text[b..]));
+        }
+        Self {
+            text,
+            line,
+            end,
+            words: words.into_iter(),
+        }
+    }
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+    fn error(&self, col: usize, kind: LevelErrorKind) -> LevelError {
+        LevelError {
+            line: self.line,
+            col,
+            kind,
+        }
+    }
+    fn next(&mut self, what: &'static str) -> Result<(usize, &'a str), LevelError> {
+        self.words
+            .next()
+            .ok_or_else(|| self.error(self.end, LevelErrorKind::Missing(what)))
+    }
+    fn number<T: FromStr>(&mut self, what: &'static str) -> Result<T, LevelError> {
+        let (col, word) = self.next(what)?;
+        self.parse(col, word)
+    }
+    fn parse<T: FromStr>(&self, col: usize, word: &str) -> Result<T, LevelError> {
+        T::from_str(word).map_err(|_| self.error(col, LevelErrorKind::BadNumber(word.to_string())))
+    }
+    fn rest(&mut self) -> Vec<(usize, &'a str)> {
+        self.words.by_ref().collect()
+    }
+    // every non-whitespace character as its own word, for packed map rows
+    fn chars(&self) -> Vec<(usize, &'a str)> {
+        self.text
+            .char_indices()
+            .enumerate()
+            .filter(|(_, (_, c))| !c.is_whitespace())
+            .map(|(col, (i, c))| (col + 1, &self.text[i..i + c.len_utf8()]))
+            .collect()
+    }
+}
+
 #[allow(dead_code)]
-pub struct Level {
+pub struct Level<E> {
     name: String,
     bg: SheetRegion,
     grid: Grid<u8>,
     tileset: Tileset,
-    starts: Vec<(EntityType, Vec2)>,
+    starts: Vec<(E, Vec2)>,
+    tile_sz: usize,
+    seed: Option<u64>,
 }
 
-impl Level {
+impl<E: StartKind> Level<E> {
     /*
-    We'll read from an ad hoc format like this, where FLAGS is either S (solid) or O (open) but could be other stuff later:
+    We'll read from an ad hoc format like this, where FLAGS is either S (solid) or O (open) but could be other stuff later
+    and the optional PROPERTY is either N (none) or S (slippery):
 
     LEVELNAME W H
+    bg X Y W H
+    tile SZ
+    seed N
+    rows packed|spaced
     ====
     SYM FLAGS X Y W H
-    SYM FLAGS X Y W H
+    SYM FLAGS PROPERTY X Y W H
     SYM FLAGS X Y W H
     ====
     SYM SYM SYM SYM SYM
@@ -40,9 +226,18 @@ impl Level {
     enemy X Y
     enemy X Y
     door LEVELNAME TO-X TO-Y X Y
-    you can add more types of thing if you want
+    you can add more types of thing if you want (see StartKind)
+
+    Map rows can also be packed, one character per tile with no spaces:
+
+    #....#
+    #.##.#
+
+    `rows packed` or `rows spaced` picks one explicitly; otherwise, if every legend symbol
+    is one character, each non-whitespace character is a tile, so both styles work.
     */
-    pub fn from_str(s: &str) -> Self {
+    pub fn parse(s: &str) -> Result<Self, LevelError> {
+        #[derive(Clone, Copy)]
         enum State {
             Metadata,
             Legend,
@@ -63,163 +258,263 @@ impl Level {
         }
         let mut state = State::Metadata;
         let mut name = None;
-        let mut dims = None;
+        let mut dims: Option<(u16, u16)> = None;
         let mut legend: HashMap<String, (u8, TileData)> = std::collections::HashMap::new();
         let mut grid = vec![];
         let mut starts = vec![];
-        let mut bg = SheetRegion::ZERO;
-        for line in s.lines() {
-            if line.is_empty() {
+        let mut bg = None;
+        let mut tile_sz = None;
+        let mut seed = None;
+        let mut packed: Option<bool> = None;
+        // Checks that the section we're leaving is complete; `at` is the line/col to blame.
+        fn finish(
+            state: State,
+            dims: Option<(u16, u16)>,
+            legend_len: usize,
+            grid_len: usize,
+            (line, col): (usize, usize),
+        ) -> Result<(), LevelError> {
+            let kind = match state {
+                State::Metadata if dims.is_none() => LevelErrorKind::MissingName,
+                State::Legend if legend_len == 0 => LevelErrorKind::EmptyLegend,
+                State::Map => {
+                    let (w, h) = dims.unwrap();
+                    let rows = grid_len / (w as usize).max(1);
+                    if rows >= h as usize {
+                        return Ok(());
+                    }
+                    LevelErrorKind::MissingRows {
+                        expected: h as usize,
+                        found: rows,
+                    }
+                }
+                _ => return Ok(()),
+            };
+            Err(LevelError { line, col, kind })
+        }
+        let mut eof = (1, 1);
+        for (line_no, line) in s.lines().enumerate() {
+            let line_no = line_no + 1;
+            eof = (line_no + 1, 1);
+            if line.trim().is_empty() {
                 continue;
             } else if line.chars().all(|c| c == '=') {
+                finish(state, dims, legend.len(), grid.len(), (line_no, 1))?;
                 state = state.next();
-            } else {
-                match state {
-                    State::Metadata => {
-                        let mut chunks = line.split_whitespace();
-                        let md = chunks
-                            .next()
-                            .expect("No metadata decl string in metadata line {line}");
-                        if md == "bg" {
-                            if bg.w != 0 {
-                                panic!("Two bg entries in metadata");
-                            }
-                            bg = SheetRegion::rect(
-                                u16::from_str(chunks.next().expect("No x in metadata line {line}"))
-                                    .expect("Couldn't parse x as u16 in {line}"),
-                                u16::from_str(chunks.next().expect("No y in metadata line {line}"))
-                                    .expect("Couldn't parse y as u16 in {line}"),
-                                i16::from_str(
-                                    chunks.next().expect("No width in metadata line {line}"),
-                                )
-                                .expect("Couldn't parse width as i16 in {line}"),
-                                i16::from_str(
-                                    chunks.next().expect("No height in metadata line {line}"),
-                                )
-                                .expect("Couldn't parse height as i16 in {line}"),
+                continue;
+            }
+            let mut words = Words::new(line_no, line);
+            match state {
+                State::Metadata => {
+                    let (col, md) = words.next("a metadata entry")?;
+                    if md == "bg" {
+                        if bg.is_some() {
+                            return Err(words.error(col, LevelErrorKind::DuplicateMetadata("bg")));
+                        }
+                        bg = Some(
+                            SheetRegion::rect(
+                                words.number("a bg x")?,
+                                words.number("a bg y")?,
+                                words.number("a bg width")?,
+                                words.number("a bg height")?,
                             )
-                            .with_depth(u16::MAX - 1);
-                        } else {
-                            if name.is_some() {
-                                panic!("Two name entries in metadata");
-                            }
-                            name = Some(md.to_string());
-                            dims = Some((
-                                u16::from_str(
-                                    chunks.next().expect("No width in metadata line {line}"),
-                                )
-                                .expect("Couldn't parse width as u16 in {line}"),
-                                u16::from_str(
-                                    chunks.next().expect("No height in metadata line {line}"),
-                                )
-                                .expect("Couldn't parse height as u16 in {line}"),
-                            ));
+                            .with_depth(u16::MAX - 1),
+                        );
+                    } else if md == "tile" {
+                        if tile_sz.is_some() {
+                            return Err(words.error(col, LevelErrorKind::DuplicateMetadata("tile")));
                         }
-                    }
-                    State::Legend => {
-                        let mut chunks = line.split_whitespace();
-                        let sym = chunks.next().expect("Couldn't get tile symbol in {line}");
-                        assert!(!legend.contains_key(sym), "Symbol {sym} already in legend");
-                        let flags = chunks
-                            .next()
-                            .expect("Couldn't get tile flags in {line}")
-                            .to_lowercase();
-                        assert!(flags == "o" || flags == "s", "The only valid flags are o(pen) or s(olid), but you could parse other kinds here in {line}");
-                        let property = chunks
-                            .next()
-                            .expect("Couldn't get tile property in {line}")
-                            .to_lowercase();
-                        assert!(property == "n" || property == "s", "The only valid properties are n(one) or s(lippery), but you could parse other kinds here in {line}");
-                        let x =
-                            u16::from_str(chunks.next().expect("No sheet x in legend line {line}"))
-                                .expect("Couldn't parse sheet x as u16 in {line}");
-                        let y =
-                            u16::from_str(chunks.next().expect("No sheet y in legend line {line}"))
-                                .expect("Couldn't parse sheet y as u16 in {line}");
-                        let w =
-                            i16::from_str(chunks.next().expect("No sheet w in legend line {line}"))
-                                .expect("Couldn't parse sheet w as i16 in {line}");
-                        let h =
-                            i16::from_str(chunks.next().expect("No sheet h in legend line {line}"))
-                                .expect("Couldn't parse sheet h as i16 in {line}");
-                        let data = TileData {
-                            solid: flags == "s",
-                            sheet_region: SheetRegion::new(0, x, y, 16, w, h),
-                            slippery: property == "s",
+                        let (col, word) = words.next("a tile size")?;
+                        let sz = words.parse(col, word)?;
+                        if sz == 0 {
+                            return Err(words.error(col, LevelErrorKind::ZeroTileSize));
+                        }
+                        tile_sz = Some(sz);
+                    } else if md == "seed" {
+                        if seed.is_some() {
+                            return Err(words.error(col, LevelErrorKind::DuplicateMetadata("seed")));
+                        }
+                        seed = Some(words.number("a random seed")?);
+                    } else if md == "rows" {
+                        if packed.is_some() {
+                            return Err(words.error(col, LevelErrorKind::DuplicateMetadata("rows")));
+                        }
+                        let (col, mode) = words.next("packed or spaced")?;
+                        packed = match mode {
+                            "packed" => Some(true),
+                            "spaced" => Some(false),
+                            _ => {
+                                return Err(
+                                    words.error(col, LevelErrorKind::BadRowMode(mode.to_string()))
+                                )
+                            }
                         };
-                        legend.insert(sym.to_string(), (legend.len() as u8, data));
+                    } else {
+                        if name.is_some() {
+                            return Err(words.error(col, LevelErrorKind::DuplicateMetadata("name")));
+                        }
+                        name = Some(md.to_string());
+                        dims = Some((words.number("a width")?, words.number("a height")?));
                     }
-                    State::Map => {
-                        let old_len = grid.len();
-                        grid.extend(line.split_whitespace().map(|sym| legend[sym].0));
-                        assert_eq!(
-                            old_len + dims.unwrap().0 as usize,
-                            grid.len(),
-                            "map line is too short: {line} for map dims {dims:?}"
+                }
+                State::Legend => {
+                    // the property column is optional, so count the columns to see if it's there
+                    let has_property = words.len() == 7;
+                    let (col, sym) = words.next("a tile symbol")?;
+                    if legend.contains_key(sym) {
+                        return Err(
+                            words.error(col, LevelErrorKind::DuplicateSymbol(sym.to_string()))
                         );
                     }
-                    State::Starts => {
-                        let mut chunks = line.split_whitespace();
-                        let etype = chunks
-                            .next()
-                            .expect("Couldn't get entity start type {line}");
-                        let etype = match etype {
-                            "player1" => EntityType::Player1,
-                            "player2" => EntityType::Player2,
-                            "enemy" => EntityType::Enemy,
-                            "door" => {
-                                let to_room = chunks.next().expect("Couldn't get dest room {line}");
-                                let to_x = u16::from_str(
-                                    chunks.next().expect("No dest x coord in door line {line}"),
-                                )
-                                .expect("Couldn't parse x coord as u16 in {line}");
-                                let to_y = u16::from_str(
-                                    chunks.next().expect("No dest y coord in door line {line}"),
-                                )
-                                .expect("Couldn't parse y coord as u16 in {line}");
-                                EntityType::Door(to_room.to_string(), to_x, to_y)
-                            }
-                            _ => panic!("Unrecognized entity type in {line}"),
-                        };
-                        let x =
-                            u16::from_str(chunks.next().expect("No x coord in start line {line}"))
-                                .expect("Couldn't parse x coord as u16 in {line}");
-                        let y =
-                            u16::from_str(chunks.next().expect("No y coord in start line {line}"))
-                                .expect("Couldn't parse y coord as u16 in {line}");
-                        starts.push((
-                            etype,
-                            Vec2 {
-                                x: (x as usize * TILE_SZ) as f32 + TILE_SZ as f32 / 2.0,
-                                y: ((dims.unwrap().1 - y) as usize * TILE_SZ) as f32
-                                    - TILE_SZ as f32 / 2.0,
+                    if packed == Some(true) && sym.chars().count() != 1 {
+                        return Err(words.error(col, LevelErrorKind::LongSymbol(sym.to_string())));
+                    }
+                    if legend.len() > u8::MAX as usize {
+                        return Err(words.error(col, LevelErrorKind::TooManySymbols));
+                    }
+                    let (col, flags) = words.next("tile flags")?;
+                    let flags = flags.to_lowercase();
+                    if flags != "o" && flags != "s" {
+                        return Err(words.error(col, LevelErrorKind::BadFlag(flags)));
+                    }
+                    let property = if has_property {
+                        let (col, property) = words.next("a tile property")?;
+                        let property = property.to_lowercase();
+                        if property != "n" && property != "s" {
+                            return Err(words.error(col, LevelErrorKind::BadProperty(property)));
+                        }
+                        property
+                    } else {
+                        "n".to_string()
+                    };
+                    let x = words.number("a sheet x")?;
+                    let y = words.number("a sheet y")?;
+                    let w = words.number("a sheet w")?;
+                    let h = words.number("a sheet h")?;
+                    let data = TileData {
+                        solid: flags == "s",
+                        sheet_region: SheetRegion::new(0, x, y, 16, w, h),
+                        slippery: property == "s",
+                    };
+                    legend.insert(sym.to_string(), (legend.len() as u8, data));
+                }
+                State::Map => {
+                    let (w, h) = dims.unwrap();
+                    if grid.len() >= w as usize * h as usize {
+                        return Err(words.error(1, LevelErrorKind::ExtraRow));
+                    }
+                    let packed =
+                        packed.unwrap_or_else(|| legend.keys().all(|sym| sym.chars().count() == 1));
+                    let row = if packed { words.chars() } else { words.rest() };
+                    if row.len() < w as usize {
+                        return Err(words.error(
+                            words.end,
+                            LevelErrorKind::ShortRow {
+                                expected: w as usize,
+                                found: row.len(),
                             },
                         ));
+                    } else if row.len() > w as usize {
+                        return Err(words.error(
+                            row[w as usize].0,
+                            LevelErrorKind::LongRow {
+                                expected: w as usize,
+                                found: row.len(),
+                            },
+                        ));
+                    }
+                    for (col, sym) in row {
+                        let (idx, _) = legend.get(sym).ok_or_else(|| {
+                            words.error(col, LevelErrorKind::UnknownSymbol(sym.to_string()))
+                        })?;
+                        grid.push(*idx);
                     }
-                    State::Done => {
-                        panic!("Unexpected file content after parsing finished in {line}")
+                }
+                State::Starts => {
+                    let (kind_col, kind) = words.next("an entity type")?;
+                    let rest = words.rest();
+                    if rest.len() < 2 {
+                        return Err(words.error(words.end, LevelErrorKind::Missing("x y coords")));
                     }
+                    let (args, coords) = rest.split_at(rest.len() - 2);
+                    let args: Vec<&str> = args.iter().map(|(_, arg)| *arg).collect();
+                    let etype = E::from_start(kind, &args).ok_or_else(|| {
+                        words.error(kind_col, LevelErrorKind::UnknownEntity(kind.to_string()))
+                    })?;
+                    let x: u16 = words.parse(coords[0].0, coords[0].1)?;
+                    let y: u16 = words.parse(coords[1].0, coords[1].1)?;
+                    let (w, h) = dims.unwrap();
+                    if x >= w || y >= h {
+                        return Err(words.error(kind_col, LevelErrorKind::StartOutOfBounds(x, y)));
+                    }
+                    let tile_sz = tile_sz.unwrap_or(DEFAULT_TILE_SZ);
+                    starts.push((etype, tile_center(x, y, h, tile_sz)));
+                }
+                State::Done => {
+                    return Err(words.error(1, LevelErrorKind::TrailingContent));
                 }
             }
         }
-        assert_ne!(name, None);
-        let name = name.unwrap();
-        assert_ne!(dims, None);
+        // Whatever sections never got closed off still have to be complete
+        while matches!(state, State::Metadata | State::Legend | State::Map) {
+            finish(state, dims, legend.len(), grid.len(), eof)?;
+            state = state.next();
+        }
         let (w, h) = dims.unwrap();
-        assert!(!legend.is_empty());
-        assert_eq!(grid.len(), w as usize * h as usize);
         let mut tiles: Vec<(u8, TileData)> = legend.into_values().collect();
         tiles.sort_by_key(|(num, _)| *num);
-        Self {
-            bg,
-            name: name.to_string(),
+        Ok(Self {
+            bg: bg.unwrap_or(SheetRegion::ZERO),
+            name: name.unwrap(),
             grid: Grid::new(w as usize, h as usize, grid),
             tileset: Tileset {
                 tiles: tiles.into_iter().map(|(_num, val)| val).collect(),
             },
             starts,
-        }
+            tile_sz: tile_sz.unwrap_or(DEFAULT_TILE_SZ),
+            seed,
+        })
+    }
+    // Each door's tile along with where it leads
+    pub fn doors(&self) -> impl Iterator<Item = (Rect, (&str, u16, u16))> {
+        self.starts.iter().filter_map(|(etype, pos)| {
+            let half = self.tile_sz as f32 / 2.0;
+            etype.door().map(|dest| {
+                (
+                    Rect {
+                        x: pos.x - half,
+                        y: pos.y - half,
+                        w: self.tile_sz as u16,
+                        h: self.tile_sz as u16,
+                    },
+                    dest,
+                )
+            })
+        })
+    }
+    pub fn door_touching(&self, rect: Rect) -> Option<(&str, u16, u16)> {
+        self.doors()
+            .find(|(door, _)| door.overlap(rect).is_some())
+            .map(|(_, dest)| dest)
+    }
+}
+
+// Grid X Y (from the top left, like in the level file) to the world position of that tile's center
+fn tile_center(x: u16, y: u16, h: u16, tile_sz: usize) -> Vec2 {
+    Vec2 {
+        x: (x as usize * tile_sz) as f32 + tile_sz as f32 / 2.0,
+        y: ((h - y) as usize * tile_sz) as f32 - tile_sz as f32 / 2.0,
     }
+}
+
+impl<E: StartKind> FromStr for Level<E> {
+    type Err = LevelError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<E> Level<E> {
     pub fn sprite_count(&self) -> usize {
         self.grid.width() * self.grid.height() + 1
     }
@@ -248,10 +543,10 @@ impl Level {
                 let y = h - y - 1;
                 *trf = Transform {
                     // and multiply by tile sz *and* offset by half tile sz
-                    x: (x * TILE_SZ + TILE_SZ / 2) as f32,
-                    y: (y * TILE_SZ + TILE_SZ / 2) as f32,
-                    w: TILE_SZ as u16,
-                    h: TILE_SZ as u16,
+                    x: (x * self.tile_sz + self.tile_sz / 2) as f32,
+                    y: (y * self.tile_sz + self.tile_sz / 2) as f32,
+                    w: self.tile_sz as u16,
+                    h: self.tile_sz as u16,
                     rot: 0.0,
                 };
                 *uv = self.tileset[*tile as usize].sheet_region;
@@ -259,21 +554,20 @@ impl Level {
         }
         if self.bg.w != 0 {
             trfs[trfs.len() - 1] = Transform {
-                x: (self.grid.width() * TILE_SZ) as f32 / 2.0,
-                y: (self.grid.height() * TILE_SZ) as f32 / 2.0,
-                w: (self.grid.width() as u16 * TILE_SZ as u16),
-                h: (self.grid.height() as u16 * TILE_SZ as u16),
+                x: (self.grid.width() * self.tile_sz) as f32 / 2.0,
+                y: (self.grid.height() * self.tile_sz) as f32 / 2.0,
+                w: (self.grid.width() as u16 * self.tile_sz as u16),
+                h: (self.grid.height() as u16 * self.tile_sz as u16),
                 rot: 0.0,
             };
             uvs[uvs.len() - 1] = self.bg;
         }
         w * h + 1
     }
-    #[allow(dead_code)]
     pub fn name(&self) -> &str {
         &self.name
     }
-    pub fn starts(&self) -> &[(EntityType, Vec2)] {
+    pub fn starts(&self) -> &[(E, Vec2)] {
         &self.starts
     }
     pub fn get_tile_at(&self, pos: Vec2) -> Option<&TileData> {
@@ -286,15 +580,15 @@ impl Level {
     }
     pub fn grid_to_world(&self, pos: grid::Coord) -> Vec2 {
         Vec2 {
-            x: pos.0 as f32 * TILE_SZ as f32,
-            y: (self.grid.height() - pos.1 - 1) as f32 * TILE_SZ as f32,
+            x: pos.0 as f32 * self.tile_sz as f32,
+            y: (self.grid.height() - pos.1 - 1) as f32 * self.tile_sz as f32,
         }
     }
     pub fn world_to_grid(&self, pos: Vec2) -> grid::Coord {
         (
-            (pos.x / TILE_SZ as f32) as usize,
-            (((self.grid.height() as f32 * TILE_SZ as f32) - pos.y - 1.0) / TILE_SZ as f32)
-                as usize,
+            (pos.x / self.tile_sz as f32) as usize,
+            (((self.grid.height() as f32 * self.tile_sz as f32) - pos.y - 1.0)
+                / self.tile_sz as f32) as usize,
         )
     }
     pub fn tiles_within(&self, rect: Rect) -> impl Iterator<Item = (Rect, &TileData)> {
@@ -314,8 +608,8 @@ impl Level {
                         Rect {
                             x: world.x,
                             y: world.y,
-                            w: TILE_SZ as u16,
-                            h: TILE_SZ as u16,
+                            w: self.tile_sz as u16,
+                            h: self.tile_sz as u16,
                         },
                         &self.tileset[*tile_dat as usize],
                     )
@@ -323,12 +617,164 @@ impl Level {
             })
         })
     }
+    // Where a start at grid X Y (e.g. a door's destination) would be placed
+    pub fn grid_center(&self, x: u16, y: u16) -> Vec2 {
+        tile_center(x, y, self.grid.height() as u16, self.tile_sz)
+    }
+    pub fn tile_sz(&self) -> usize {
+        self.tile_sz
+    }
+    // The random seed the level asks for, if any
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
     pub fn width(&self) -> usize {
         self.grid.width()
     }
     pub fn height(&self) -> usize {
         self.grid.height()
     }
+    // Walks the grid one tile at a time (DDA) from `origin` along `dir`, which needn't
+    // be unit length, and returns the first tile `filter` accepts within `max_dist`.
+    // The tile the ray starts in doesn't count, so a ray starting on a wall's face
+    // (like a bounced one) heads off freely, and rays stop where they leave the map.
+    // One that starts off the map is walked from where it comes onto it.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        filter: impl Fn(&TileData) -> bool,
+    ) -> Option<RayHit> {
+        let len = dir.mag_sq().sqrt();
+        if len == 0.0 {
+            return None;
+        }
+        let dir = dir * (1.0 / len);
+        let ts = self.tile_sz as f32;
+        let (o, d) = ([origin.x, origin.y], [dir.x, dir.y]);
+        let size = [self.width() as f32, self.height() as f32];
+        // a ray from off the map starts where it crosses onto it, and the tile there
+        // counts (entered through the face on axis `enter_i`)
+        let (mut enter, mut enter_i, mut leave) = (0.0, None, f32::INFINITY);
+        for i in 0..2 {
+            let (lo, hi) = (0.0, size[i] * ts);
+            if d[i] == 0.0 {
+                if o[i] < lo || o[i] >= hi {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((lo - o[i]) / d[i], (hi - o[i]) / d[i]);
+            let (near, far) = (t0.min(t1), t0.max(t1));
+            if near > enter {
+                (enter, enter_i) = (near, Some(i));
+            }
+            leave = leave.min(far);
+        }
+        if enter >= leave || enter > max_dist {
+            return None;
+        }
+        let start = [o[0] + d[0] * enter, o[1] + d[1] * enter];
+        // which tile we're in, counting rows up from the bottom like world y does
+        // (kept on the map in case rounding put the entry point just off it)
+        let mut cell = [0, 1].map(|i| (start[i] / ts).floor());
+        if enter_i.is_some() {
+            cell = [0, 1].map(|i| cell[i].clamp(0.0, size[i] - 1.0));
+        }
+        let mut step = [0.0; 2];
+        // how far along the ray the next column/row boundary is, and the distance between them
+        let mut next = [f32::INFINITY; 2];
+        let mut gap = [f32::INFINITY; 2];
+        for i in 0..2 {
+            if d[i] != 0.0 {
+                step[i] = d[i].signum();
+                let edge = if d[i] > 0.0 { cell[i] + 1.0 } else { cell[i] };
+                next[i] = enter + (edge * ts - start[i]) / d[i];
+                gap[i] = ts / d[i].abs();
+            }
+        }
+        let hit = |cell: [f32; 2], i: usize, dist: f32| {
+            let tile = (cell[0] as usize, self.height() - 1 - cell[1] as usize);
+            let tile_data = &self.tileset[*self.grid.get(tile.0, tile.1).unwrap() as usize];
+            if !filter(tile_data) {
+                return None;
+            }
+            let mut normal = [0.0; 2];
+            normal[i] = -step[i];
+            Some(RayHit {
+                tile,
+                point: origin + dir * dist,
+                normal: Vec2 {
+                    x: normal[0],
+                    y: normal[1],
+                },
+                dist,
+            })
+        };
+        if let Some(i) = enter_i {
+            if let Some(found) = hit(cell, i, enter) {
+                return Some(found);
+            }
+        }
+        loop {
+            let i = if next[0] < next[1] { 0 } else { 1 };
+            let dist = next[i];
+            if dist > max_dist {
+                return None;
+            }
+            cell[i] += step[i];
+            next[i] += gap[i];
+            if (0..2).any(|i| cell[i] < 0.0 || cell[i] >= size[i]) {
+                return None;
+            }
+            if let Some(found) = hit(cell, i, dist) {
+                return Some(found);
+            }
+        }
+    }
+    // The path a bullet fired from `origin` along `dir` would take: it reflects off the
+    // first `bounces` walls it meets the same way projectiles do, and ends at the next
+    // wall after that or once it's gone `max_dist` in all.  The points start with
+    // `origin`, so consecutive pairs are the segments to draw.
+    pub fn raycast_bounces(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        bounces: usize,
+        filter: impl Fn(&TileData) -> bool,
+    ) -> Vec<Vec2> {
+        let mut path = vec![origin];
+        let (mut pos, mut dir, mut left) = (origin, dir, max_dist);
+        for bounce in 0..=bounces {
+            let Some(hit) = self.raycast(pos, dir, left, &filter) else {
+                let len = dir.mag_sq().sqrt();
+                if len > 0.0 {
+                    path.push(pos + dir * (left / len));
+                }
+                break;
+            };
+            path.push(hit.point);
+            if bounce == bounces {
+                break;
+            }
+            dir = dir - hit.normal * (2.0 * dir.dot(hit.normal));
+            pos = hit.point;
+            left -= hit.dist;
+        }
+        path
+    }
+}
+
+// Where a ray met a tile: its grid coordinate, the exact spot, the face's outward
+// normal and how far along the ray it was
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RayHit {
+    pub tile: grid::Coord,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub dist: f32,
 }
 
 #[derive(Debug)]
@@ -341,3 +787,371 @@ impl std::ops::Index<usize> for Tileset {
         &self.tiles[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Player,
+        Door(String, u16, u16),
+    }
+    impl StartKind for Kind {
+        fn from_start(kind: &str, args: &[&str]) -> Option<Self> {
+            match (kind, args) {
+                ("player", []) => Some(Kind::Player),
+                ("door", [to, x, y]) => {
+                    Some(Kind::Door(to.to_string(), x.parse().ok()?, y.parse().ok()?))
+                }
+                _ => None,
+            }
+        }
+        fn door(&self) -> Option<(&str, u16, u16)> {
+            match self {
+                Kind::Door(to, x, y) => Some((to, *x, *y)),
+                _ => None,
+            }
+        }
+    }
+
+    const GOOD: &str = "test 3 2
+bg 1 2 3 4
+====
+. o 0 0 16 16
+# s s 16 0 16 16
+====
+# . #
+. . .
+====
+player 1 0
+door other 0 1 2 1
+";
+
+    fn err(s: &str) -> (usize, usize, LevelErrorKind) {
+        let e = Level::<Kind>::parse(s)
+            .err()
+            .expect("level should not parse");
+        (e.line, e.col, e.kind)
+    }
+
+    #[test]
+    fn test_parse_level() {
+        let level = Level::<Kind>::parse(GOOD).unwrap();
+        assert_eq!(level.name(), "test");
+        assert_eq!((level.width(), level.height()), (3, 2));
+        assert_eq!(level.tile_sz(), DEFAULT_TILE_SZ);
+        assert_eq!(level.seed(), None);
+        let seeded =
+            Level::<Kind>::parse(&GOOD.replace("bg 1 2 3 4", "bg 1 2 3 4\nseed 42")).unwrap();
+        assert_eq!(seeded.seed(), Some(42));
+        assert_eq!(level.starts()[0].0, Kind::Player);
+        assert_eq!(level.starts()[1].0, Kind::Door("other".to_string(), 0, 1));
+        // player 1 0 is the center of the top middle tile
+        assert_eq!(level.starts()[0].1, Vec2 { x: 24.0, y: 24.0 });
+        assert!(
+            level
+                .get_tile_at(Vec2 { x: 8.0, y: 24.0 })
+                .unwrap()
+                .slippery
+        );
+        assert!(!level.get_tile_at(Vec2 { x: 8.0, y: 8.0 }).unwrap().solid);
+        assert!(GOOD.parse::<Level<Kind>>().is_ok());
+    }
+
+    #[test]
+    fn test_metadata_errors() {
+        assert_eq!(
+            err(&GOOD.replace("bg 1 2 3 4", "seed -1")),
+            (2, 6, LevelErrorKind::BadNumber("-1".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("test 3 2", "test 3 two")),
+            (1, 8, LevelErrorKind::BadNumber("two".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("test 3 2", "test 3")),
+            (1, 7, LevelErrorKind::Missing("a height"))
+        );
+        assert_eq!(
+            err(&GOOD.replace("bg 1 2 3 4", "bg 1 2 3 4\ntest 3 2")),
+            (3, 1, LevelErrorKind::DuplicateMetadata("name"))
+        );
+        assert_eq!(
+            err(&GOOD.replace("test 3 2\n", "")),
+            (2, 1, LevelErrorKind::MissingName)
+        );
+        assert_eq!(err("test 3 2\n"), (2, 1, LevelErrorKind::EmptyLegend));
+        assert_eq!(
+            err(&GOOD.replace("bg 1 2 3 4", "tile 0")),
+            (2, 6, LevelErrorKind::ZeroTileSize)
+        );
+    }
+
+    #[test]
+    fn test_legend_errors() {
+        assert_eq!(
+            err(&GOOD.replace("# s s", ". s s")),
+            (5, 1, LevelErrorKind::DuplicateSymbol(".".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("# s s", "# x s")),
+            (5, 3, LevelErrorKind::BadFlag("x".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("# s s", "# s q")),
+            (5, 5, LevelErrorKind::BadProperty("q".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_map_errors() {
+        assert_eq!(
+            err(&GOOD.replace("# . #", "# ? #")),
+            (7, 3, LevelErrorKind::UnknownSymbol("?".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("# . #", "# .")),
+            (
+                7,
+                4,
+                LevelErrorKind::ShortRow {
+                    expected: 3,
+                    found: 2
+                }
+            )
+        );
+        assert_eq!(
+            err(&GOOD.replace("# . #", "# . # #")),
+            (
+                7,
+                7,
+                LevelErrorKind::LongRow {
+                    expected: 3,
+                    found: 4
+                }
+            )
+        );
+        assert_eq!(
+            err(&GOOD.replace(". . .\n", "")),
+            (
+                8,
+                1,
+                LevelErrorKind::MissingRows {
+                    expected: 2,
+                    found: 1
+                }
+            )
+        );
+        assert_eq!(
+            err(&GOOD.replace(". . .\n", ". . .\n. . .\n")),
+            (9, 1, LevelErrorKind::ExtraRow)
+        );
+    }
+
+    #[test]
+    fn test_packed_rows() {
+        let packed = GOOD.replace("# . #", "#.#").replace(". . .", "...");
+        let level = Level::<Kind>::parse(&packed).unwrap();
+        assert!(
+            level
+                .get_tile_at(Vec2 { x: 8.0, y: 24.0 })
+                .unwrap()
+                .slippery
+        );
+        // both styles are fine when the mode is auto-detected
+        let mixed = GOOD.replace("# . #", "#.#");
+        assert!(Level::<Kind>::parse(&mixed).is_ok());
+        assert_eq!(
+            err(&mixed.replace("test 3 2", "test 3 2\nrows spaced")),
+            (
+                8,
+                4,
+                LevelErrorKind::ShortRow {
+                    expected: 3,
+                    found: 1
+                }
+            )
+        );
+        assert_eq!(
+            err(&packed.replace("#.#", "#.##")),
+            (
+                7,
+                4,
+                LevelErrorKind::LongRow {
+                    expected: 3,
+                    found: 4
+                }
+            )
+        );
+        assert_eq!(
+            err(&packed
+                .replace("test 3 2", "test 3 2\nrows packed")
+                .replace("# s s", "## s s")),
+            (6, 1, LevelErrorKind::LongSymbol("##".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("test 3 2", "test 3 2\nrows tight")),
+            (2, 6, LevelErrorKind::BadRowMode("tight".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_content_levels() {
+        struct Any;
+        impl StartKind for Any {
+            fn from_start(_kind: &str, _args: &[&str]) -> Option<Self> {
+                Some(Any)
+            }
+        }
+        for text in [
+            include_str!("../content/level1.txt"),
+            include_str!("../content/level3.txt"),
+            include_str!("../content/level4.txt"),
+            include_str!("../content/level5.txt"),
+            include_str!("../content/level6.txt"),
+        ] {
+            if let Err(e) = Level::<Any>::parse(text) {
+                panic!("{e}");
+            }
+        }
+        let maze = Level::<Any>::parse(include_str!("../content/level5.txt")).unwrap();
+        assert_eq!((maze.width(), maze.height()), (49, 19));
+    }
+
+    #[test]
+    fn test_start_errors() {
+        assert_eq!(
+            err(&GOOD.replace("player 1 0", "dragon 1 0")),
+            (10, 1, LevelErrorKind::UnknownEntity("dragon".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("player 1 0", "player 1 x")),
+            (10, 10, LevelErrorKind::BadNumber("x".to_string()))
+        );
+        assert_eq!(
+            err(&GOOD.replace("player 1 0", "player 1 2")),
+            (10, 1, LevelErrorKind::StartOutOfBounds(1, 2))
+        );
+        assert_eq!(
+            err(&format!("{GOOD}====\nplayer 0 0\n")),
+            (13, 1, LevelErrorKind::TrailingContent)
+        );
+        let e = Level::<Kind>::parse(&GOOD.replace("player 1 0", "dragon 1 0")).err();
+        assert_eq!(
+            e.unwrap().to_string(),
+            "line 10, column 1: unrecognized entity type or arguments for \"dragon\""
+        );
+    }
+
+    #[test]
+    fn test_doors() {
+        let level = Level::<Kind>::parse(GOOD).unwrap();
+        let near = |x, y| Rect { x, y, w: 4, h: 4 };
+        // the door is on the bottom right tile and leads to the bottom left of "other"
+        assert_eq!(level.door_touching(near(38.0, 6.0)), Some(("other", 0, 1)));
+        assert_eq!(level.door_touching(near(2.0, 22.0)), None);
+        assert_eq!(level.doors().count(), 1);
+        assert_eq!(level.grid_center(0, 1), Vec2 { x: 8.0, y: 8.0 });
+        assert_eq!(level.grid_center(1, 0), level.starts()[0].1);
+    }
+
+    const ROOM: &str = "room 6 4
+====
+. o 0 0 16 16
+# s 16 0 16 16
+====
+######
+#..#.#
+#....#
+######
+";
+
+    #[test]
+    fn test_raycast() {
+        let level = Level::<Kind>::parse(ROOM).unwrap();
+        let solid = |tile: &TileData| tile.solid;
+        // from the middle of grid (1, 2) straight right, into the right wall at x = 80
+        let start = Vec2 { x: 24.0, y: 24.0 };
+        let hit = level
+            .raycast(start, Vec2 { x: 2.0, y: 0.0 }, 100.0, solid)
+            .unwrap();
+        assert_eq!(hit.tile, (5, 2));
+        assert_eq!(hit.point, Vec2 { x: 80.0, y: 24.0 });
+        assert_eq!(hit.normal, Vec2 { x: -1.0, y: 0.0 });
+        assert_eq!(hit.dist, 56.0);
+        assert_eq!(
+            level.raycast(start, Vec2 { x: 1.0, y: 0.0 }, 50.0, solid),
+            None
+        );
+        // diagonally it slips between tile corners and meets the top wall's bottom face
+        let hit = level
+            .raycast(start, Vec2 { x: 1.0, y: 1.0 }, 100.0, solid)
+            .unwrap();
+        assert_eq!(hit.tile, (2, 0));
+        assert_eq!(hit.normal, Vec2 { x: 0.0, y: -1.0 });
+        assert!((hit.dist - 24.0 * 2f32.sqrt()).abs() < 1e-4);
+        // rays that leave the map, or that nothing stops, find nothing
+        let outside = Vec2 { x: -40.0, y: 24.0 };
+        assert_eq!(
+            level.raycast(outside, Vec2 { x: -1.0, y: 0.0 }, 100.0, solid),
+            None
+        );
+        // but one from off the map that points onto it meets the first tile in
+        let hit = level
+            .raycast(outside, Vec2 { x: 1.0, y: 0.0 }, 100.0, solid)
+            .unwrap();
+        assert_eq!(hit.tile, (0, 2));
+        assert_eq!(hit.point, Vec2 { x: 0.0, y: 24.0 });
+        assert_eq!(hit.normal, Vec2 { x: -1.0, y: 0.0 });
+        assert_eq!(hit.dist, 40.0);
+        // and walks on from there when that tile isn't one it's after
+        let floor = |tile: &TileData| !tile.solid;
+        let hit = level
+            .raycast(outside, Vec2 { x: 1.0, y: 0.0 }, 100.0, floor)
+            .unwrap();
+        assert_eq!(hit.tile, (1, 2));
+        assert_eq!(hit.dist, 56.0);
+        // coming down from above the map onto the top wall
+        let above = Vec2 { x: 40.0, y: 100.0 };
+        let hit = level
+            .raycast(above, Vec2 { x: 0.0, y: -1.0 }, 100.0, solid)
+            .unwrap();
+        assert_eq!(hit.tile, (2, 0));
+        assert_eq!(hit.normal, Vec2 { x: 0.0, y: 1.0 });
+        assert_eq!(hit.dist, 36.0);
+        // it still has to get there within `max_dist`, and has to cross the map at all
+        assert_eq!(
+            level.raycast(outside, Vec2 { x: 1.0, y: 0.0 }, 30.0, solid),
+            None
+        );
+        assert_eq!(
+            level.raycast(above, Vec2 { x: 1.0, y: 0.0 }, 100.0, solid),
+            None
+        );
+        assert_eq!(
+            level.raycast(start, Vec2 { x: 1.0, y: 0.0 }, 100.0, |_| false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_raycast_bounces() {
+        let level = Level::<Kind>::parse(ROOM).unwrap();
+        let solid = |tile: &TileData| tile.solid;
+        let start = Vec2 { x: 24.0, y: 24.0 };
+        let path = level.raycast_bounces(start, Vec2 { x: 1.0, y: 0.0 }, 200.0, 1, solid);
+        // off the right wall and back to the left one, where it stops
+        assert_eq!(
+            path,
+            vec![start, Vec2 { x: 80.0, y: 24.0 }, Vec2 { x: 16.0, y: 24.0 }]
+        );
+        // running out of distance ends it in the open
+        let path = level.raycast_bounces(start, Vec2 { x: 1.0, y: 0.0 }, 60.0, 1, solid);
+        assert_eq!(path[2], Vec2 { x: 76.0, y: 24.0 });
+        // a diagonal shot comes off the floor heading up
+        let path = level.raycast_bounces(start, Vec2 { x: 1.0, y: -1.0 }, 200.0, 2, solid);
+        assert_eq!(path[1], Vec2 { x: 32.0, y: 16.0 });
+        assert!(path[2].x > path[1].x && path[2].y > path[1].y);
+    }
+}
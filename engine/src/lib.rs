@@ -0,0 +1,23 @@
+pub mod app;
+pub mod arena;
+pub mod assets;
+pub mod camera;
+pub mod collision;
+pub mod controls;
+pub mod ecs;
+pub mod font;
+pub mod gamepad;
+pub mod geom;
+pub mod grid;
+pub mod hud;
+pub mod input;
+pub mod layers;
+pub mod level;
+pub mod registry;
+pub mod replay;
+pub mod sheets;
+pub mod spatial;
+pub mod split;
+pub mod stepper;
+
+pub use app::{run, App, Args, DT};
@@ -0,0 +1,401 @@
+use crate::assets::AssetWatch;
+use crate::geom::*;
+use crate::level::{Level, LevelError, StartKind};
+use assets_manager::AssetCache;
+use std::collections::HashMap;
+
+// All the levels a game can reach, looked up by `Level::name()` (which is what doors
+// refer to).  `S` is whatever the game wants to remember about a level while the
+// players are somewhere else, like which enemies are already dead.
+pub struct LevelRegistry<E, S> {
+    levels: Vec<Level<E>>,
+    by_name: HashMap<String, usize>,
+    saved: Vec<Option<S>>,
+    // each level's file, by its name
+    watches: Vec<AssetWatch>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Asset(String, assets_manager::Error),
+    Parse(String, LevelError),
+    // the file's first line names a different level than the one a door asked for
+    Misnamed {
+        id: String,
+        name: String,
+    },
+    // a door's destination is off the map of the level it leads to
+    BadDoor {
+        from: String,
+        to: String,
+        x: u16,
+        y: u16,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Asset(id, err) => write!(f, "couldn't access {id}.txt: {err}"),
+            LoadError::Parse(id, err) => write!(f, "couldn't parse {id}.txt: {err}"),
+            LoadError::Misnamed { id, name } => {
+                write!(f, "{id}.txt is named {name:?}, but doors expect {id:?}")
+            }
+            LoadError::BadDoor { from, to, x, y } => {
+                write!(f, "door in {from} leads to {x} {y}, which is outside {to}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl<E, S> Default for LevelRegistry<E, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, S> LevelRegistry<E, S> {
+    pub fn new() -> Self {
+        Self {
+            levels: Vec::new(),
+            by_name: HashMap::new(),
+            saved: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+    // Adding a level with a name that's already here replaces it in place (keeping its index and saved state)
+    pub fn add(&mut self, level: Level<E>) -> usize {
+        if let Some(&idx) = self.by_name.get(level.name()) {
+            self.levels[idx] = level;
+            return idx;
+        }
+        let idx = self.levels.len();
+        self.by_name.insert(level.name().to_string(), idx);
+        self.watches.push(AssetWatch::new(level.name()));
+        self.levels.push(level);
+        self.saved.push(None);
+        idx
+    }
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.by_name.get(name).copied()
+    }
+    pub fn get(&self, idx: usize) -> &Level<E> {
+        &self.levels[idx]
+    }
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+    // Remember `state` for when the players come back to level `idx`
+    pub fn save(&mut self, idx: usize, state: S) {
+        self.saved[idx] = Some(state);
+    }
+    pub fn take_saved(&mut self, idx: usize) -> Option<S> {
+        self.saved[idx].take()
+    }
+    // Forget what was saved for every level, so they all start over from their starts
+    pub fn clear_saved(&mut self) {
+        self.saved.fill_with(|| None);
+    }
+}
+
+impl<E: StartKind, S> LevelRegistry<E, S> {
+    // Loads the level with asset id `first` (it gets index 0) along with every level
+    // reachable from it through doors.  Each level is loaded from the asset with the
+    // same id as its name.
+    pub fn load(cache: &AssetCache, first: &str) -> Result<Self, LoadError> {
+        let mut registry = Self::new();
+        registry.load_more(cache, first)?;
+        Ok(registry)
+    }
+    // Adds the level with asset id `id` and everything reachable from it, like `load`,
+    // to a registry that already has some levels (ones it has are left alone).
+    // Returns `id`'s index.
+    pub fn load_more(&mut self, cache: &AssetCache, id: &str) -> Result<usize, LoadError> {
+        let mut todo = vec![id.to_string()];
+        while let Some(id) = todo.pop() {
+            if self.index_of(&id).is_some() {
+                continue;
+            }
+            let handle = cache
+                .load::<String>(&id)
+                .map_err(|err| LoadError::Asset(id.clone(), err))?;
+            let level =
+                Level::parse(&handle.read()).map_err(|err| LoadError::Parse(id.clone(), err))?;
+            if level.name() != id {
+                return Err(LoadError::Misnamed {
+                    id,
+                    name: level.name().to_string(),
+                });
+            }
+            todo.extend(level.doors().map(|(_, (to, _, _))| to.to_string()));
+            self.add(level);
+        }
+        for level in self.levels.iter() {
+            for (_, (to, x, y)) in level.doors() {
+                let dest = self.get(self.index_of(to).unwrap());
+                if x as usize >= dest.width() || y as usize >= dest.height() {
+                    return Err(LoadError::BadDoor {
+                        from: level.name().to_string(),
+                        to: to.to_string(),
+                        x,
+                        y,
+                    });
+                }
+            }
+        }
+        Ok(self.index_of(id).unwrap())
+    }
+    // Re-parses every level whose file has been hot-reloaded since we last looked.  A
+    // level whose new text doesn't parse stays as it was, and the error comes back instead.
+    pub fn reload_changed(&mut self, cache: &AssetCache) -> Vec<LoadError> {
+        let mut changed = Vec::new();
+        for (idx, watch) in self.watches.iter_mut().enumerate() {
+            if !watch.changed::<String>(cache) {
+                continue;
+            }
+            if let Some(handle) = cache.get_cached::<String>(watch.id()) {
+                changed.push((idx, handle.read().to_string()));
+            }
+        }
+        changed
+            .into_iter()
+            .filter_map(|(idx, text)| self.reload(idx, &text).err())
+            .collect()
+    }
+    // Swaps level `idx` for what `text` parses to, as if its file had just been edited
+    // to say that.  Text that doesn't parse, or names a different level, leaves it as
+    // it was.
+    pub fn reload(&mut self, idx: usize, text: &str) -> Result<(), LoadError> {
+        let id = self.watches[idx].id();
+        match text.parse::<Level<E>>() {
+            Ok(new) if new.name() == id => {
+                self.levels[idx] = new;
+                Ok(())
+            }
+            Ok(new) => Err(LoadError::Misnamed {
+                id: id.to_string(),
+                name: new.name().to_string(),
+            }),
+            Err(err) => Err(LoadError::Parse(id.to_string(), err)),
+        }
+    }
+    // If `rect` is touching a door in level `from`, the index of the level it leads to
+    // and the spot to put the player there.  Doors to levels that aren't in the
+    // registry don't go anywhere.
+    pub fn door_dest(&self, from: usize, rect: Rect) -> Option<(usize, Vec2)> {
+        let (to, x, y) = self.levels[from].door_touching(rect)?;
+        let to = self.index_of(to)?;
+        Some((to, self.levels[to].grid_center(x, y)))
+    }
+    // Notes which players (by their `rects`) are standing on a door in level `at`,
+    // for `door_taken`.  Players who arrive on a door have to step off it before it
+    // works.
+    pub fn arrive(&self, at: usize, rects: &[Rect], on_door: &mut [bool]) {
+        for (rect, on_door) in rects.iter().zip(on_door) {
+            *on_door = self.levels[at].door_touching(*rect).is_some();
+        }
+    }
+    // The door in level `at` that takes the players along this step, if any: the
+    // first one that a player who can use doors (the `bool` beside their rect) has
+    // just walked onto.  `on_door` says who was already on one last step, and is
+    // updated for the next one.
+    pub fn door_taken(
+        &self,
+        at: usize,
+        players: &[(Rect, bool)],
+        on_door: &mut [bool],
+    ) -> Option<(usize, Vec2)> {
+        let mut door = None;
+        for ((rect, can_use), on_door) in players.iter().zip(on_door) {
+            let dest = self.door_dest(at, *rect);
+            if dest.is_some() && !*on_door && *can_use {
+                door = door.or(dest);
+            }
+            *on_door = dest.is_some();
+        }
+        door
+    }
+}
+
+impl<E, S> std::ops::Index<usize> for LevelRegistry<E, S> {
+    type Output = Level<E>;
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Kind {
+        // players, enemies, etc.
+        Other,
+        Door(String, u16, u16),
+    }
+    impl StartKind for Kind {
+        fn from_start(kind: &str, args: &[&str]) -> Option<Self> {
+            match (kind, args) {
+                ("door", [to, x, y]) => {
+                    Some(Kind::Door(to.to_string(), x.parse().ok()?, y.parse().ok()?))
+                }
+                (_, []) => Some(Kind::Other),
+                _ => None,
+            }
+        }
+        fn door(&self) -> Option<(&str, u16, u16)> {
+            match self {
+                Kind::Door(to, x, y) => Some((to, *x, *y)),
+                Kind::Other => None,
+            }
+        }
+    }
+
+    fn level(name: &str, door_to: &str) -> Level<Kind> {
+        Level::parse(&format!(
+            "{name} 3 1\n====\n. o 0 0 16 16\n====\n...\n====\nplayer 0 0\ndoor {door_to} 1 0 2 0\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_doors_between_levels() {
+        let mut registry: LevelRegistry<Kind, Vec<u8>> = LevelRegistry::new();
+        assert_eq!(registry.add(level("a", "b")), 0);
+        assert_eq!(registry.add(level("b", "a")), 1);
+        assert_eq!(registry.index_of("b"), Some(1));
+        assert_eq!(registry.index_of("c"), None);
+        let on_door = Rect {
+            x: 36.0,
+            y: 4.0,
+            w: 8,
+            h: 8,
+        };
+        // the door is on the right tile and leads to the middle tile of the other level
+        assert_eq!(
+            registry.door_dest(0, on_door),
+            Some((1, Vec2 { x: 24.0, y: 8.0 }))
+        );
+        assert_eq!(registry.door_dest(0, Rect { x: 0.0, ..on_door }), None);
+        // doors to levels we don't have are inert
+        registry.add(level("b", "nowhere"));
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.door_dest(1, on_door), None);
+    }
+
+    #[test]
+    fn test_door_taken() {
+        let mut registry: LevelRegistry<Kind, ()> = LevelRegistry::new();
+        registry.add(level("a", "b"));
+        registry.add(level("b", "a"));
+        let on = Rect {
+            x: 36.0,
+            y: 4.0,
+            w: 8,
+            h: 8,
+        };
+        let off = Rect { x: 0.0, ..on };
+        let there = Some((1, Vec2 { x: 24.0, y: 8.0 }));
+        // arriving on the door doesn't take anyone anywhere until they step off it
+        let mut on_door = [false; 2];
+        registry.arrive(0, &[on, off], &mut on_door);
+        assert_eq!(on_door, [true, false]);
+        assert_eq!(
+            registry.door_taken(0, &[(on, true), (off, true)], &mut on_door),
+            None
+        );
+        assert_eq!(
+            registry.door_taken(0, &[(off, true), (off, true)], &mut on_door),
+            None
+        );
+        assert_eq!(on_door, [false, false]);
+        assert_eq!(
+            registry.door_taken(0, &[(on, true), (off, true)], &mut on_door),
+            there
+        );
+        // someone who can't use doors (say, they're dead) doesn't open them, but
+        // still has to step off before they would
+        let mut on_door = [false; 2];
+        assert_eq!(
+            registry.door_taken(0, &[(off, true), (on, false)], &mut on_door),
+            None
+        );
+        assert_eq!(on_door, [false, true]);
+        assert_eq!(
+            registry.door_taken(0, &[(off, true), (on, true)], &mut on_door),
+            None
+        );
+    }
+
+    #[test]
+    fn test_saved_state() {
+        let mut registry: LevelRegistry<Kind, Vec<u8>> = LevelRegistry::new();
+        registry.add(level("a", "b"));
+        assert_eq!(registry.take_saved(0), None);
+        registry.save(0, vec![1, 2]);
+        // replacing the level keeps what we saved for it
+        registry.add(level("a", "c"));
+        assert_eq!(registry.take_saved(0), Some(vec![1, 2]));
+        assert_eq!(registry.take_saved(0), None);
+        registry.add(level("b", "a"));
+        registry.save(0, vec![3]);
+        registry.save(1, vec![4]);
+        registry.clear_saved();
+        assert_eq!(registry.take_saved(0), None);
+        assert_eq!(registry.take_saved(1), None);
+    }
+
+    #[test]
+    fn test_load_follows_doors() {
+        let cache = AssetCache::new(concat!(env!("CARGO_MANIFEST_DIR"), "/content")).unwrap();
+        let mut registry: LevelRegistry<Kind, ()> = LevelRegistry::load(&cache, "level1").unwrap();
+        assert_eq!(registry[0].name(), "level1");
+        assert!(registry.index_of("level6").is_some());
+        // more levels go on the end, and ones it already has keep their place
+        assert_eq!(registry.load_more(&cache, "level3").unwrap(), 2);
+        assert_eq!(registry.load_more(&cache, "level6").unwrap(), 1);
+        assert!(matches!(
+            LevelRegistry::<Kind, ()>::load(&cache, "nonexistent"),
+            Err(LoadError::Asset(..))
+        ));
+    }
+
+    #[test]
+    fn test_reload() {
+        let mut registry: LevelRegistry<Kind, ()> = LevelRegistry::new();
+        registry.add(level("a", "b"));
+        let edited = "a 4 1\n====\n. o 0 0 16 16\n====\n....\n";
+        registry.reload(0, edited).unwrap();
+        assert_eq!(registry[0].width(), 4);
+        // a broken edit keeps the level we already had
+        let broken = "a 5 1\n====\n. o 0 0 16 16\n====\n..\n";
+        assert!(matches!(
+            registry.reload(0, broken),
+            Err(LoadError::Parse(..))
+        ));
+        assert_eq!(registry[0].width(), 4);
+        // and so does one that renames it
+        let renamed = "z 3 1\n====\n. o 0 0 16 16\n====\n...\n";
+        assert!(matches!(
+            registry.reload(0, renamed),
+            Err(LoadError::Misnamed { .. })
+        ));
+        assert_eq!(registry[0].name(), "a");
+        assert_eq!(registry.index_of("z"), None);
+    }
+
+    #[test]
+    fn test_reload_changed() {
+        // nothing's been edited since loading, so there's nothing to reload
+        let cache = AssetCache::new(concat!(env!("CARGO_MANIFEST_DIR"), "/content")).unwrap();
+        let mut registry: LevelRegistry<Kind, ()> = LevelRegistry::load(&cache, "level1").unwrap();
+        let width = registry[0].width();
+        assert!(registry.reload_changed(&cache).is_empty());
+        assert_eq!(registry[0].width(), width);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::input::{InputState, Key};
+
+// The keys a game's `World::step` looked at on every tick, plus the level and seed it
+// started from, so the same run can be played back exactly.  Only the game's bound
+// keys are kept (as a bitmask over `keys`), since nothing else can change the world.
+//
+// The file format is plain text, run-length encoded:
+//   replay LEVELNAME SEED
+//   TICKS KEY KEY ...    (this many ticks in a row with exactly these keys down)
+// e.g. `240` is four idle seconds and `12 KeyW Space` is 12 ticks of W and space held.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    pub level: String,
+    pub seed: u64,
+    keys: Vec<Key>,
+    ticks: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    // a line number (from 1) and what was wrong with it
+    Parse(usize, String),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{err}"),
+            ReplayError::Parse(line, msg) => write!(f, "line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Recording {
+    // `keys` are all the keys the game reads (at most 32)
+    pub fn new(level: &str, seed: u64, keys: &[Key]) -> Self {
+        assert!(keys.len() <= 32, "can only record up to 32 keys");
+        Self {
+            level: level.to_string(),
+            seed,
+            keys: keys.to_vec(),
+            ticks: Vec::new(),
+        }
+    }
+    // Call with the input each tick is simulated with
+    pub fn record(&mut self, input: &InputState) {
+        let mask = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| input.is_key_down(**key))
+            .fold(0, |mask, (i, _)| mask | 1 << i);
+        self.ticks.push(mask);
+    }
+    // how many ticks are recorded
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+    pub fn playback(self) -> Playback {
+        Playback {
+            recording: self,
+            next: 0,
+            input: InputState::default(),
+        }
+    }
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+    pub fn load(path: impl AsRef<std::path::Path>, keys: &[Key]) -> Result<Self, ReplayError> {
+        Self::parse(&std::fs::read_to_string(path)?, keys)
+    }
+    // Key names are matched against `keys`, so a replay only loads into a game that
+    // binds every key it uses
+    pub fn parse(text: &str, keys: &[Key]) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| ReplayError::Parse(1, "empty replay".to_string()))?;
+        let (level, seed) = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            ["replay", level, seed] => (
+                level,
+                seed.parse()
+                    .map_err(|_| ReplayError::Parse(1, format!("bad seed {seed:?}")))?,
+            ),
+            _ => {
+                return Err(ReplayError::Parse(
+                    1,
+                    "expected `replay LEVELNAME SEED`".to_string(),
+                ))
+            }
+        };
+        let mut recording = Self::new(level, seed, keys);
+        for (line_no, line) in lines {
+            let mut words = line.split_whitespace();
+            let Some(count) = words.next() else {
+                continue;
+            };
+            let count: usize = count
+                .parse()
+                .map_err(|_| ReplayError::Parse(line_no, format!("bad tick count {count:?}")))?;
+            let mut mask = 0;
+            for name in words {
+                let i = keys
+                    .iter()
+                    .position(|key| format!("{key:?}") == name)
+                    .ok_or_else(|| ReplayError::Parse(line_no, format!("unbound key {name}")))?;
+                mask |= 1 << i;
+            }
+            recording.ticks.extend(std::iter::repeat_n(mask, count));
+        }
+        Ok(recording)
+    }
+}
+
+impl std::fmt::Display for Recording {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "replay {} {}", self.level, self.seed)?;
+        let mut ticks = self.ticks.iter().peekable();
+        while let Some(&mask) = ticks.next() {
+            let mut count = 1;
+            while ticks.next_if_eq(&&mask).is_some() {
+                count += 1;
+            }
+            write!(f, "{count}")?;
+            for (i, key) in self.keys.iter().enumerate() {
+                if mask & 1 << i != 0 {
+                    write!(f, " {key:?}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Feeds a recording back one tick at a time in place of the keyboard
+pub struct Playback {
+    recording: Recording,
+    next: usize,
+    input: InputState,
+}
+
+impl Playback {
+    pub fn level(&self) -> &str {
+        &self.recording.level
+    }
+    pub fn seed(&self) -> u64 {
+        self.recording.seed
+    }
+    // ticks left to play
+    pub fn remaining(&self) -> usize {
+        self.recording.len() - self.next
+    }
+    // The input for the next tick, or None once the recording runs out
+    pub fn next_input(&mut self) -> Option<&InputState> {
+        let mask = *self.recording.ticks.get(self.next)?;
+        self.next += 1;
+        self.input.next_frame();
+        for (i, key) in self.recording.keys.iter().enumerate() {
+            if mask & 1 << i != 0 {
+                self.input.press(*key);
+            } else {
+                self.input.release(*key);
+            }
+        }
+        Some(&self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[Key] = &[Key::KeyW, Key::Space];
+
+    #[test]
+    fn test_round_trip() {
+        let mut recording = Recording::new("level3", 7, KEYS);
+        let mut input = InputState::default();
+        for tick in 0..10 {
+            match tick {
+                2 => input.press(Key::Space),
+                3 => input.release(Key::Space),
+                4 => input.press(Key::KeyW),
+                // unbound keys aren't recorded
+                5 => input.press(Key::KeyZ),
+                _ => {}
+            }
+            recording.record(&input);
+            input.next_frame();
+        }
+        let text = recording.to_string();
+        assert_eq!(text, "replay level3 7\n2\n1 Space\n1\n6 KeyW\n");
+        assert_eq!(Recording::parse(&text, KEYS).unwrap(), recording);
+
+        let mut playback = recording.playback();
+        assert_eq!(playback.remaining(), 10);
+        playback.next_input();
+        playback.next_input();
+        let tick = playback.next_input().unwrap();
+        assert!(tick.is_key_pressed(Key::Space));
+        assert!(playback.next_input().unwrap().is_key_released(Key::Space));
+        let tick = playback.next_input().unwrap();
+        assert!(tick.is_key_pressed(Key::KeyW));
+        assert!(!playback.next_input().unwrap().is_key_pressed(Key::KeyW));
+        for _ in 0..4 {
+            assert!(playback.next_input().unwrap().is_key_down(Key::KeyW));
+        }
+        assert!(playback.next_input().is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Recording::parse("replay level3\n", KEYS),
+            Err(ReplayError::Parse(1, _))
+        ));
+        assert!(matches!(
+            Recording::parse("replay level3 7\n3\nx KeyW\n", KEYS),
+            Err(ReplayError::Parse(3, _))
+        ));
+        assert!(matches!(
+            Recording::parse("replay level3 7\n3 KeyZ\n", KEYS),
+            Err(ReplayError::Parse(2, _))
+        ));
+    }
+}
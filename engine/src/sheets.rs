@@ -0,0 +1,121 @@
+use crate::assets::{load_array_texture, load_array_texture_layers, AssetWatch};
+use crate::font::Font;
+use crate::hud::Hud;
+use crate::level::StartKind;
+use crate::registry::LevelRegistry;
+use crate::split::SplitScreen;
+use assets_manager::{asset::Png, AssetCache};
+use frenderer::sprites::{SheetRegion, Transform};
+use frenderer::{wgpu, Immediate};
+
+// The textures a game draws from, and the sprite groups that draw them: groups 0 and
+// 1 are the halves of a split screen, each with its own camera, and group 2 is the
+// HUD, whose texture is the tile sheet plus the font's glyphs.  Both PNGs are watched
+// so hot-reloading either one re-uploads them.
+pub struct Sheets {
+    tilesheet: AssetWatch,
+    glyphs: AssetWatch,
+}
+
+impl Sheets {
+    // Uses asset `tilesheet` for the tiles and asset `font` for the HUD's font (or the
+    // default font, if it doesn't load).  Also returns the HUD for a `w` by `h`
+    // screen, which draws its bars with `solid`.
+    pub fn new(
+        cache: &AssetCache,
+        tilesheet: &str,
+        font: &str,
+        (w, h): (f32, f32),
+        solid: SheetRegion,
+    ) -> (Self, Hud) {
+        let font = Font::load(cache, font).unwrap_or_else(|err| {
+            eprintln!("Using the default font: {err}");
+            Font::default()
+        });
+        let sheets = Self {
+            tilesheet: AssetWatch::new(tilesheet),
+            glyphs: AssetWatch::new(font.image()),
+        };
+        // the glyphs are the second layer of the HUD's texture
+        (sheets, Hud::new(w, h, font.with_sheet(1), solid))
+    }
+    // The tile sheet for the world, and the tile sheet plus the glyph sheet for the HUD
+    fn load_textures(
+        &self,
+        frend: &Immediate,
+        cache: &AssetCache,
+    ) -> Result<(wgpu::Texture, wgpu::Texture), assets_manager::Error> {
+        let tile_tex = load_array_texture(frend, cache, self.tilesheet.id())?;
+        let hud_tex =
+            load_array_texture_layers(frend, cache, &[self.tilesheet.id(), self.glyphs.id()])?;
+        Ok((tile_tex, hud_tex))
+    }
+    // Uploads the sheets and adds the three sprite groups, starting each with room
+    // for `lens` sprites
+    pub fn add_sprite_groups(
+        &self,
+        frend: &mut Immediate,
+        cache: &AssetCache,
+        screen: &SplitScreen,
+        hud: &Hud,
+        lens: [usize; 3],
+    ) -> Result<(), assets_manager::Error> {
+        let textures = self.load_textures(frend, cache)?;
+        add_groups(frend, textures, screen, hud, lens);
+        Ok(())
+    }
+    // Picks up whatever hot-reloading changed since last frame.  Levels get their new
+    // tiles (players and enemies stay put) and changed sheets are re-uploaded, with
+    // each sprite group keeping its size.  Anything that doesn't load stays as it was.
+    pub fn hot_reload<E: StartKind, S>(
+        &mut self,
+        frend: &mut Immediate,
+        cache: &AssetCache,
+        levels: &mut LevelRegistry<E, S>,
+        screen: &SplitScreen,
+        hud: &Hud,
+    ) {
+        cache.hot_reload();
+        for err in levels.reload_changed(cache) {
+            eprintln!("Keeping the old level: {err}");
+        }
+        // `|` rather than `||` so both watches notice
+        if !(self.tilesheet.changed::<Png>(cache) | self.glyphs.changed::<Png>(cache)) {
+            return;
+        }
+        match self.load_textures(frend, cache) {
+            Ok(textures) => {
+                // freed groups get reused last-in first-out, so remove them backwards
+                // to get them back in order
+                let lens = [0, 1, 2].map(|group| frend.sprite_group_size(group));
+                for group in (0..3).rev() {
+                    frend.sprite_group_remove(group);
+                }
+                add_groups(frend, textures, screen, hud, lens);
+            }
+            Err(err) => eprintln!("Keeping the old tilesheet: {err}"),
+        }
+    }
+}
+
+fn add_groups(
+    frend: &mut Immediate,
+    (tile_tex, hud_tex): (wgpu::Texture, wgpu::Texture),
+    screen: &SplitScreen,
+    hud: &Hud,
+    lens: [usize; 3],
+) {
+    let cameras = [
+        screen.views(1.0)[0].camera,
+        screen.views(1.0)[0].camera,
+        hud.camera(),
+    ];
+    for (group, (len, camera)) in lens.into_iter().zip(cameras).enumerate() {
+        frend.sprite_group_add(
+            if group == 2 { &hud_tex } else { &tile_tex },
+            vec![Transform::ZERO; len],
+            vec![SheetRegion::ZERO; len],
+            camera,
+        );
+    }
+}
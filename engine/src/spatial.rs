@@ -0,0 +1,131 @@
+use crate::geom::Rect;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+// Cell coordinates are tiny and not attacker-controlled, so a multiply is plenty of
+// hashing (std's default SipHash is most of the cost otherwise)
+#[derive(Default)]
+struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+    fn write_i32(&mut self, i: i32) {
+        self.write_u64(i as u32 as u64);
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// A uniform grid of buckets for finding what might be touching what without testing
+// every pair.  Cells work best around the size of the things in them; a tile is
+// about right for our sprites and bullets.
+pub struct SpatialHash {
+    cell_sz: f32,
+    buckets: HashMap<(i32, i32), Vec<usize>, BuildHasherDefault<CellHasher>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_sz: f32) -> Self {
+        assert!(cell_sz > 0.0, "spatial hash cells need a size");
+        Self {
+            cell_sz,
+            buckets: HashMap::default(),
+        }
+    }
+    // Buckets every rect under its index
+    pub fn build(cell_sz: f32, rects: &[Rect]) -> Self {
+        let mut hash = Self::new(cell_sz);
+        for (idx, rect) in rects.iter().enumerate() {
+            hash.insert(idx, *rect);
+        }
+        hash
+    }
+    // Empties the buckets but keeps their memory for the next tick
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.values_mut() {
+            bucket.clear();
+        }
+    }
+    // Every cell `rect` touches, including ones it only reaches the edge of, since
+    // touching rects count as overlapping
+    fn cells(&self, rect: Rect) -> impl Iterator<Item = (i32, i32)> {
+        let cell_sz = self.cell_sz;
+        let cell = move |v: f32| (v / cell_sz).floor() as i32;
+        let (x0, x1) = (cell(rect.x), cell(rect.x + rect.w as f32));
+        let (y0, y1) = (cell(rect.y), cell(rect.y + rect.h as f32));
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+    }
+    pub fn insert(&mut self, idx: usize, rect: Rect) {
+        let cells = self.cells(rect);
+        for cell in cells {
+            self.buckets.entry(cell).or_default().push(idx);
+        }
+    }
+    // Fills `found` with the indices of everything sharing a cell with `rect`, in
+    // increasing order and without repeats
+    pub fn query(&self, rect: Rect, found: &mut Vec<usize>) {
+        found.clear();
+        for cell in self.cells(rect) {
+            if let Some(bucket) = self.buckets.get(&cell) {
+                found.extend_from_slice(bucket);
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+    // (a index, hashed index) for every pair that might overlap, in the same order a
+    // nested loop over `objs_a` and then the hashed rects would visit them
+    pub fn candidate_pairs(&self, objs_a: &[Rect]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut found = Vec::new();
+        for (a_idx, a_rect) in objs_a.iter().enumerate() {
+            self.query(*a_rect, &mut found);
+            pairs.extend(found.iter().map(|b_idx| (a_idx, *b_idx)));
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: u16) -> Rect {
+        Rect { x, y, w, h: w }
+    }
+
+    #[test]
+    fn test_query() {
+        let hash = SpatialHash::build(
+            16.0,
+            &[
+                rect(0.0, 0.0, 4),
+                rect(40.0, 40.0, 4),
+                // big enough to land in four cells
+                rect(8.0, 8.0, 20),
+            ],
+        );
+        let mut found = Vec::new();
+        hash.query(rect(2.0, 2.0, 2), &mut found);
+        assert_eq!(found, vec![0, 2]);
+        hash.query(rect(20.0, 20.0, 2), &mut found);
+        assert_eq!(found, vec![2]);
+        hash.query(rect(100.0, -50.0, 2), &mut found);
+        assert!(found.is_empty());
+        // just touching the next cell over still finds what's in it
+        hash.query(rect(28.0, 44.0, 4), &mut found);
+        assert_eq!(found, vec![1]);
+        assert_eq!(
+            hash.candidate_pairs(&[rect(2.0, 2.0, 2), rect(40.0, 40.0, 1)]),
+            vec![(0, 0), (0, 2), (1, 1)]
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use crate::camera::Camera;
+use crate::geom::Vec2;
+use crate::level::Level;
+use frenderer::sprites::{Camera2D, SheetRegion, Transform};
+use frenderer::Immediate;
+use std::str::FromStr;
+
+// Two players on a big level: while they're close enough for one camera to frame
+// them both they share the screen, and once they're not it splits and each gets a
+// camera of their own.  frenderer has no viewports, so each half is a sprite group
+// whose camera is stretched to cover twice the world it shows (putting its world in
+// the right half of the screen), with the sprites that would spill into the other
+// half cropped off on the CPU.
+
+// Which way the screen splits: `Vertical` puts player 1 on the left and player 2 on
+// the right, `Horizontal` puts player 1 on top
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    Vertical,
+    Horizontal,
+}
+
+impl FromStr for Split {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vertical" => Ok(Split::Vertical),
+            "horizontal" => Ok(Split::Horizontal),
+            _ => Err(format!("a split is vertical or horizontal, not {s:?}")),
+        }
+    }
+}
+
+// once split, the players have to get this much closer than where it split (as a
+// fraction of `max_zoom`) before it joins again, so it doesn't flicker back and forth
+const REJOIN: f32 = 0.8;
+
+// the line between the halves, in screen pixels
+const DIVIDER_WIDTH: f32 = 2.0;
+
+// The part of the world a view shows, in world pixels; sprites outside it get
+// cropped off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clip {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Clip {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+}
+
+// One sprite group's worth of the screen: the camera to give the group, what to crop
+// its sprites to, and (on one of the halves of a split) where to draw the line down
+// the middle
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub camera: Camera2D,
+    pub clip: Clip,
+    pub divider: Option<Transform>,
+}
+
+impl View {
+    fn new(camera: Camera2D) -> Self {
+        let min = Vec2 {
+            x: camera.screen_pos[0],
+            y: camera.screen_pos[1],
+        };
+        let size = Vec2 {
+            x: camera.screen_size[0],
+            y: camera.screen_size[1],
+        };
+        Self {
+            camera,
+            clip: Clip {
+                min,
+                max: min + size,
+            },
+            divider: None,
+        }
+    }
+    // Where `pos` shows up in a render target of `screen` pixels, from the bottom left
+    pub fn world_to_screen(&self, pos: Vec2, screen: Vec2) -> Vec2 {
+        Vec2 {
+            x: (pos.x - self.camera.screen_pos[0]) / self.camera.screen_size[0] * screen.x,
+            y: (pos.y - self.camera.screen_pos[1]) / self.camera.screen_size[1] * screen.y,
+        }
+    }
+    // Draws one sprite into `group`, cropped to the view
+    pub fn draw_sprite(
+        &self,
+        frend: &mut Immediate,
+        group: usize,
+        mut trf: Transform,
+        mut uv: SheetRegion,
+    ) {
+        if clip_sprite(&mut trf, &mut uv, &self.clip) {
+            frend.draw_sprite(group, trf, uv);
+        }
+    }
+    // Draws `level`'s tiles into `group`, cropped to the view
+    pub fn draw_level<E>(&self, frend: &mut Immediate, group: usize, level: &Level<E>) {
+        let (trfs, uvs) = frend.draw_sprites(group, level.sprite_count());
+        level.render_into(trfs, uvs);
+        clip_sprites(trfs, uvs, &self.clip);
+    }
+}
+
+// Crops a sprite to `clip`, trimming the same share off its sheet region so what's
+// left isn't squashed.  Rotated sprites can't be cut straight, so they're kept
+// whole if their middle's inside (a tank on the line pokes under the divider) and
+// dropped otherwise.  Returns whether any of it's left to draw.
+pub fn clip_sprite(trf: &mut Transform, uv: &mut SheetRegion, clip: &Clip) -> bool {
+    if trf.rot != 0.0 {
+        return clip.contains(Vec2 { x: trf.x, y: trf.y });
+    }
+    let (w, h) = (trf.w as f32, trf.h as f32);
+    // whole pixels to cut off each side, rounding up so nothing pokes past the clip
+    let cut = |over: f32| over.max(0.0).ceil();
+    let left = cut(clip.min.x - (trf.x - w / 2.0));
+    let right = cut(trf.x + w / 2.0 - clip.max.x);
+    let bottom = cut(clip.min.y - (trf.y - h / 2.0));
+    let top = cut(trf.y + h / 2.0 - clip.max.y);
+    if left + right >= w || bottom + top >= h {
+        return false;
+    }
+    if left + right + bottom + top == 0.0 {
+        return true;
+    }
+    let (uv_w, uv_h) = (uv.w as f32, uv.h as f32);
+    trf.x += (left - right) / 2.0;
+    trf.y += (bottom - top) / 2.0;
+    trf.w = (w - left - right) as u16;
+    trf.h = (h - bottom - top) as u16;
+    // the sheet's y goes down, so its top edge is the sprite's top; signed widths
+    // (flipped regions) work out the same way
+    uv.x = (uv.x as f32 + (uv_w * left / w).round()) as u16;
+    uv.y = (uv.y as f32 + (uv_h * top / h).round()) as u16;
+    uv.w = (uv_w * trf.w as f32 / w).round() as i16;
+    uv.h = (uv_h * trf.h as f32 / h).round() as i16;
+    true
+}
+
+// `clip_sprite` for a whole batch; whatever's entirely outside turns invisible
+pub fn clip_sprites(trfs: &mut [Transform], uvs: &mut [SheetRegion], clip: &Clip) {
+    for (trf, uv) in trfs.iter_mut().zip(uvs.iter_mut()) {
+        if !clip_sprite(trf, uv, clip) {
+            *trf = Transform::ZERO;
+            *uv = SheetRegion::ZERO;
+        }
+    }
+}
+
+// The cameras for two players: one they share, and one each for when the screen's
+// split.  With no `split` it only ever shares, like a lone `Camera`.
+pub struct SplitScreen {
+    split: Option<Split>,
+    shared: Camera,
+    halves: [Camera; 2],
+    is_split: bool,
+}
+
+impl SplitScreen {
+    pub fn new(view_w: f32, view_h: f32, split: Option<Split>) -> Self {
+        let half = match split {
+            Some(Split::Horizontal) => (view_w, view_h / 2.0),
+            _ => (view_w / 2.0, view_h),
+        };
+        Self {
+            split,
+            shared: Camera::new(view_w, view_h),
+            halves: [Camera::new(half.0, half.1), Camera::new(half.0, half.1)],
+            is_split: false,
+        }
+    }
+    pub fn is_split(&self) -> bool {
+        self.is_split
+    }
+    pub fn shared(&self) -> &Camera {
+        &self.shared
+    }
+    // Moves every camera `dt` seconds' worth towards its players (`None` for one
+    // who's out), then decides whether to be split
+    pub fn follow<E>(&mut self, level: &Level<E>, players: [Option<Vec2>; 2], dt: f32) {
+        let living: Vec<Vec2> = players.iter().flatten().copied().collect();
+        self.shared.follow(level, &living, dt);
+        for (camera, player) in self.halves.iter_mut().zip(players) {
+            let targets: Vec<Vec2> = player.into_iter().collect();
+            camera.follow(level, &targets, dt);
+        }
+        if self.split.is_none() || living.len() < 2 {
+            self.is_split = false;
+            return;
+        }
+        let zoom = self.shared.fit_zoom(&living);
+        let max_zoom = self.shared.max_zoom;
+        if !self.is_split && zoom > max_zoom {
+            self.is_split = true;
+        } else if self.is_split && zoom <= max_zoom * REJOIN {
+            self.is_split = false;
+        }
+    }
+    pub fn shake(&mut self, strength: f32, time: f32) {
+        self.shared.shake(strength, time);
+        for camera in self.halves.iter_mut() {
+            camera.shake(strength, time);
+        }
+    }
+    // What to draw, one view per sprite group: just the shared one, or player 1's
+    // half and then player 2's
+    pub fn views(&self, alpha: f32) -> Vec<View> {
+        let Some(split) = self.split.filter(|_| self.is_split) else {
+            return vec![View::new(self.shared.view(alpha))];
+        };
+        let mut views: Vec<View> = self
+            .halves
+            .iter()
+            .enumerate()
+            .map(|(p_i, camera)| {
+                let half = camera.view(alpha);
+                let [x, y] = half.screen_pos;
+                let [w, h] = half.screen_size;
+                let mut view = View::new(half);
+                view.camera = match (split, p_i) {
+                    (Split::Vertical, 0) => Camera2D {
+                        screen_pos: [x, y],
+                        screen_size: [w * 2.0, h],
+                    },
+                    (Split::Vertical, _) => Camera2D {
+                        screen_pos: [x - w, y],
+                        screen_size: [w * 2.0, h],
+                    },
+                    (Split::Horizontal, 0) => Camera2D {
+                        screen_pos: [x, y - h],
+                        screen_size: [w, h * 2.0],
+                    },
+                    (Split::Horizontal, _) => Camera2D {
+                        screen_pos: [x, y],
+                        screen_size: [w, h * 2.0],
+                    },
+                };
+                view
+            })
+            .collect();
+        // the line goes along player 1's inner edge, drawn over everything
+        let clip = views[0].clip;
+        let thick = (DIVIDER_WIDTH * self.halves[0].zoom()).ceil();
+        let size = clip.max - clip.min;
+        views[0].divider = Some(match split {
+            Split::Vertical => Transform {
+                x: clip.max.x,
+                y: clip.min.y + size.y / 2.0,
+                w: thick as u16,
+                h: size.y.ceil() as u16,
+                rot: 0.0,
+            },
+            Split::Horizontal => Transform {
+                x: clip.min.x + size.x / 2.0,
+                y: clip.min.y,
+                w: size.x.ceil() as u16,
+                h: thick as u16,
+                rot: 0.0,
+            },
+        });
+        views
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::StartKind;
+
+    struct NoStarts;
+    impl StartKind for NoStarts {
+        fn from_start(_kind: &str, _args: &[&str]) -> Option<Self> {
+            None
+        }
+    }
+
+    // level5's size, 784x304 pixels
+    fn big() -> Level<NoStarts> {
+        let rows = vec![".".repeat(49); 19].join("\n");
+        Level::parse(&format!(
+            "big 49 19\n====\n. o 0 0 16 16\n====\n{rows}\n====\n"
+        ))
+        .unwrap()
+    }
+
+    const SCREEN: Vec2 = Vec2 { x: 320.0, y: 240.0 };
+
+    #[test]
+    fn test_splits_and_rejoins() {
+        let level = big();
+        let mut screen = SplitScreen::new(SCREEN.x, SCREEN.y, Some(Split::Vertical));
+        let left = Vec2 { x: 100.0, y: 150.0 };
+        // close together they share
+        screen.follow(
+            &level,
+            [Some(left), Some(Vec2 { x: 300.0, y: 150.0 })],
+            1.0 / 60.0,
+        );
+        assert!(!screen.is_split());
+        assert_eq!(screen.views(1.0).len(), 1);
+        // too far apart for max_zoom (they'd need 2.25) and it splits
+        let right = Vec2 { x: 756.0, y: 150.0 };
+        screen.follow(&level, [Some(left), Some(right)], 1.0 / 60.0);
+        assert!(screen.is_split());
+        for _ in 0..120 {
+            screen.follow(&level, [Some(left), Some(right)], 1.0 / 60.0);
+        }
+        // each player's in the middle of their own half
+        let views = screen.views(1.0);
+        assert_eq!(views.len(), 2);
+        let on_screen = views[0].world_to_screen(left, SCREEN);
+        assert!(on_screen.x > 0.0 && on_screen.x < 160.0);
+        let on_screen = views[1].world_to_screen(right, SCREEN);
+        assert!(on_screen.x > 160.0 && on_screen.x < 320.0);
+        assert!(views[0].divider.is_some() && views[1].divider.is_none());
+        // each half only shows its own player
+        assert!(views[0].clip.contains(left) && !views[0].clip.contains(right));
+        assert!(views[1].clip.contains(right) && !views[1].clip.contains(left));
+
+        // just inside max_zoom isn't close enough to join again...
+        let nearer = Vec2 { x: 650.0, y: 150.0 };
+        screen.follow(&level, [Some(left), Some(nearer)], 1.0 / 60.0);
+        assert!(screen.is_split());
+        // ...but well inside is, and so is one of them dying
+        screen.follow(
+            &level,
+            [Some(left), Some(Vec2 { x: 500.0, y: 150.0 })],
+            1.0 / 60.0,
+        );
+        assert!(!screen.is_split());
+        screen.follow(&level, [Some(left), Some(right)], 1.0 / 60.0);
+        assert!(screen.is_split());
+        screen.follow(&level, [Some(left), None], 1.0 / 60.0);
+        assert!(!screen.is_split());
+
+        // without a split it never does
+        let mut screen = SplitScreen::new(SCREEN.x, SCREEN.y, None);
+        screen.follow(&level, [Some(left), Some(right)], 1.0 / 60.0);
+        assert!(!screen.is_split());
+    }
+
+    #[test]
+    fn test_horizontal_puts_player_1_on_top() {
+        let level = big();
+        let mut screen = SplitScreen::new(SCREEN.x, SCREEN.y, Some(Split::Horizontal));
+        let (bottom, top) = (Vec2 { x: 40.0, y: 20.0 }, Vec2 { x: 740.0, y: 280.0 });
+        screen.follow(&level, [Some(top), Some(bottom)], 1.0 / 60.0);
+        let views = screen.views(1.0);
+        assert_eq!(views.len(), 2);
+        assert!(views[0].world_to_screen(top, SCREEN).y > 120.0);
+        assert!(views[1].world_to_screen(bottom, SCREEN).y < 120.0);
+        assert_eq!("horizontal".parse(), Ok(Split::Horizontal));
+        assert!("diagonal".parse::<Split>().is_err());
+    }
+
+    #[test]
+    fn test_clip_sprite() {
+        let clip = Clip {
+            min: Vec2 { x: 0.0, y: 0.0 },
+            max: Vec2 { x: 100.0, y: 100.0 },
+        };
+        let tile = |x: f32, y: f32| Transform {
+            x,
+            y,
+            w: 16,
+            h: 16,
+            rot: 0.0,
+        };
+        // neither of these compares, so look at the bits that change
+        let rect = |uv: SheetRegion| (uv.x, uv.y, uv.w, uv.h);
+        // all inside: untouched
+        let (mut trf, mut uv) = (tile(50.0, 50.0), SheetRegion::rect(10, 20, 16, 16));
+        assert!(clip_sprite(&mut trf, &mut uv, &clip));
+        assert_eq!(rect(uv), (10, 20, 16, 16));
+        // a quarter over the right edge loses its right quarter
+        let (mut trf, mut uv) = (tile(96.0, 50.0), SheetRegion::rect(10, 20, 16, 16));
+        assert!(clip_sprite(&mut trf, &mut uv, &clip));
+        assert_eq!((trf.x, trf.w), (94.0, 12));
+        assert_eq!(rect(uv), (10, 20, 12, 16));
+        // half over the top loses the top half of its sheet region too
+        let (mut trf, mut uv) = (tile(50.0, 100.0), SheetRegion::rect(10, 20, 32, 32));
+        assert!(clip_sprite(&mut trf, &mut uv, &clip));
+        assert_eq!((trf.y, trf.h), (96.0, 8));
+        assert_eq!(rect(uv), (10, 36, 32, 16));
+        // all outside, or rotated with its middle outside: gone
+        let mut trfs = [tile(120.0, 50.0), tile(50.0, 50.0)];
+        let mut uvs = [SheetRegion::rect(10, 20, 16, 16); 2];
+        clip_sprites(&mut trfs, &mut uvs, &clip);
+        assert_eq!((trfs[0].w, rect(uvs[0])), (0, (0, 0, 0, 0)));
+        assert_eq!(
+            (trfs[1].x, trfs[1].w, rect(uvs[1])),
+            (50.0, 16, (10, 20, 16, 16))
+        );
+        let (mut trf, mut uv) = (tile(101.0, 50.0), SheetRegion::rect(10, 20, 16, 16));
+        trf.rot = 1.0;
+        assert!(!clip_sprite(&mut trf, &mut uv, &clip));
+    }
+}
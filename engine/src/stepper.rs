@@ -0,0 +1,83 @@
+// Turns however long each frame really took into a whole number of fixed-length
+// simulation steps, carrying what's left over into the next frame.  `run` uses one
+// with `DT`; `alpha` says how far the leftover is towards the next step, so a game
+// can draw things partway between where they were and where they are.
+pub struct FixedStepper {
+    dt: f32,
+    max_steps: usize,
+    // time banked towards the next step, in steps (so always less than 1 after `advance`)
+    acc: f32,
+}
+
+// a frame this close to a whole number of steps counts as exactly that many, so a
+// 60Hz display's vsync jitter doesn't make us alternate between 0 and 2 steps
+const SNAP: f32 = 0.0002;
+// more steps than this in one frame and we stop trying to catch up (see `advance`)
+const MAX_STEPS: usize = 8;
+
+impl FixedStepper {
+    pub fn new(dt: f32) -> Self {
+        Self {
+            dt,
+            max_steps: MAX_STEPS,
+            acc: 0.0,
+        }
+    }
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+    // Banks `elapsed` seconds of real time and returns how many steps to run now.
+    // After a long stall (dragging the window, sitting in the debugger) it runs at
+    // most `max_steps` and forgets the rest, so the game skips ahead a little instead
+    // of spending the next few seconds catching up.
+    pub fn advance(&mut self, elapsed: f32) -> usize {
+        let mut steps = elapsed.max(0.0) / self.dt;
+        if (steps - steps.round()).abs() * self.dt < SNAP {
+            steps = steps.round();
+        }
+        self.acc += steps;
+        let whole = self.acc.floor();
+        self.acc -= whole;
+        (whole as usize).min(self.max_steps)
+    }
+    // How far (0 to 1) the time that's been banked is from the last step to the
+    // next one
+    pub fn alpha(&self) -> f32 {
+        self.acc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_snap_and_clamp() {
+        let dt = 1.0 / 60.0;
+        let mut stepper = FixedStepper::new(dt);
+        // a frame that's a hair off one step is one step, with nothing left over
+        assert_eq!(stepper.advance(dt + 0.0001), 1);
+        assert_eq!(stepper.alpha(), 0.0);
+        assert_eq!(stepper.advance(dt - 0.0001), 1);
+        assert_eq!(stepper.alpha(), 0.0);
+        // on a 120Hz display every other frame steps, and the ones between are halfway
+        assert_eq!(stepper.advance(dt / 2.0), 0);
+        assert!((stepper.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(stepper.advance(dt / 2.0), 1);
+        assert!(stepper.alpha() < 1e-4);
+        // a long stall only runs a few steps, but keeps the part of a step it had
+        stepper.advance(dt / 4.0);
+        assert_eq!(stepper.advance(10.0), MAX_STEPS);
+        assert!((stepper.alpha() - 0.25).abs() < 1e-3);
+        assert_eq!(stepper.advance(0.0), 0);
+
+        let mut strict = FixedStepper::new(dt).with_max_steps(2);
+        assert_eq!(strict.advance(dt * 3.0), 2);
+        assert_eq!(strict.advance(dt), 1);
+        assert_eq!(strict.dt(), dt);
+    }
+}
@@ -0,0 +1,484 @@
+use crate::world::World;
+use assets_manager::AssetCache;
+use engine::controls::Action;
+use engine::input::InputState;
+
+// How a match goes, from arenas.txt: how many rounds it lasts at most, and which
+// levels the rounds rotate through
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub best_of: u32,
+    pub arenas: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RulesError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl std::fmt::Display for RulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+impl Default for Rules {
+    fn default() -> Self {
+        include_str!("../../engine/content/arenas.txt")
+            .parse()
+            .expect("content/arenas.txt should parse")
+    }
+}
+
+impl std::str::FromStr for Rules {
+    type Err = RulesError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl Rules {
+    pub fn parse(text: &str) -> Result<Self, RulesError> {
+        let mut best_of = None;
+        let mut arenas = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let error = |msg: String| RulesError { line: line_no, msg };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+                [comment, ..] if comment.starts_with('#') => {}
+                ["best_of", n] => {
+                    let n = n
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| error(format!("{n:?} isn't a number of rounds")))?;
+                    best_of = Some(n);
+                }
+                ["arena", name] => arenas.push(name.to_string()),
+                _ => return Err(error(format!("couldn't make sense of {line:?}"))),
+            }
+        }
+        let last = text.lines().count();
+        let best_of = best_of.ok_or_else(|| RulesError {
+            line: last,
+            msg: "no `best_of N` line".to_string(),
+        })?;
+        if arenas.is_empty() {
+            return Err(RulesError {
+                line: last,
+                msg: "no `arena LEVEL` lines".to_string(),
+            });
+        }
+        Ok(Self { best_of, arenas })
+    }
+    // Loads arenas.txt (or whichever text asset `id` names) from `cache`
+    pub fn load(cache: &AssetCache, id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let handle = cache.load::<String>(id)?;
+        let rules = Self::parse(&handle.read())?;
+        Ok(rules)
+    }
+    // The arenas in the order a match that starts in `first` goes through them: on
+    // around the list from `first`, or `first` and then the whole list if it isn't
+    // one of the arenas
+    pub fn rotation_from(&self, first: &str) -> Vec<String> {
+        let start = self.arenas.iter().position(|arena| arena == first);
+        let (before, after) = self.arenas.split_at(start.unwrap_or(0));
+        let mut rotation = Vec::with_capacity(self.arenas.len() + 1);
+        if start.is_none() {
+            rotation.push(first.to_string());
+        }
+        rotation.extend(after.iter().chain(before).cloned());
+        rotation
+    }
+    // rounds it takes to win the match
+    pub fn to_win(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+}
+
+// where a match is up to; the numbers are seconds left before moving on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    // waiting for someone to press fire
+    Title,
+    // the tanks are at their starts and can't move yet
+    Countdown(f32),
+    Playing,
+    // one tank's left (or none, for a draw); the round's decided but the world keeps
+    // going for a moment so everyone can see how, with the tanks out of harm's way so
+    // a stray bullet can't take the win back
+    RoundOver(Option<usize>, f32),
+    // someone's won enough rounds, or all `best_of` have been played and whoever's
+    // ahead takes it (nobody, if they're level); fire starts another match
+    MatchOver(Option<usize>),
+}
+
+const COUNTDOWN_TIME: f32 = 3.0;
+const ROUND_OVER_TIME: f32 = 2.0;
+
+// A match between the two tanks: best of `rules.best_of` rounds, each one in the
+// next arena, with the scores carried from round to round.  It's in charge of when
+// the world steps, so main.rs (and replays) step it instead of the world.
+pub struct Rounds {
+    pub phase: Phase,
+    pub scores: [u32; 2],
+    // rounds played so far this match
+    pub round: usize,
+    best_of: u32,
+    to_win: u32,
+    // the arenas' level indices, in order
+    arenas: Vec<usize>,
+}
+
+impl Rounds {
+    pub fn new(rules: &Rules, arenas: Vec<usize>) -> Self {
+        assert!(!arenas.is_empty(), "a match needs an arena");
+        Self {
+            phase: Phase::Title,
+            scores: [0, 0],
+            round: 0,
+            best_of: rules.best_of,
+            to_win: rules.to_win(),
+            arenas,
+        }
+    }
+    pub fn step(&mut self, world: &mut World, input: &InputState, dt: f32) {
+        let fired = (0..2).any(|p_i| world.controls.is_pressed(input, p_i, Action::Fire));
+        match self.phase {
+            Phase::Title | Phase::MatchOver(_) => {
+                if fired {
+                    self.start_match(world);
+                }
+            }
+            Phase::Countdown(left) => {
+                self.phase = if left > dt {
+                    Phase::Countdown(left - dt)
+                } else {
+                    Phase::Playing
+                };
+            }
+            Phase::Playing => {
+                world.step(input, dt);
+                let alive: Vec<usize> = (0..2).filter(|p_i| world.player(*p_i).alive).collect();
+                if alive.len() < 2 {
+                    let winner = alive.first().copied();
+                    if let Some(winner) = winner {
+                        self.scores[winner] += 1;
+                    }
+                    world.truce = true;
+                    self.phase = Phase::RoundOver(winner, ROUND_OVER_TIME);
+                }
+            }
+            Phase::RoundOver(winner, left) => {
+                world.step(input, dt);
+                if left > dt {
+                    self.phase = Phase::RoundOver(winner, left - dt);
+                } else if let Some(champ) = (0..2).find(|p_i| self.scores[*p_i] >= self.to_win) {
+                    self.phase = Phase::MatchOver(Some(champ));
+                } else if self.round as u32 + 1 >= self.best_of {
+                    // out of rounds, after draws or an even split
+                    let [p1, p2] = self.scores;
+                    self.phase = Phase::MatchOver(match p1.cmp(&p2) {
+                        std::cmp::Ordering::Greater => Some(0),
+                        std::cmp::Ordering::Less => Some(1),
+                        std::cmp::Ordering::Equal => None,
+                    });
+                } else {
+                    self.round += 1;
+                    self.start_round(world);
+                }
+            }
+        }
+    }
+    // A new match from the first round, as if someone had just pressed fire
+    pub fn start_match(&mut self, world: &mut World) {
+        self.scores = [0, 0];
+        self.round = 0;
+        self.start_round(world);
+    }
+    // the level this round's in
+    pub fn arena(&self) -> usize {
+        self.arenas[self.round % self.arenas.len()]
+    }
+    // What to put across the screen right now, if anything
+    pub fn banner(&self) -> Option<String> {
+        match self.phase {
+            Phase::Title => Some("PRESS FIRE".to_string()),
+            Phase::Countdown(left) => Some(format!("{}", left.ceil() as u32)),
+            Phase::Playing => None,
+            Phase::RoundOver(Some(winner), _) => Some(format!("PLAYER {} SCORES", winner + 1)),
+            Phase::RoundOver(None, _) => Some("DRAW".to_string()),
+            Phase::MatchOver(Some(champ)) => Some(format!("PLAYER {} WINS", champ + 1)),
+            Phase::MatchOver(None) => Some("MATCH DRAWN".to_string()),
+        }
+    }
+    // Every round starts from scratch in its arena, and stays there
+    fn start_round(&mut self, world: &mut World) {
+        world.doors_open = false;
+        world.restart(self.arena());
+        self.phase = Phase::Countdown(COUNTDOWN_TIME);
+    }
+}
+
+// What `--headless` prints about the match at the end of a run
+impl std::fmt::Display for Rounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [p1, p2] = self.scores;
+        writeln!(
+            f,
+            "round {} at {p1} - {p2}: {:?}",
+            self.round + 1,
+            self.phase
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Level;
+    use engine::geom::Vec2;
+    use engine::input::Key;
+    use engine::registry::LevelRegistry;
+    use engine::DT;
+
+    // two small arenas with the players in opposite corners
+    fn arena(name: &str) -> Level {
+        Level::parse(&format!(
+            "{name} 7 5
+====
+. o 594 55 16 16
+# s 419 61 16 16
+====
+#######
+#.....#
+#.....#
+#.....#
+#######
+====
+player1 1 1
+player2 5 3
+enemy 3 2
+"
+        ))
+        .unwrap()
+    }
+
+    fn rounds() -> (Rounds, World) {
+        rounds_of(3)
+    }
+
+    fn rounds_of(best_of: u32) -> (Rounds, World) {
+        let mut levels = LevelRegistry::new();
+        levels.add(arena("a"));
+        levels.add(arena("b"));
+        let rules = Rules::parse(&format!("best_of {best_of}\narena a\narena b\n")).unwrap();
+        (Rounds::new(&rules, vec![0, 1]), World::new(levels, 0))
+    }
+
+    // steps until the phase changes (or gives up after a while)
+    fn step_until_change(rounds: &mut Rounds, world: &mut World, input: &mut InputState) {
+        let phase = std::mem::discriminant(&rounds.phase);
+        for _ in 0..1000 {
+            rounds.step(world, input, DT);
+            input.next_frame();
+            if std::mem::discriminant(&rounds.phase) != phase {
+                return;
+            }
+        }
+        panic!("stuck in {:?}", rounds.phase);
+    }
+
+    // player 2 goes down and player 1 takes the round
+    fn win_round(rounds: &mut Rounds, world: &mut World, input: &mut InputState) {
+        assert_eq!(rounds.phase, Phase::Playing);
+        world.player_mut(1).alive = false;
+        step_until_change(rounds, world, input);
+        assert!(matches!(rounds.phase, Phase::RoundOver(Some(0), _)));
+        step_until_change(rounds, world, input);
+    }
+
+    #[test]
+    fn test_match_flow() {
+        let (mut rounds, mut world) = rounds();
+        let mut input = InputState::default();
+        // nothing moves on the title screen
+        let start = world.player(0).pos;
+        input.press(Key::ArrowUp);
+        rounds.step(&mut world, &input, DT);
+        assert_eq!(rounds.phase, Phase::Title);
+        assert_eq!(world.player(0).pos, start);
+        assert_eq!(rounds.banner().as_deref(), Some("PRESS FIRE"));
+
+        // fire starts the countdown, and the tanks still wait for it
+        input.press(Key::Space);
+        rounds.step(&mut world, &input, DT);
+        input.next_frame();
+        assert!(matches!(rounds.phase, Phase::Countdown(_)));
+        assert_eq!(rounds.banner().as_deref(), Some("3"));
+        assert!(!world.doors_open);
+        step_until_change(&mut rounds, &mut world, &mut input);
+        assert_eq!(world.player(0).pos, start);
+        assert_eq!(rounds.phase, Phase::Playing);
+
+        // first round: player 1 scores, and the next round's in the other arena
+        // with everyone back where they started and no bullets left over
+        input.release(Key::Space);
+        input.next_frame();
+        input.press(Key::Space);
+        rounds.step(&mut world, &input, DT);
+        input.next_frame();
+        assert!(world.projectiles().count() > 0);
+        win_round(&mut rounds, &mut world, &mut input);
+        assert_eq!(rounds.scores, [1, 0]);
+        assert_eq!(world.level().name(), "b");
+        assert!(matches!(rounds.phase, Phase::Countdown(_)));
+        assert!(world.player(1).alive);
+        assert_eq!(world.player(0).pos, start);
+        assert_eq!(world.projectiles().count(), 0);
+        assert_eq!(world.enemies().count(), 1);
+
+        // a draw doesn't score
+        step_until_change(&mut rounds, &mut world, &mut input);
+        world.player_mut(0).alive = false;
+        world.player_mut(1).alive = false;
+        step_until_change(&mut rounds, &mut world, &mut input);
+        assert!(matches!(rounds.phase, Phase::RoundOver(None, _)));
+        assert_eq!(rounds.banner().as_deref(), Some("DRAW"));
+        step_until_change(&mut rounds, &mut world, &mut input);
+        assert_eq!(rounds.scores, [1, 0]);
+        // back to the first arena
+        assert_eq!(world.level().name(), "a");
+
+        // two rounds out of (best of) three takes the match
+        step_until_change(&mut rounds, &mut world, &mut input);
+        win_round(&mut rounds, &mut world, &mut input);
+        assert_eq!(rounds.phase, Phase::MatchOver(Some(0)));
+        assert_eq!(rounds.banner().as_deref(), Some("PLAYER 1 WINS"));
+
+        // and fire starts a new one from scratch
+        input.release(Key::Space);
+        input.next_frame();
+        input.press(Key::Space);
+        rounds.step(&mut world, &input, DT);
+        assert_eq!(rounds.scores, [0, 0]);
+        assert_eq!(world.level().name(), "a");
+        assert!(matches!(rounds.phase, Phase::Countdown(_)));
+    }
+
+    // presses fire on the title screen and waits out the countdown
+    fn start_match(rounds: &mut Rounds, world: &mut World, input: &mut InputState) {
+        input.press(Key::Space);
+        rounds.step(world, input, DT);
+        input.release(Key::Space);
+        input.next_frame();
+        step_until_change(rounds, world, input);
+        assert_eq!(rounds.phase, Phase::Playing);
+    }
+
+    // only `survivors` are left standing, and the round plays out to whatever's next
+    fn end_round(
+        rounds: &mut Rounds,
+        world: &mut World,
+        input: &mut InputState,
+        survivors: &[usize],
+    ) {
+        for p_i in 0..2 {
+            world.player_mut(p_i).alive = survivors.contains(&p_i);
+        }
+        step_until_change(rounds, world, input);
+        step_until_change(rounds, world, input);
+        if matches!(rounds.phase, Phase::Countdown(_)) {
+            step_until_change(rounds, world, input);
+        }
+    }
+
+    #[test]
+    fn test_drawn_rounds_end_the_match() {
+        let (mut rounds, mut world) = rounds();
+        let mut input = InputState::default();
+        start_match(&mut rounds, &mut world, &mut input);
+        // nobody ever scores, but three rounds is still all a best of three gets
+        end_round(&mut rounds, &mut world, &mut input, &[]);
+        end_round(&mut rounds, &mut world, &mut input, &[]);
+        assert_eq!(rounds.phase, Phase::Playing);
+        end_round(&mut rounds, &mut world, &mut input, &[]);
+        assert_eq!(rounds.phase, Phase::MatchOver(None));
+        assert_eq!(rounds.banner().as_deref(), Some("MATCH DRAWN"));
+
+        // and with a draw in the middle, whoever's ahead at the end takes it
+        let (mut rounds, mut world) = rounds_of(3);
+        start_match(&mut rounds, &mut world, &mut input);
+        end_round(&mut rounds, &mut world, &mut input, &[1]);
+        end_round(&mut rounds, &mut world, &mut input, &[]);
+        end_round(&mut rounds, &mut world, &mut input, &[]);
+        assert_eq!(rounds.scores, [0, 1]);
+        assert_eq!(rounds.phase, Phase::MatchOver(Some(1)));
+    }
+
+    #[test]
+    fn test_even_best_of_can_tie() {
+        let (mut rounds, mut world) = rounds_of(4);
+        let mut input = InputState::default();
+        start_match(&mut rounds, &mut world, &mut input);
+        for winner in [0, 1, 0, 1] {
+            assert_eq!(rounds.phase, Phase::Playing);
+            end_round(&mut rounds, &mut world, &mut input, &[winner]);
+        }
+        assert_eq!(rounds.scores, [2, 2]);
+        assert_eq!(rounds.phase, Phase::MatchOver(None));
+    }
+
+    #[test]
+    fn test_survivor_safe_after_round() {
+        let (mut rounds, mut world) = rounds();
+        let mut input = InputState::default();
+        input.press(Key::Space);
+        rounds.step(&mut world, &input, DT);
+        input.release(Key::Space);
+        input.next_frame();
+        step_until_change(&mut rounds, &mut world, &mut input);
+        world.player_mut(1).alive = false;
+        rounds.step(&mut world, &input, DT);
+        assert!(matches!(rounds.phase, Phase::RoundOver(Some(0), _)));
+        // a bullet that was already on its way runs into the winner
+        let pos = world.player(0).pos;
+        world.fire(pos - Vec2 { x: 4.0, y: 0.0 }, 0.0);
+        rounds.step(&mut world, &input, DT);
+        assert!(world.player(0).alive);
+        assert_eq!(rounds.banner().as_deref(), Some("PLAYER 1 SCORES"));
+        step_until_change(&mut rounds, &mut world, &mut input);
+        assert_eq!(rounds.scores, [1, 0]);
+        // and the next round's played for real again
+        step_until_change(&mut rounds, &mut world, &mut input);
+        let pos = world.player(0).pos;
+        world.fire(pos - Vec2 { x: 4.0, y: 0.0 }, 0.0);
+        rounds.step(&mut world, &input, DT);
+        assert!(!world.player(0).alive);
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = Rules::default();
+        assert_eq!(rules.arenas[0], "level3");
+        assert_eq!(Rules::parse("best_of 5\narena x\n").unwrap().to_win(), 3);
+        assert_eq!(Rules::parse("best_of 4\narena x\n").unwrap().to_win(), 3);
+        let err = |text: &str| Rules::parse(text).unwrap_err();
+        assert_eq!(err("best_of 0\narena x\n").line, 1);
+        assert_eq!(err("best_of 3\n").msg, "no `arena LEVEL` lines");
+        assert_eq!(err("best_of 3\narena\n").line, 2);
+    }
+
+    #[test]
+    fn test_rotation_from() {
+        let rules = Rules::parse("best_of 3\narena a\narena b\narena c\n").unwrap();
+        assert_eq!(rules.rotation_from("a"), ["a", "b", "c"]);
+        assert_eq!(rules.rotation_from("b"), ["b", "c", "a"]);
+        assert_eq!(rules.rotation_from("x"), ["x", "a", "b", "c"]);
+    }
+}
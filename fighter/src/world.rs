@@ -0,0 +1,1068 @@
+use engine::arena::{Arena, EntityId};
+use engine::collision::*;
+use engine::controls::{Action, Controls};
+use engine::ecs::{
+    despawn, Actor, Bounces, Components, Controller, Despawn, DespawnCause, Lifetime, Motion,
+    Velocity,
+};
+use engine::geom::*;
+use engine::input::InputState;
+use engine::level::StartKind;
+use engine::registry::LevelRegistry;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityType {
+    Player1,
+    Player2,
+    Enemy,
+    Projectile,
+    // which level, grid x in dest level, grid y in dest level
+    Door(String, u16, u16),
+}
+
+impl StartKind for EntityType {
+    fn from_start(kind: &str, args: &[&str]) -> Option<Self> {
+        match (kind, args) {
+            // levels with one start (like the mazes) just say "player"
+            ("player" | "player1", []) => Some(EntityType::Player1),
+            ("player2", []) => Some(EntityType::Player2),
+            ("enemy", []) => Some(EntityType::Enemy),
+            ("door", [to_room, to_x, to_y]) => Some(EntityType::Door(
+                to_room.to_string(),
+                to_x.parse().ok()?,
+                to_y.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+    fn door(&self) -> Option<(&str, u16, u16)> {
+        match self {
+            EntityType::Door(to_room, to_x, to_y) => Some((to_room, *to_x, *to_y)),
+            _ => None,
+        }
+    }
+}
+
+pub type Level = engine::level::Level<EntityType>;
+
+// the enemies left behind in a level, for when the players come back through its door
+pub type LevelState = Vec<Entity>;
+
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub alive: bool,
+    pub pos: Vec2,
+    pub dir: f32,
+    pub etype: EntityType,
+}
+
+impl Actor for Entity {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+    fn dir(&self) -> f32 {
+        self.dir
+    }
+    fn place(&mut self, pos: Vec2, dir: f32) {
+        self.pos = pos;
+        self.dir = dir;
+    }
+    fn alive(&self) -> bool {
+        self.alive
+    }
+    fn kill(&mut self) {
+        self.alive = false;
+    }
+}
+
+impl Entity {
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        }
+    }
+    pub fn circle(&self) -> Circle {
+        Circle {
+            x: self.pos.x,
+            y: self.pos.y,
+            // bullets are drawn 4 pixels across
+            r: if self.etype == EntityType::Projectile {
+                2.0
+            } else {
+                TILE_SZ as f32 / 2.0
+            },
+        }
+    }
+    pub fn shape_rect(&self) -> Shape {
+        Shape::Rect(Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        })
+    }
+    pub fn shape_circle(&self) -> Shape {
+        Shape::Circle(self.circle())
+    }
+    // the same box as rect(), turned to match the sprite
+    pub fn oriented_rect(&self) -> OrientedRect {
+        OrientedRect {
+            x: self.pos.x,
+            y: self.pos.y,
+            w: TILE_SZ as f32 - 4.0,
+            h: TILE_SZ as f32 - 4.0,
+            rot: self.dir,
+        }
+    }
+    // What bumps into walls.  Tank hulls turn with the tank; everything else keeps an
+    // upright box.
+    pub fn collider(&self) -> Shape {
+        match self.etype {
+            EntityType::Player1 | EntityType::Player2 => Shape::Oriented(self.oriented_rect()),
+            _ => self.shape_rect(),
+        }
+    }
+}
+
+// Everything the game simulates; main.rs draws it and feeds it input
+pub struct World {
+    pub current_level: usize,
+    pub levels: LevelRegistry<EntityType, LevelState>,
+    // the players, enemies and bullets
+    pub entities: Arena<Entity>,
+    pub components: Components,
+    pub players: [EntityId; 2],
+    // whether each player was already touching a door last frame
+    pub on_door: [bool; 2],
+    pub p1_attack_timer: f32,
+    pub p2_attack_timer: f32,
+    #[allow(dead_code)]
+    pub health: u8,
+    // bullets that ran out of bounces or time during the last step
+    pub despawned: Vec<Despawn>,
+    // how many steps have run
+    pub frame: usize,
+    // every random choice goes through this, so a seed replays the same game
+    pub rng: StdRng,
+    // which keys each player's actions are on
+    pub controls: Controls,
+    // while set, bullets go through the tanks instead of killing them (they still
+    // knock enemies around); `restart` lifts it
+    pub truce: bool,
+    // whether walking onto a door takes the players to another level; a match shuts
+    // them so each round stays in its arena
+    pub doors_open: bool,
+}
+
+// Feel free to change this if you use a different tilesheet
+pub const TILE_SZ: usize = 16;
+
+// pixels per second
+const PLAYER_SPEED: f32 = 64.0;
+const ENEMY_SPEED: f32 = 32.0;
+const PROJECTILE_SPEED: f32 = 60.0;
+const KNOCKBACK_SPEED: f32 = 128.0;
+// radians per second
+const ROTATE_SPEED: f32 = 6.0;
+// how often an enemy picks a new direction, on average, per second
+const ENEMY_TURN_RATE: f64 = 3.0;
+
+// tanks get up to speed and stop again in an eighth of a second, enemies in a quarter
+const PLAYER_MOTION: Motion = Motion {
+    max_speed: PLAYER_SPEED,
+    accel: PLAYER_SPEED * 8.0,
+    friction: PLAYER_SPEED * 8.0,
+};
+const ENEMY_MOTION: Motion = Motion {
+    max_speed: ENEMY_SPEED,
+    accel: ENEMY_SPEED * 4.0,
+    friction: ENEMY_SPEED * 4.0,
+};
+
+const ATTACK_MAX_TIME: f32 = 0.3;
+#[allow(dead_code)]
+const ATTACK_COOLDOWN_TIME: f32 = 0.1;
+const KNOCKBACK_TIME: f32 = 0.25;
+
+// walls a bullet can bounce off before the next one stops it, and how many seconds
+// it lasts if it never gets there
+const PROJECTILE_BOUNCES: u8 = 3;
+const PROJECTILE_LIFETIME: f32 = 5.0;
+
+impl World {
+    // `ids` are the entities the contacts' a sides were gathered from
+    fn do_collision_response(&mut self, ids: &[EntityId], contacts: &mut [Contact2]) {
+        for contact in contacts.iter() {
+            if let Some(entity) = self.entities.get_mut(ids[contact.a_i]) {
+                // an earlier push may already have cleared this tile, so check again
+                // from where the entity is now
+                if let Some(mtv) = entity.collider().mtv(contact.b_r) {
+                    entity.pos += mtv;
+                }
+            }
+        }
+    }
+
+    // bullets only end up in walls if they're fired into one; the bounces from
+    // moving are handled in move_projectiles
+    //todo! Separate projectiles from entities
+    fn projectile_level_response(&mut self, ids: &[EntityId], contacts: &mut [Contact2]) {
+        for contact in contacts.iter() {
+            let id = ids[contact.a_i];
+            if let Some(projectile) = self.entities.get_mut(id) {
+                let Some(pen) = projectile.shape_circle().penetration(contact.b_r) else {
+                    continue;
+                };
+                projectile.pos += pen.mtv();
+                // reflect off the wall, unless an earlier tile already turned it around
+                let vel = dir_to_vec2(projectile.dir);
+                let into = vel.dot(pen.normal);
+                if into < 0.0 && projectile.alive {
+                    if !self.components.spend_bounce(id) {
+                        despawn(
+                            projectile,
+                            id,
+                            DespawnCause::OutOfBounces,
+                            &mut self.despawned,
+                        );
+                        continue;
+                    }
+                    projectile.dir = vec2_to_dir(vel - pen.normal * (2.0 * into));
+                }
+            }
+        }
+    }
+
+    // Moves every bullet `dist` along its heading, bouncing off walls on the way (see
+    // `sweep_bounce`).  One that's out of bounces stops at the wall instead.
+    fn move_projectiles(&mut self, dist: f32) {
+        let level = &self.levels[self.current_level];
+        let projectiles = self
+            .entities
+            .iter_mut()
+            .filter(|(_, entity)| entity.etype == EntityType::Projectile && entity.alive);
+        for (id, projectile) in projectiles {
+            let bounds = projectile.circle().bounds();
+            let moved = sweep_bounce(
+                bounds,
+                projectile.dir,
+                dist,
+                level,
+                |tile| tile.solid,
+                || self.components.spend_bounce(id),
+            );
+            projectile.pos += moved.delta;
+            projectile.dir = moved.dir;
+            if moved.stopped {
+                despawn(
+                    projectile,
+                    id,
+                    DespawnCause::OutOfBounces,
+                    &mut self.despawned,
+                );
+            }
+        }
+    }
+
+    // `projectile_ids` and `ids` are the entities the contacts' a and b sides were
+    // gathered from.  Bullets kill players and knock enemies back the way they were going.
+    fn kill_player(
+        &mut self,
+        projectile_ids: &[EntityId],
+        ids: &[EntityId],
+        player_contacts: &mut [Contact2],
+    ) {
+        for contact in player_contacts.iter_mut() {
+            let id = ids[contact.b_i];
+            if self.components.controller.contains(id) {
+                if !self.truce {
+                    self.entities[id].alive = false;
+                }
+            } else if self.entities[id].etype == EntityType::Enemy {
+                let push = dir_to_vec2(self.entities[projectile_ids[contact.a_i]].dir);
+                self.components
+                    .knock_back(id, push * KNOCKBACK_SPEED, KNOCKBACK_TIME);
+            }
+        }
+    }
+}
+
+// Where `level` puts player 1 and player 2
+fn player_starts(level: &Level) -> (Vec2, Vec2) {
+    let player_start = *level
+        .starts()
+        .iter()
+        .find(|(t, _)| *t == EntityType::Player1)
+        .map(|(_, ploc)| ploc)
+        .expect("Start level doesn't put the player anywhere");
+    // single-player levels only have one start, so both players share it
+    let player2_start = level
+        .starts()
+        .iter()
+        .find(|(t, _)| *t == EntityType::Player2)
+        .map(|(_, ploc)| *ploc)
+        .unwrap_or(player_start);
+    (player_start, player2_start)
+}
+
+impl World {
+    // Starts in level 0 of `levels`, with randomness drawn from `seed`
+    pub fn new(levels: LevelRegistry<EntityType, LevelState>, seed: u64) -> Self {
+        let current_level = 0;
+        let (player_start, player2_start) = player_starts(&levels[current_level]);
+
+        let mut entities = Arena::new();
+        let players = [
+            entities.insert(Entity {
+                alive: true,
+                etype: EntityType::Player1,
+                pos: player_start,
+                dir: 0.0,
+            }),
+            entities.insert(Entity {
+                alive: true,
+                etype: EntityType::Player2,
+                pos: player2_start,
+                dir: 0.0,
+            }),
+        ];
+        let mut components = Components::default();
+        for (p_i, id) in players.iter().enumerate() {
+            components.controller.insert(*id, Controller::new(p_i));
+            components.velocity.insert(*id, Velocity(Vec2::ZERO));
+            components.motion.insert(*id, PLAYER_MOTION);
+        }
+        let mut world = World {
+            current_level,
+            p1_attack_timer: 0.0,
+            p2_attack_timer: 0.0,
+            levels,
+            on_door: [false; 2],
+            health: 3,
+            despawned: Vec::new(),
+            entities,
+            components,
+            players,
+            frame: 0,
+            rng: StdRng::seed_from_u64(seed),
+            controls: Controls::default(),
+            truce: false,
+            doors_open: true,
+        };
+        world.enter_level(player_start, player2_start);
+        world
+    }
+    pub fn level(&self) -> &Level {
+        &self.levels[self.current_level]
+    }
+    // player 0 or 1
+    pub fn player(&self, p_i: usize) -> &Entity {
+        &self.entities[self.players[p_i]]
+    }
+    pub fn player_mut(&mut self, p_i: usize) -> &mut Entity {
+        &mut self.entities[self.players[p_i]]
+    }
+    pub fn enemies(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy)
+    }
+    pub fn projectiles(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Projectile)
+    }
+    // Puts a bullet at `pos` heading `dir`
+    pub fn fire(&mut self, pos: Vec2, dir: f32) -> EntityId {
+        let id = self.entities.insert(Entity {
+            alive: true,
+            pos,
+            dir,
+            etype: EntityType::Projectile,
+        });
+        self.components
+            .bounces
+            .insert(id, Bounces(PROJECTILE_BOUNCES));
+        self.components
+            .lifetime
+            .insert(id, Lifetime(PROJECTILE_LIFETIME));
+        id
+    }
+    fn spawn_enemy(&mut self, enemy: Entity) -> EntityId {
+        let id = self.entities.insert(enemy);
+        self.components.velocity.insert(id, Velocity(Vec2::ZERO));
+        self.components.motion.insert(id, ENEMY_MOTION);
+        id
+    }
+    fn enter_level(&mut self, player_pos: Vec2, player2_pos: Vec2) {
+        self.components.keep_controlled(&mut self.entities);
+        self.player_mut(0).pos = player_pos;
+        self.player_mut(1).pos = player2_pos;
+        // levels we've been to before pick up where we left them
+        if let Some(enemies) = self.levels.take_saved(self.current_level) {
+            for enemy in enemies {
+                self.spawn_enemy(enemy);
+            }
+        } else {
+            let starts = self.levels[self.current_level].starts().to_vec();
+            for (etype, pos) in starts {
+                match etype {
+                    EntityType::Player1 => {}
+                    EntityType::Player2 => {}
+                    EntityType::Door(..) => {}
+                    EntityType::Enemy => {
+                        self.spawn_enemy(Entity {
+                            alive: true,
+                            pos,
+                            dir: 3.0 * FRAC_PI_2,
+                            etype,
+                        });
+                    }
+                    EntityType::Projectile => {}
+                }
+            }
+        }
+        let rects = [self.player(0).rect(), self.player(1).rect()];
+        self.levels
+            .arrive(self.current_level, &rects, &mut self.on_door);
+    }
+    // Starts a fresh round in level `level`: both tanks back in one piece at their
+    // starts, facing the way they started, with the enemies rebuilt from the level's
+    // starts, every bullet gone and any truce over.  Whatever was left in the level
+    // last time, or in any other level, is forgotten.
+    pub fn restart(&mut self, level: usize) {
+        self.current_level = level;
+        self.levels.clear_saved();
+        for id in self.players {
+            let player = &mut self.entities[id];
+            player.alive = true;
+            player.dir = 0.0;
+            self.components.velocity.insert(id, Velocity(Vec2::ZERO));
+            self.components.knockback.remove(id);
+        }
+        self.p1_attack_timer = 0.0;
+        self.p2_attack_timer = 0.0;
+        self.despawned.clear();
+        self.truce = false;
+        let (player_start, player2_start) = player_starts(self.level());
+        self.enter_level(player_start, player2_start);
+    }
+    fn go_through_door(&mut self, to: usize, pos: Vec2) {
+        let enemies = self
+            .entities
+            .remove_where(|_, entity| entity.etype == EntityType::Enemy);
+        self.levels.save(self.current_level, enemies);
+        self.current_level = to;
+        self.enter_level(pos, pos);
+    }
+
+    // How ready player `p_i`'s next shot is, from 0 right after firing to 1
+    pub fn reload(&self, p_i: usize) -> f32 {
+        let timer = [self.p1_attack_timer, self.p2_attack_timer][p_i];
+        1.0 - (timer / ATTACK_MAX_TIME).clamp(0.0, 1.0)
+    }
+
+    pub fn step(&mut self, input: &InputState, dt: f32) {
+        self.frame += 1;
+        self.despawned.clear();
+        self.components.remember_positions(&self.entities);
+        if self.p1_attack_timer > 0.0 {
+            self.p1_attack_timer -= dt;
+        }
+        if self.p2_attack_timer > 0.0 {
+            self.p2_attack_timer -= dt;
+        }
+
+        // a stick turns (and below, drives) as far as it's pushed; keys go all the way.
+        // Wrecks stay put.
+        for p_i in 0..2 {
+            if !self.player(p_i).alive {
+                continue;
+            }
+            let turn = self.controls.turn(input, p_i);
+            self.player_mut(p_i).dir += turn * ROTATE_SPEED * dt;
+        }
+
+        if self.p1_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 0, Action::Fire)
+            && self.player(0).alive
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            // how to put the bullet at the top of the tank so it doesnt kill itself
+            let player = self.player(0);
+            self.fire(player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+
+            self.p1_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        if self.p2_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 1, Action::Fire)
+            && self.player(1).alive
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            let player = self.player(1);
+            self.fire(player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+
+            self.p2_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        for p_i in 0..2 {
+            if !self.player(p_i).alive {
+                continue;
+            }
+            let id = self.players[p_i];
+            let throttle = self.controls.throttle(input, p_i);
+            let player = &mut self.entities[id];
+            let vel = self
+                .components
+                .drive(id, dir_to_vec2(player.dir), throttle, dt);
+            player.pos += vel * dt;
+        }
+
+        let enemies = self
+            .entities
+            .iter_mut()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy);
+        for (id, enemy) in enemies {
+            if self.rng.gen_bool((ENEMY_TURN_RATE * dt as f64).min(1.0)) {
+                enemy.dir = match self.rng.gen_range(0..4) {
+                    0 => PI,
+                    1 => 0.0,
+                    2 => 3.0 * FRAC_PI_2,
+                    3 => FRAC_PI_2,
+                    _ => panic!(),
+                };
+            }
+            let vel = self.components.drive(id, dir_to_vec2(enemy.dir), 1.0, dt);
+            enemy.pos += vel * dt;
+        }
+
+        self.move_projectiles(PROJECTILE_SPEED * dt);
+        for id in self.components.tick_lifetimes(dt) {
+            if let Some(entity) = self.entities.get_mut(id) {
+                despawn(entity, id, DespawnCause::Expired, &mut self.despawned);
+            }
+        }
+
+        //Collision Detection & Response:
+        // (bullets that just despawned are removed below, and can't hit anyone first)
+        let (projectile_ids, projectile_circles): (Vec<EntityId>, Vec<Shape>) = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Projectile && entity.alive)
+            .map(|(id, projectile)| (id, projectile.shape_circle()))
+            .unzip();
+
+        let (ids, colliders): (Vec<EntityId>, Vec<Shape>) = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.etype != EntityType::Projectile && entity.alive)
+            .map(|(id, entity)| (id, entity.collider()))
+            .unzip();
+
+        let mut player_level_contacts: Vec<Contact2> =
+            gather_level_contacts_2(&colliders, self.level());
+
+        let mut projectile_player_contacts: Vec<Contact2> = gather_contacts_2(
+            &projectile_circles,
+            &colliders,
+            self.level().tile_sz() as f32,
+        );
+
+        let mut projectile_level_contacts: Vec<Contact2> =
+            gather_level_contacts_2(&projectile_circles, self.level());
+
+        sort_contacts_2(&mut player_level_contacts);
+
+        self.do_collision_response(&ids, &mut player_level_contacts);
+        self.kill_player(&projectile_ids, &ids, &mut projectile_player_contacts);
+        self.projectile_level_response(&projectile_ids, &mut projectile_level_contacts);
+
+        // the dead don't stick around, except players (who get drawn as wrecks)
+        let controllers = &self.components.controller;
+        self.entities
+            .retain(|id, entity| entity.alive || controllers.contains(id));
+        self.components.retain_alive(&self.entities);
+
+        // a door takes both players along when either one walks onto it
+        let players = [0, 1].map(|p_i| (self.player(p_i).rect(), self.player(p_i).alive));
+        let door = self
+            .levels
+            .door_taken(self.current_level, &players, &mut self.on_door);
+        if let Some((to, pos)) = door.filter(|_| self.doors_open) {
+            self.go_through_door(to, pos);
+        }
+    }
+}
+
+// What `--headless` prints at the end of a run
+impl std::fmt::Display for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} after {} frames", self.level().name(), self.frame)?;
+        for entity in self.entities.values() {
+            writeln!(
+                f,
+                "{:?} {} at ({:.1}, {:.1}) facing {:.2}",
+                entity.etype,
+                if entity.alive { "alive" } else { "dead" },
+                entity.pos.x,
+                entity.pos.y,
+                entity.dir
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::gamepad::{FakePads, PadAxis, PadButton};
+    use engine::input::Key;
+    use engine::replay::Recording;
+    use engine::DT;
+
+    // a walled box with player1 facing the right wall and player2 out of the way
+    const ARENA: &str = "arena 7 5
+====
+. o 594 55 16 16
+# s 419 61 16 16
+====
+#######
+#.....#
+#.....#
+#.....#
+#######
+====
+player1 1 1
+player2 1 3
+";
+
+    fn world() -> World {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(ARENA).unwrap());
+        World::new(levels, 0)
+    }
+
+    fn run(world: &mut World, input: &mut InputState, frames: usize) {
+        for _ in 0..frames {
+            world.step(input, DT);
+            input.next_frame();
+        }
+    }
+
+    #[test]
+    fn test_bullet_bounces_off_wall() {
+        let mut world = world();
+        let mut input = InputState::default();
+        input.press(Key::Space);
+        run(&mut world, &mut input, 1);
+        input.release(Key::Space);
+        assert_eq!(world.projectiles().count(), 1);
+        let bullet = |world: &World| world.projectiles().next().unwrap().1.clone();
+        assert!(dir_to_vec2(bullet(&world).dir).x > 0.0);
+        // the right wall starts at x = 96
+        run(&mut world, &mut input, 60);
+        let bullet = bullet(&world);
+        assert!(bullet.pos.x < 96.0);
+        assert!(dir_to_vec2(bullet.dir).x < 0.0);
+        assert!((bullet.pos.y - world.player(0).pos.y).abs() < 1.0);
+        // and on the way back it hits whoever shot it
+        run(&mut world, &mut input, 60);
+        assert!(!world.player(0).alive);
+        assert!(world.player(1).alive);
+        assert_eq!(world.frame, 121);
+    }
+
+    #[test]
+    fn test_bullets_run_out() {
+        let mut world = world();
+        let mut input = InputState::default();
+        let mut despawned = vec![];
+        let mut run_logged = |world: &mut World, frames| {
+            for _ in 0..frames {
+                run(world, &mut input, 1);
+                despawned.extend(world.despawned.iter().copied());
+            }
+        };
+        // right, left, right, and then the left wall is the one that stops it
+        let bullet = world.fire(Vec2 { x: 40.0, y: 40.0 }, 0.0);
+        run_logged(&mut world, 290);
+        assert!(!world.entities.contains(bullet));
+        // one that never reaches a wall runs out of time instead
+        let short = world.fire(Vec2 { x: 40.0, y: 40.0 }, 0.0);
+        world.components.lifetime.insert(short, Lifetime(0.5));
+        run_logged(&mut world, 32);
+        assert!(world.projectiles().next().is_none());
+        let causes: Vec<_> = despawned.iter().map(|d| (d.id, d.cause)).collect();
+        assert_eq!(
+            causes,
+            vec![
+                (bullet, DespawnCause::OutOfBounces),
+                (short, DespawnCause::Expired)
+            ]
+        );
+        assert!(despawned[0].pos.x < 24.0);
+        assert!(despawned[1].pos.x > 60.0);
+    }
+
+    #[test]
+    fn test_rebound_fire_key() {
+        let mut world = world();
+        let mut input = InputState::default();
+        world.controls.bind(0, Action::Fire, Key::Enter);
+        input.press(Key::Space);
+        run(&mut world, &mut input, 1);
+        assert_eq!(world.projectiles().count(), 0);
+        input.press(Key::Enter);
+        run(&mut world, &mut input, 1);
+        assert_eq!(world.projectiles().count(), 1);
+        // player 1 just fired, so their reload bar's empty and player 2's is full
+        assert!(world.reload(0) < 0.1);
+        assert_eq!(world.reload(1), 1.0);
+        // after swapping, Q fires from player 1's tank
+        world.controls.swap(0, 1);
+        world.p1_attack_timer = 0.0;
+        input.press(Key::KeyQ);
+        run(&mut world, &mut input, 1);
+        let (_, newest) = world.projectiles().last().unwrap();
+        assert!((newest.pos.y - world.player(0).pos.y).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_gamepad_steers_player_two() {
+        let mut world = world();
+        let mut input = InputState::default();
+        let mut pads = FakePads::new();
+        // the first pad plugged in is player 1's, the second player 2's
+        pads.connect(4);
+        pads.connect(5);
+        pads.tilt(5, PadAxis::LeftStickX, -0.5);
+        input.poll_pads(&mut pads);
+        run(&mut world, &mut input, 10);
+        // half a stick turns half as fast as a key
+        assert!((world.player(1).dir - 10.0 * DT * 0.5 * ROTATE_SPEED).abs() < 1e-4);
+        assert_eq!(world.player(0).dir, 0.0);
+
+        pads.press(5, PadButton::South);
+        input.poll_pads(&mut pads);
+        run(&mut world, &mut input, 1);
+        assert_eq!(world.projectiles().count(), 1);
+
+        // unplugged, it stops turning
+        pads.disconnect(5);
+        input.poll_pads(&mut pads);
+        let dir = world.player(1).dir;
+        run(&mut world, &mut input, 10);
+        assert_eq!(world.player(1).dir, dir);
+    }
+
+    #[test]
+    fn test_speed_doesnt_depend_on_dt() {
+        // holding forward for half a second goes (about) as far in 30 big steps as in
+        // 120 small ones
+        let drive = |steps: usize| {
+            let mut world = world();
+            let mut input = InputState::default();
+            input.press(Key::ArrowUp);
+            for _ in 0..steps {
+                world.step(&input, 0.5 / steps as f32);
+                input.next_frame();
+            }
+            world.player(0).pos.x
+        };
+        let start = world().player(0).pos.x;
+        let (coarse, fine) = (drive(30), drive(120));
+        assert!((coarse - fine).abs() < 0.5);
+        // an eighth of a second getting up to speed, then full speed
+        let expected = PLAYER_SPEED * (0.5 - 0.125 / 2.0);
+        assert!((fine - start - expected).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_drawn_between_steps() {
+        let mut world = world();
+        let mut input = InputState::default();
+        input.press(Key::ArrowUp);
+        run(&mut world, &mut input, 20);
+        let before = world.player(0).pos;
+        run(&mut world, &mut input, 1);
+        let after = world.player(0).pos;
+        let id = world.players[0];
+        let drawn = |world: &World, id, alpha| {
+            let entity = world.components.interpolated(&world.entities, id, alpha);
+            entity.pos
+        };
+        assert_eq!(drawn(&world, id, 0.0), before);
+        assert_eq!(drawn(&world, id, 1.0), after);
+        let halfway = drawn(&world, id, 0.5);
+        assert!((halfway.x - (before.x + after.x) / 2.0).abs() < 1e-4);
+        // a bullet fired this step is drawn where it is
+        let bullet = world.fire(after, 0.0);
+        assert_eq!(drawn(&world, bullet, 0.5), after);
+    }
+
+    #[test]
+    fn test_bullets_knock_enemies_back() {
+        let mut world = world();
+        let enemy = world.spawn_enemy(Entity {
+            alive: true,
+            etype: EntityType::Enemy,
+            pos: Vec2 { x: 56.0, y: 40.0 },
+            dir: 0.0,
+        });
+        // so it doesn't wander off on its own
+        world.components.motion.remove(enemy);
+        world.fire(Vec2 { x: 48.0, y: 40.0 }, 0.0);
+        run(&mut world, &mut InputState::default(), 1);
+        assert_eq!(
+            world.components.velocity.get(enemy),
+            Some(&Velocity(Vec2 {
+                x: KNOCKBACK_SPEED,
+                y: 0.0
+            }))
+        );
+        // it flies back until the knockback wears off
+        run(&mut world, &mut InputState::default(), 20);
+        assert!(world.entities[enemy].pos.x > 56.0 + KNOCKBACK_SPEED * 0.25);
+        assert!(!world.components.knockback.contains(enemy));
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let play = |seed| {
+            let mut levels = LevelRegistry::new();
+            levels.add(Level::parse(include_str!("../../engine/content/level3.txt")).unwrap());
+            let mut world = World::new(levels, seed);
+            run(&mut world, &mut InputState::default(), 300);
+            world.to_string()
+        };
+        assert_eq!(play(7), play(7));
+        // with 3 enemies turning at random for 5 seconds, another seed should go elsewhere
+        assert_ne!(play(7), play(8));
+    }
+
+    #[test]
+    fn test_replay_matches_recording() {
+        let level = || {
+            let mut levels = LevelRegistry::new();
+            levels.add(Level::parse(include_str!("../../engine/content/level3.txt")).unwrap());
+            levels
+        };
+        let mut world = World::new(level(), 7);
+        let mut recording = Recording::new("level3", 7, &world.controls.keys());
+        let mut input = InputState::default();
+        for tick in 0..240 {
+            match tick {
+                10 => input.press(Key::KeyW),
+                50 => input.press(Key::Space),
+                51 => input.release(Key::Space),
+                90 => input.press(Key::ArrowLeft),
+                130 => input.release(Key::KeyW),
+                _ => {}
+            }
+            recording.record(&input);
+            world.step(&input, DT);
+            input.next_frame();
+        }
+        let text = recording.to_string();
+        let mut playback = Recording::parse(&text, &world.controls.keys())
+            .unwrap()
+            .playback();
+        let mut replayed = World::new(level(), playback.seed());
+        while let Some(input) = playback.next_input() {
+            replayed.step(input, DT);
+        }
+        assert_eq!(replayed.to_string(), world.to_string());
+    }
+
+    #[test]
+    fn test_turned_tank_stays_out_of_walls() {
+        let mut world = world();
+        // player 1 starts in the corner; at 45 degrees its hull pokes into both walls
+        world.player_mut(0).dir = std::f32::consts::FRAC_PI_4;
+        let hull = world.player(0).oriented_rect();
+        let in_wall = |world: &World, hull: OrientedRect| {
+            world
+                .level()
+                .tiles_within(hull.bounds())
+                .filter(|(_, tile)| tile.solid)
+                .filter_map(|(rect, _)| hull.mtv(rect.into()))
+                .any(|mtv| mtv.mag_sq() > 1e-3)
+        };
+        assert!(in_wall(&world, hull));
+        run(&mut world, &mut InputState::default(), 1);
+        assert!(!in_wall(&world, world.player(0).oriented_rect()));
+        // the upright box would have said there was nothing to fix
+        assert!(world.player(0).pos.x > 24.4);
+    }
+
+    #[test]
+    fn test_fast_bullet_bounces_off_thin_wall() {
+        let mut levels = LevelRegistry::new();
+        levels.add(
+            Level::parse(
+                "thin 7 5
+====
+. o 594 55 16 16
+# s 419 61 16 16
+====
+#######
+#..#..#
+#..#..#
+#..#..#
+#######
+====
+player1 1 1
+",
+            )
+            .unwrap(),
+        );
+        let mut world = World::new(levels, 0);
+        world.fire(Vec2 { x: 24.0, y: 40.0 }, 0.0);
+        // the wall is x = 48 to 64, so 40 pixels in one step would skip past its near
+        // face; instead the bullet's edge hits it 22 pixels along (with its center at
+        // x = 46) and it comes back the remaining 18
+        world.move_projectiles(40.0);
+        let (_, bullet) = world.projectiles().next().unwrap();
+        assert!((bullet.pos.x - 28.0).abs() < 1e-3);
+        assert_eq!(bullet.pos.y, 40.0);
+        assert!(dir_to_vec2(bullet.dir).x < 0.0);
+
+        // one that starts the step already a pixel into the wall bounces straight back
+        // rather than carrying on out the far side
+        let id = world.projectiles().next().unwrap().0;
+        world.entities.remove(id);
+        world.fire(Vec2 { x: 47.0, y: 40.0 }, 0.0);
+        world.move_projectiles(20.0);
+        let (_, bullet) = world.projectiles().next().unwrap();
+        assert!((bullet.pos.x - 27.0).abs() < 1e-3);
+        assert!(dir_to_vec2(bullet.dir).x < 0.0);
+    }
+
+    #[test]
+    fn test_wrecks_stay_put() {
+        let mut world = world();
+        let mut input = InputState::default();
+        world.player_mut(0).alive = false;
+        let (pos, dir) = (world.player(0).pos, world.player(0).dir);
+        input.press(Key::ArrowUp);
+        input.press(Key::ArrowLeft);
+        run(&mut world, &mut input, 30);
+        assert_eq!(world.player(0).pos, pos);
+        assert_eq!(world.player(0).dir, dir);
+    }
+
+    #[test]
+    fn test_enemies_head_along_the_grid() {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(include_str!("../../engine/content/level3.txt")).unwrap());
+        let mut world = World::new(levels, 0);
+        let close = |a: Vec2, b: Vec2| (a - b).mag_sq() < 1e-6;
+        // they start off facing straight along -y
+        for (_, enemy) in world.enemies() {
+            assert!(close(dir_to_vec2(enemy.dir), Vec2 { x: 0.0, y: -1.0 }));
+        }
+        // and only ever turn to face one of the four directions
+        let axes = [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)].map(|(x, y)| Vec2 { x, y });
+        for _ in 0..120 {
+            run(&mut world, &mut InputState::default(), 1);
+            for (_, enemy) in world.enemies() {
+                let heading = dir_to_vec2(enemy.dir);
+                assert!(axes.iter().any(|axis| close(heading, *axis)), "{heading:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_dead_enemies_are_reclaimed() {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(include_str!("../../engine/content/level3.txt")).unwrap());
+        let mut world = World::new(levels, 0);
+        assert_eq!(world.enemies().count(), 3);
+        let players = world.players;
+        let enemy = world
+            .entities
+            .ids()
+            .find(|id| !players.contains(id))
+            .unwrap();
+        world.entities[enemy].alive = false;
+        run(&mut world, &mut InputState::default(), 1);
+        assert_eq!(world.enemies().count(), 2);
+        assert!(world.entities.get(enemy).is_none());
+        // the players' handles still find them
+        assert_eq!(world.player(0).etype, EntityType::Player1);
+        assert_eq!(world.player(1).etype, EntityType::Player2);
+    }
+
+    #[test]
+    fn test_restart_level() {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(include_str!("../../engine/content/level3.txt")).unwrap());
+        let mut world = World::new(levels, 0);
+        let starts = [world.player(0).pos, world.player(1).pos];
+        let mut input = InputState::default();
+        input.press(Key::Space);
+        input.press(Key::ArrowUp);
+        run(&mut world, &mut input, 30);
+        world.player_mut(1).alive = false;
+        let enemy = world.enemies().next().unwrap().0;
+        world.entities[enemy].alive = false;
+        run(&mut world, &mut input, 1);
+        assert_eq!(world.projectiles().count(), 1);
+        assert_eq!(world.enemies().count(), 2);
+
+        world.restart(0);
+        assert_eq!(world.projectiles().count(), 0);
+        assert_eq!(world.enemies().count(), 3);
+        for (p_i, start) in starts.into_iter().enumerate() {
+            assert!(world.player(p_i).alive);
+            assert_eq!(world.player(p_i).pos, start);
+            assert_eq!(world.player(p_i).dir, 0.0);
+        }
+        assert_eq!(world.reload(0), 1.0);
+    }
+
+    #[test]
+    fn test_restart_forgets_every_level() {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(include_str!("../../engine/content/level1.txt")).unwrap());
+        levels.add(Level::parse(include_str!("../../engine/content/level6.txt")).unwrap());
+        let mut world = World::new(levels, 0);
+        let door = world
+            .level()
+            .starts()
+            .iter()
+            .find(|(etype, _)| matches!(etype, EntityType::Door(..)))
+            .map(|(_, pos)| *pos)
+            .unwrap();
+        // thin out level6's enemies and come back, so it has something saved
+        world.go_through_door(1, door);
+        let enemy = world.enemies().next().unwrap().0;
+        world.entities.remove(enemy);
+        world.go_through_door(0, door);
+        world.restart(0);
+        world.go_through_door(1, door);
+        assert_eq!(world.enemies().count(), 4);
+
+        // with the doors shut, walking onto one goes nowhere
+        world.restart(0);
+        world.doors_open = false;
+        world.player_mut(0).pos = door;
+        run(&mut world, &mut InputState::default(), 1);
+        assert_eq!(world.level().name(), "level1");
+        world.doors_open = true;
+        world.on_door = [false; 2];
+        world.player_mut(0).pos = door;
+        run(&mut world, &mut InputState::default(), 1);
+        assert_eq!(world.level().name(), "level6");
+    }
+}
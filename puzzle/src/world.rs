@@ -0,0 +1,621 @@
+use engine::arena::{Arena, EntityId};
+use engine::collision::*;
+use engine::controls::{Action, Controls};
+use engine::ecs::{
+    despawn, Actor, Bounces, Components, Controller, Despawn, DespawnCause, Lifetime, Motion,
+    Velocity,
+};
+use engine::geom::*;
+use engine::input::InputState;
+use engine::level::StartKind;
+use engine::registry::LevelRegistry;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityType {
+    Player,
+    Enemy,
+    Projectile,
+    // which level, grid x in dest level, grid y in dest level
+    Door(String, u16, u16),
+}
+
+impl StartKind for EntityType {
+    fn from_start(kind: &str, args: &[&str]) -> Option<Self> {
+        match (kind, args) {
+            ("player" | "player1" | "player2", []) => Some(EntityType::Player),
+            ("enemy", []) => Some(EntityType::Enemy),
+            ("door", [to_room, to_x, to_y]) => Some(EntityType::Door(
+                to_room.to_string(),
+                to_x.parse().ok()?,
+                to_y.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+    fn door(&self) -> Option<(&str, u16, u16)> {
+        match self {
+            EntityType::Door(to_room, to_x, to_y) => Some((to_room, *to_x, *to_y)),
+            _ => None,
+        }
+    }
+}
+
+pub type Level = engine::level::Level<EntityType>;
+
+// the enemies left behind in a level, for when the players come back through its door
+pub type LevelState = Vec<Entity>;
+
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub alive: bool,
+    pub pos: Vec2,
+    pub dir: f32,
+    pub etype: EntityType,
+}
+
+impl Actor for Entity {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+    fn dir(&self) -> f32 {
+        self.dir
+    }
+    fn place(&mut self, pos: Vec2, dir: f32) {
+        self.pos = pos;
+        self.dir = dir;
+    }
+    fn alive(&self) -> bool {
+        self.alive
+    }
+    fn kill(&mut self) {
+        self.alive = false;
+    }
+}
+
+impl Entity {
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        }
+    }
+    #[allow(dead_code)]
+    pub fn circle(&self) -> Circle {
+        Circle {
+            x: self.pos.x,
+            y: self.pos.y,
+            r: TILE_SZ as f32 / 2.0,
+        }
+    }
+    #[allow(dead_code)]
+    pub fn shape_rect(&self) -> Shape {
+        Shape::Rect(Rect {
+            x: self.pos.x - TILE_SZ as f32 / 2.0 + 2.0,
+            y: self.pos.y - TILE_SZ as f32 / 2.0 + 2.0,
+            w: TILE_SZ as u16 - 4,
+            h: TILE_SZ as u16 - 4,
+        })
+    }
+    #[allow(dead_code)]
+    pub fn shape_circle(&self) -> Shape {
+        Shape::Circle(Circle {
+            x: self.pos.x,
+            y: self.pos.y,
+            r: TILE_SZ as f32 / 2.0,
+        })
+    }
+}
+
+// Everything the game simulates; main.rs draws it and feeds it input
+pub struct World {
+    pub current_level: usize,
+    pub levels: LevelRegistry<EntityType, LevelState>,
+    // the players, enemies and bullets
+    pub entities: Arena<Entity>,
+    pub components: Components,
+    pub players: [EntityId; 2],
+    // whether each player was already touching a door last frame
+    pub on_door: [bool; 2],
+    pub p1_attack_timer: f32,
+    pub p2_attack_timer: f32,
+    #[allow(dead_code)]
+    pub health: u8,
+    // bullets that ran out of bounces or time during the last step
+    pub despawned: Vec<Despawn>,
+    // how many steps have run
+    pub frame: usize,
+    // every random choice goes through this, so a seed replays the same game
+    pub rng: StdRng,
+    // which keys each player's actions are on
+    pub controls: Controls,
+}
+
+// Feel free to change this if you use a different tilesheet
+pub const TILE_SZ: usize = 16;
+
+// pixels per second
+const PLAYER_SPEED: f32 = 64.0;
+const ENEMY_SPEED: f32 = 32.0;
+const PROJECTILE_SPEED: f32 = 60.0;
+// nothing gets hit hard enough for these yet
+#[allow(dead_code)]
+const KNOCKBACK_SPEED: f32 = 128.0;
+// radians per second
+const ROTATE_SPEED: f32 = 6.0;
+// how often an enemy picks a new direction, on average, per second
+const ENEMY_TURN_RATE: f64 = 3.0;
+
+// tanks get up to speed and stop again in an eighth of a second, enemies in a quarter
+const PLAYER_MOTION: Motion = Motion {
+    max_speed: PLAYER_SPEED,
+    accel: PLAYER_SPEED * 8.0,
+    friction: PLAYER_SPEED * 8.0,
+};
+const ENEMY_MOTION: Motion = Motion {
+    max_speed: ENEMY_SPEED,
+    accel: ENEMY_SPEED * 4.0,
+    friction: ENEMY_SPEED * 4.0,
+};
+
+const ATTACK_MAX_TIME: f32 = 0.3;
+#[allow(dead_code)]
+const ATTACK_COOLDOWN_TIME: f32 = 0.1;
+#[allow(dead_code)]
+const KNOCKBACK_TIME: f32 = 0.25;
+
+// walls a bullet can bounce off before the next one stops it, and how many seconds
+// it lasts if it never gets there
+const PROJECTILE_BOUNCES: u8 = 3;
+const PROJECTILE_LIFETIME: f32 = 5.0;
+
+impl World {
+    // `ids` are the entities the contacts were gathered from
+    fn do_collision_response(&mut self, ids: &[EntityId], contacts: &mut [Contact]) {
+        for contact in contacts.iter_mut() {
+            if contact.displacement.x < contact.displacement.y {
+                contact.displacement.y = 0.0;
+            } else {
+                contact.displacement.x = 0.0;
+            }
+
+            let b_pos = contact.b_r.rect_to_pos();
+
+            if let Some(entity) = self.entities.get_mut(ids[contact.a_i]) {
+                if entity.pos.x < b_pos.x {
+                    contact.displacement.x *= -1.0;
+                }
+                if entity.pos.y < b_pos.y {
+                    contact.displacement.y *= -1.0;
+                }
+
+                entity.pos += contact.displacement;
+            }
+        }
+    }
+
+    // on ice the controls stop working and you slide until you're off it
+    fn slippery_response(&mut self, ids: &[EntityId], player_contacts: &[Contact]) {
+        for contact in player_contacts.iter() {
+            if let Some(controller) = self.components.controller.get_mut(ids[contact.a_i]) {
+                controller.locked = true;
+            }
+        }
+    }
+
+    fn nonslippery_response(&mut self, ids: &[EntityId], player_contacts: &[Contact]) {
+        for contact in player_contacts.iter() {
+            if let Some(controller) = self.components.controller.get_mut(ids[contact.a_i]) {
+                controller.locked = false;
+            }
+        }
+    }
+}
+
+impl World {
+    // Starts in level 0 of `levels`, with randomness drawn from `seed`
+    pub fn new(levels: LevelRegistry<EntityType, LevelState>, seed: u64) -> Self {
+        let current_level = 0;
+        let player_start = *levels[current_level]
+            .starts()
+            .iter()
+            .find(|(t, _)| *t == EntityType::Player)
+            .map(|(_, ploc)| ploc)
+            .expect("Start level doesn't put the player anywhere");
+        let player2_start = *levels[current_level]
+            .starts()
+            .iter()
+            .find(|(t, _)| *t == EntityType::Player)
+            .map(|(_, ploc)| ploc)
+            .expect("Start level doesn't put the player anywhere");
+
+        let mut entities = Arena::new();
+        let players = [
+            entities.insert(Entity {
+                alive: true,
+                etype: EntityType::Player,
+                pos: player_start,
+                dir: 0.0,
+            }),
+            entities.insert(Entity {
+                alive: true,
+                etype: EntityType::Player,
+                pos: player2_start,
+                dir: 0.0,
+            }),
+        ];
+        let mut components = Components::default();
+        for (p_i, id) in players.iter().enumerate() {
+            components.controller.insert(*id, Controller::new(p_i));
+            components.velocity.insert(*id, Velocity(Vec2::ZERO));
+            components.motion.insert(*id, PLAYER_MOTION);
+        }
+        let mut world = World {
+            current_level,
+            p1_attack_timer: 0.0,
+            p2_attack_timer: 0.0,
+            levels,
+            on_door: [false; 2],
+            health: 3,
+            despawned: Vec::new(),
+            entities,
+            components,
+            players,
+            frame: 0,
+            rng: StdRng::seed_from_u64(seed),
+            controls: Controls::default(),
+        };
+        world.enter_level(player_start, player2_start);
+        world
+    }
+    pub fn level(&self) -> &Level {
+        &self.levels[self.current_level]
+    }
+    // player 0 or 1
+    pub fn player(&self, p_i: usize) -> &Entity {
+        &self.entities[self.players[p_i]]
+    }
+    pub fn player_mut(&mut self, p_i: usize) -> &mut Entity {
+        &mut self.entities[self.players[p_i]]
+    }
+    // whether player 0 or 1 is sliding on ice
+    pub fn sliding(&self, p_i: usize) -> bool {
+        let controller = self.components.controller.get(self.players[p_i]);
+        controller.is_some_and(|controller| controller.locked)
+    }
+    pub fn enemies(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy)
+    }
+    pub fn projectiles(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .filter(|(_, entity)| entity.etype == EntityType::Projectile)
+    }
+    // Puts a bullet at `pos` heading `dir`
+    pub fn fire(&mut self, pos: Vec2, dir: f32) -> EntityId {
+        let id = self.entities.insert(Entity {
+            alive: true,
+            pos,
+            dir,
+            etype: EntityType::Projectile,
+        });
+        self.components
+            .bounces
+            .insert(id, Bounces(PROJECTILE_BOUNCES));
+        self.components
+            .lifetime
+            .insert(id, Lifetime(PROJECTILE_LIFETIME));
+        id
+    }
+    fn spawn_enemy(&mut self, enemy: Entity) -> EntityId {
+        let id = self.entities.insert(enemy);
+        self.components.velocity.insert(id, Velocity(Vec2::ZERO));
+        self.components.motion.insert(id, ENEMY_MOTION);
+        id
+    }
+    fn enter_level(&mut self, player_pos: Vec2, player2_pos: Vec2) {
+        self.components.keep_controlled(&mut self.entities);
+        self.player_mut(0).pos = player_pos;
+        self.player_mut(1).pos = player2_pos;
+        // levels we've been to before pick up where we left them
+        if let Some(enemies) = self.levels.take_saved(self.current_level) {
+            for enemy in enemies {
+                self.spawn_enemy(enemy);
+            }
+        } else {
+            let starts = self.levels[self.current_level].starts().to_vec();
+            for (etype, pos) in starts {
+                match etype {
+                    EntityType::Player => {}
+                    EntityType::Door(..) => {}
+                    EntityType::Enemy => {
+                        self.spawn_enemy(Entity {
+                            alive: true,
+                            pos,
+                            dir: 3.0 * FRAC_PI_2,
+                            etype,
+                        });
+                    }
+                    EntityType::Projectile => {}
+                }
+            }
+        }
+        let rects = [self.player(0).rect(), self.player(1).rect()];
+        self.levels
+            .arrive(self.current_level, &rects, &mut self.on_door);
+    }
+    fn go_through_door(&mut self, to: usize, pos: Vec2) {
+        let enemies = self
+            .entities
+            .remove_where(|_, entity| entity.etype == EntityType::Enemy);
+        self.levels.save(self.current_level, enemies);
+        self.current_level = to;
+        self.enter_level(pos, pos);
+    }
+
+    // How ready player `p_i`'s next shot is, from 0 right after firing to 1
+    pub fn reload(&self, p_i: usize) -> f32 {
+        let timer = [self.p1_attack_timer, self.p2_attack_timer][p_i];
+        1.0 - (timer / ATTACK_MAX_TIME).clamp(0.0, 1.0)
+    }
+
+    pub fn step(&mut self, input: &InputState, dt: f32) {
+        self.frame += 1;
+        self.despawned.clear();
+        self.components.remember_positions(&self.entities);
+        if self.p1_attack_timer > 0.0 {
+            self.p1_attack_timer -= dt;
+        }
+        if self.p2_attack_timer > 0.0 {
+            self.p2_attack_timer -= dt;
+        }
+
+        let mut d_angle: f32 = 0.0;
+        let mut d_angle2: f32 = 0.0;
+
+        if self.controls.is_down(input, 0, Action::TurnLeft) {
+            d_angle += ROTATE_SPEED;
+        } else if self.controls.is_down(input, 0, Action::TurnRight) {
+            d_angle -= ROTATE_SPEED;
+        }
+
+        if self.controls.is_down(input, 1, Action::TurnLeft) {
+            d_angle2 += ROTATE_SPEED;
+        } else if self.controls.is_down(input, 1, Action::TurnRight) {
+            d_angle2 -= ROTATE_SPEED;
+        }
+
+        self.player_mut(0).dir += d_angle * dt;
+        self.player_mut(1).dir += d_angle2 * dt;
+
+        if self.p1_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 0, Action::Fire)
+            && self.player(0).alive
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            // how to put the bullet at the top of the tank so it doesnt kill itself
+            let player = self.player(0);
+            self.fire(player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+
+            self.p1_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        if self.p2_attack_timer <= 0.0
+            && self.controls.is_pressed(input, 1, Action::Fire)
+            && self.player(1).alive
+        {
+            // TODO POINT: compute the attack area's center based on the player's position and facing and some offset
+            // For the spritesheet provided, the attack is placed 8px "forwards" from the player.
+            let player = self.player(1);
+            self.fire(player.pos + dir_to_vec2(player.dir) * 15.0, player.dir);
+
+            self.p2_attack_timer = ATTACK_MAX_TIME;
+        }
+
+        let mut throttle: f32 = 0.0;
+        let mut throttle2: f32 = 0.0;
+
+        if !self.sliding(0) {
+            if self.controls.is_down(input, 0, Action::Forward) {
+                throttle = 1.0;
+            } else if self.controls.is_down(input, 0, Action::Back) {
+                throttle = -1.0;
+            }
+        } else {
+            throttle = 1.0;
+        }
+
+        if !self.sliding(1) {
+            if self.controls.is_down(input, 1, Action::Forward) {
+                throttle2 = 1.0;
+            } else if self.controls.is_down(input, 1, Action::Back) {
+                throttle2 = -1.0;
+            }
+        } else {
+            throttle2 = 1.0;
+        }
+
+        for (p_i, throttle) in [throttle, throttle2].into_iter().enumerate() {
+            let id = self.players[p_i];
+            let player = &mut self.entities[id];
+            let vel = self
+                .components
+                .drive(id, dir_to_vec2(player.dir), throttle, dt);
+            player.pos += vel * dt;
+        }
+
+        let enemies = self
+            .entities
+            .iter_mut()
+            .filter(|(_, entity)| entity.etype == EntityType::Enemy);
+        for (id, enemy) in enemies {
+            if self.rng.gen_bool((ENEMY_TURN_RATE * dt as f64).min(1.0)) {
+                enemy.dir = match self.rng.gen_range(0..4) {
+                    0 => PI,
+                    1 => 0.0,
+                    2 => 3.0 * FRAC_PI_2,
+                    3 => FRAC_PI_2,
+                    _ => panic!(),
+                };
+            }
+            let vel = self.components.drive(id, dir_to_vec2(enemy.dir), 1.0, dt);
+            enemy.pos += vel * dt;
+        }
+
+        // bullets bounce off the walls they'd hit on the way (and off ones they were
+        // fired into), so that's all the wall response they need
+        let level = &self.levels[self.current_level];
+        let projectiles = self
+            .entities
+            .iter_mut()
+            .filter(|(_, entity)| entity.etype == EntityType::Projectile && entity.alive);
+        for (id, projectile) in projectiles {
+            let moved = sweep_bounce(
+                projectile.rect(),
+                projectile.dir,
+                PROJECTILE_SPEED * dt,
+                level,
+                |tile| tile.solid,
+                || self.components.spend_bounce(id),
+            );
+            projectile.pos += moved.delta;
+            projectile.dir = moved.dir;
+            if moved.stopped {
+                despawn(
+                    projectile,
+                    id,
+                    DespawnCause::OutOfBounces,
+                    &mut self.despawned,
+                );
+            }
+        }
+        for id in self.components.tick_lifetimes(dt) {
+            if let Some(entity) = self.entities.get_mut(id) {
+                despawn(entity, id, DespawnCause::Expired, &mut self.despawned);
+            }
+        }
+
+        //Collision Detection & Response:
+        let (ids, player_rects): (Vec<EntityId>, Vec<Rect>) = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.etype != EntityType::Projectile)
+            .map(|(id, entity)| (id, entity.rect()))
+            .unzip();
+
+        let mut player_level_contacts: Vec<Contact> =
+            gather_level_contacts(&player_rects, self.level());
+
+        sort_contacts(&mut player_level_contacts);
+
+        self.do_collision_response(&ids, &mut player_level_contacts);
+
+        let player_nonslippery_contacts: Vec<Contact> =
+            gather_level_contacts_where(&player_rects, self.level(), |tile| {
+                !tile.solid && !tile.slippery
+            });
+        self.nonslippery_response(&ids, &player_nonslippery_contacts);
+
+        let player_slippery_contacts: Vec<Contact> =
+            gather_level_contacts_where(&player_rects, self.level(), |tile| tile.slippery);
+        self.slippery_response(&ids, &player_slippery_contacts);
+
+        // the dead don't stick around, except players
+        let controllers = &self.components.controller;
+        self.entities
+            .retain(|id, entity| entity.alive || controllers.contains(id));
+        self.components.retain_alive(&self.entities);
+
+        // a door takes both players along when either one walks onto it
+        let players = [0, 1].map(|p_i| (self.player(p_i).rect(), self.player(p_i).alive));
+        let door = self
+            .levels
+            .door_taken(self.current_level, &players, &mut self.on_door);
+        if let Some((to, pos)) = door {
+            self.go_through_door(to, pos);
+        }
+    }
+}
+
+// What `--headless` prints at the end of a run
+impl std::fmt::Display for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} after {} frames", self.level().name(), self.frame)?;
+        for (id, entity) in self.entities.iter() {
+            let controller = self.components.controller.get(id);
+            writeln!(
+                f,
+                "{:?} {}{} at ({:.1}, {:.1}) facing {:.2}",
+                entity.etype,
+                if entity.alive { "alive" } else { "dead" },
+                if controller.is_some_and(|controller| controller.locked) {
+                    " sliding"
+                } else {
+                    ""
+                },
+                entity.pos.x,
+                entity.pos.y,
+                entity.dir
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::input::Key;
+    use engine::DT;
+
+    // a corridor with two slippery tiles in the middle
+    const ICE: &str = "ice 8 3
+====
+. o n 594 55 16 16
+# s n 419 61 16 16
+& o s 336 75 16 16
+====
+########
+#..&&..#
+########
+====
+player 1 1
+";
+
+    fn run(world: &mut World, input: &mut InputState, frames: usize) {
+        for _ in 0..frames {
+            world.step(input, DT);
+            input.next_frame();
+        }
+    }
+
+    #[test]
+    fn test_slide_across_ice() {
+        let mut levels = LevelRegistry::new();
+        levels.add(Level::parse(ICE).unwrap());
+        let mut world = World::new(levels, 0);
+        let mut input = InputState::default();
+        // walk right until we're partway onto the ice (which starts at x = 48)
+        input.press(Key::ArrowUp);
+        run(&mut world, &mut input, 25);
+        input.release(Key::ArrowUp);
+        assert!(world.sliding(0));
+        // letting go doesn't stop us until we're all the way off it (it ends at x = 80)
+        run(&mut world, &mut input, 60);
+        assert!(!world.sliding(0));
+        let player = &world.player(0);
+        assert!(player.rect().x > 80.0);
+        let stopped_at = player.pos;
+        run(&mut world, &mut input, 30);
+        assert_eq!(world.player(0).pos, stopped_at);
+        // player 2 never touched the keys
+        assert_eq!(world.player(1).pos, Vec2 { x: 24.0, y: 24.0 });
+    }
+}